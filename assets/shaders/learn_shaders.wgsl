@@ -1,5 +1,6 @@
 #import bevy_pbr::mesh_view_bindings
 #import bevy_pbr::mesh_bindings
+#import bevy_pbr::mesh_functions
 #import bevy_pbr::pbr_types
 
 #import bevy_pbr::utils
@@ -8,10 +9,34 @@
 #import bevy_pbr::shadows
 #import bevy_pbr::pbr_functions
 
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    // The channels of the noise graph besides the height, in the order of their names.
+    @location(5) channels: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    #import bevy_pbr::mesh_vertex_output
+    @location(5) channels: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    out.world_normal = mesh_normal_local_to_world(vertex.normal);
+    out.world_position = mesh_position_local_to_world(mesh.model, vec4<f32>(vertex.position, 1.0));
+    out.clip_position = mesh_position_world_to_clip(out.world_position);
+    out.channels = vertex.channels;
+    return out;
+}
+
 struct FragmentInput {
     @builtin(front_facing) is_front: bool,
     @builtin(position) frag_coord: vec4<f32>,
     #import bevy_pbr::mesh_vertex_output
+    @location(5) channels: vec4<f32>,
 };
 
 struct GradientPoint {
@@ -52,8 +52,8 @@ fn insert_assets_as_resources(
     mut json_assets: ResMut<Assets<ValueWrapper>>,
 ) {
     let noise_graph = json_assets.remove(assets.noise_graph.clone()).unwrap();
-    let noise_graph: NoiseGraphResource =
-        serde_json::from_value(noise_graph.0).expect("Failed to parse noise graph");
+    let noise_graph =
+        NoiseGraphResource::from_json(noise_graph.0).expect("Failed to load noise graph");
     let chunks_config = json_assets.remove(assets.chunks_config.clone()).unwrap();
     let chunks_config: ChunksConfig =
         serde_json::from_value(chunks_config.0).expect("Failed to parse chunks config");
@@ -24,8 +24,11 @@ impl Plugin for ChunkGridPlugin {
     }
 }
 
+/// The camera before it got its grid coordinates.
+type NewCamera = (With<CameraController>, Without<GridCoordinates>);
+
 fn add_grid_coordinates_to_camera(
-    query: Query<(Entity, &Transform), (With<CameraController>, Without<GridCoordinates>)>,
+    query: Query<(Entity, &Transform), NewCamera>,
     mut commands: Commands,
     config: Res<ChunksConfig>,
 ) {
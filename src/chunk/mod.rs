@@ -1,7 +1,11 @@
 use bevy::{
     prelude::*,
     reflect::TypeUuid,
-    render::{mesh::Indices, primitives::Aabb, render_resource::PrimitiveTopology},
+    render::{
+        mesh::{Indices, MeshVertexAttribute},
+        primitives::Aabb,
+        render_resource::{PrimitiveTopology, VertexFormat},
+    },
     tasks::{AsyncComputeTaskPool, Task},
 };
 use bevy_inspector_egui::egui::{Checkbox, DragValue, Grid, Widget};
@@ -18,6 +22,14 @@ use self::grid::{ChunkGrid, ChunkGridPlugin, GridCoordinates};
 
 mod grid;
 
+/// How many channels of the noise graph fit into a chunk mesh.
+pub const MAX_CHANNELS: usize = 4;
+
+/// The values of the noise graph's channels besides the height, in the order
+/// of their names. Components without a channel are 0.
+pub const ATTRIBUTE_CHANNELS: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Channels", 1_592_376_004, VertexFormat::Float32x4);
+
 pub struct ChunkPlugin;
 
 impl Plugin for ChunkPlugin {
@@ -98,12 +110,18 @@ fn spawn_compute_mesh_tasks(
     for (entity, grid_coordinates, chunk) in query.iter() {
         let grid_coordinates = *grid_coordinates;
         let Chunk { cell_size } = *chunk;
-        let noise = noise_graph.get_noise_fn().clone();
+        let height = noise_graph.terrain_height();
+        let channels = noise_graph.terrain_channels();
         let translation = grid_coordinates.to_translation(chunks_config.size as i32);
         let size = chunks_config.size;
         let task = pool.spawn(async move {
             generate_chunk_data(size, cell_size, translation, |x, z| {
-                noise.get([x as f64, z as f64]) as f32
+                let point = [x as f64, z as f64];
+                let mut values = [0.0; MAX_CHANNELS];
+                for (value, channel) in values.iter_mut().zip(&channels) {
+                    *value = channel.get(point) as f32;
+                }
+                (height.get(point) as f32, values)
             })
         });
         let mut entity = commands.entity(entity);
@@ -180,9 +198,12 @@ fn reload_chunks(
     })
 }
 
+/// Chunks which are not about to be despawned.
+type LiveChunk = (With<Chunk>, Without<DespawnChunk>);
+
 fn unload_chunks(
     mut commands: Commands,
-    chunks: Query<(Entity, &GridCoordinates), (With<Chunk>, Without<DespawnChunk>)>,
+    chunks: Query<(Entity, &GridCoordinates), LiveChunk>,
     camera: Query<&GridCoordinates, With<CameraController>>,
     chunks_config: Res<ChunksConfig>,
     chunk_grid: Res<ChunkGrid>,
@@ -215,17 +236,20 @@ fn despawn_chunks(
         })
 }
 
+/// Builds the mesh of a chunk from the height and the channels computed for
+/// each of its vertices.
 fn generate_chunk_data(
     size: f32,
     cell_size: f32,
     position: Vec3,
-    compute_height: impl FnMut(f32, f32) -> f32,
+    compute: impl FnMut(f32, f32) -> (f32, [f32; MAX_CHANNELS]),
 ) -> ChunkData {
     assert!(size % cell_size == 0.0);
     let cells_per_side = (size / cell_size) as usize;
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-    let vertices = vertices(cell_size, cells_per_side, position, compute_height);
+    let (vertices, channels) = vertices(cell_size, cells_per_side, position, compute);
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.insert_attribute(ATTRIBUTE_CHANNELS, channels);
     mesh.set_indices(Some(Indices::U32(indices(cells_per_side))));
     mesh.duplicate_vertices();
     mesh.set_indices(None);
@@ -240,22 +264,25 @@ fn vertices(
     cell_size: f32,
     cells_per_side: usize,
     position: Vec3,
-    mut compute_height: impl FnMut(f32, f32) -> f32,
-) -> Vec<[f32; 3]> {
-    let mut vertices = Vec::with_capacity((cells_per_side + 1) * (cells_per_side + 1));
+    mut compute: impl FnMut(f32, f32) -> (f32, [f32; MAX_CHANNELS]),
+) -> (Vec<[f32; 3]>, Vec<[f32; MAX_CHANNELS]>) {
+    let count = (cells_per_side + 1) * (cells_per_side + 1);
+    let mut vertices = Vec::with_capacity(count);
+    let mut channels = Vec::with_capacity(count);
     let cells_per_direction = cells_per_side as isize / 2;
 
     for x_index in -cells_per_direction..=cells_per_direction {
         for z_index in -cells_per_direction..=cells_per_direction {
             let x = x_index as f32 * cell_size;
             let z = z_index as f32 * cell_size;
-            let y = compute_height(x + position.x, z + position.z);
+            let (y, values) = compute(x + position.x, z + position.z);
 
             vertices.push([x, y, z]);
+            channels.push(values);
         }
     }
 
-    vertices
+    (vertices, channels)
 }
 
 fn indices(cells_per_side: usize) -> Vec<u32> {
@@ -329,9 +356,9 @@ impl ChunksConfig {
             .expect("Expected at least 1 lod_breakpoint");
 
         if distance <= lowest_breakpoint.distance {
-            return lowest_breakpoint.cell_size;
+            lowest_breakpoint.cell_size
         } else if distance >= highest_breakpoint.distance {
-            return highest_breakpoint.cell_size;
+            highest_breakpoint.cell_size
         } else {
             for window in self.lod_breakpoints.windows(2) {
                 if window[0].distance <= distance && window[1].distance > distance {
@@ -57,7 +57,7 @@ impl Sun {
             let delta = time.elapsed_seconds_wrapped() * settings.speed / 100.0;
             atmosphere.sun_position = Vec3::new(0.0, delta.sin(), delta.cos());
 
-            if let Some((mut transform, mut light)) = query.get_single_mut().ok() {
+            if let Ok((mut transform, mut light)) = query.get_single_mut() {
                 transform.rotation = Quat::from_rotation_x(-delta.sin().atan2(delta.cos()));
                 light.illuminance = delta.sin().max(0.0).powf(2.0) * 100_000.0;
             }
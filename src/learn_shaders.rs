@@ -1,13 +1,16 @@
 use bevy::{
+    pbr::{MaterialPipeline, MaterialPipelineKey},
     prelude::*,
     reflect::TypeUuid,
     render::{
+        mesh::MeshVertexBufferLayout,
         render_asset::RenderAssets,
         render_resource::{
             encase::StorageBuffer, AsBindGroup, AsBindGroupError, BindGroupDescriptor,
             BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
             BindingType, BufferBindingType, BufferInitDescriptor, BufferUsages,
-            OwnedBindingResource, PreparedBindGroup, ShaderRef, ShaderStages, ShaderType,
+            OwnedBindingResource, PreparedBindGroup, RenderPipelineDescriptor, ShaderRef,
+            ShaderStages, ShaderType, SpecializedMeshPipelineError,
         },
         renderer::RenderDevice,
         texture::FallbackImage,
@@ -16,6 +19,7 @@ use bevy::{
 use bevy_inspector_egui::egui::{DragValue, Grid, Response, Ui, Widget};
 
 use crate::{
+    chunk::ATTRIBUTE_CHANNELS,
     pause::GameState,
     widgets::{ColorWidget, ListWidget},
 };
@@ -74,7 +78,12 @@ impl AsBindGroup for LearnShadersMaterial {
     ) -> Result<PreparedBindGroup<Self>, AsBindGroupError> {
         let bindings = vec![{
             let mut buffer = StorageBuffer::new(Vec::new());
-            buffer.write(&self.gradient_points).unwrap();
+            let gradient_points: Vec<ShaderGradientPoint> = self
+                .gradient_points
+                .iter()
+                .map(|point| point.to_shader())
+                .collect();
+            buffer.write(&gradient_points).unwrap();
             OwnedBindingResource::Buffer(render_device.create_buffer_with_data(
                 &BufferInitDescriptor {
                     label: None,
@@ -90,7 +99,7 @@ impl AsBindGroup for LearnShadersMaterial {
                     resource: bindings[0usize].get_binding(),
                 }],
                 label: None,
-                layout: &layout,
+                layout,
             };
             render_device.create_bind_group(&descriptor)
         };
@@ -108,7 +117,7 @@ impl AsBindGroup for LearnShadersMaterial {
                 ty: BindingType::Buffer {
                     ty: BufferBindingType::Storage { read_only: true },
                     has_dynamic_offset: false,
-                    min_binding_size: Some(<Vec<GradientPoint> as ShaderType>::min_size()),
+                    min_binding_size: Some(<Vec<ShaderGradientPoint> as ShaderType>::min_size()),
                 },
                 count: None,
             }],
@@ -118,17 +127,51 @@ impl AsBindGroup for LearnShadersMaterial {
 }
 
 impl Material for LearnShadersMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/learn_shaders.wgsl".into()
+    }
+
     fn fragment_shader() -> ShaderRef {
         "shaders/learn_shaders.wgsl".into()
     }
+
+    /// Passes the channels of the noise graph to the shader next to the
+    /// positions and normals of the chunk meshes.
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+            ATTRIBUTE_CHANNELS.at_shader_location(5),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
 }
 
-#[derive(Clone, Copy, Debug, Default, ShaderType, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct GradientPoint {
     pub color: Color,
     pub height: f32,
 }
 
+/// A gradient point as laid out in the shader, the linear color followed by
+/// the height, which is padded to the size of a `vec4`.
+type ShaderGradientPoint = [Vec4; 2];
+
+impl GradientPoint {
+    fn to_shader(self) -> ShaderGradientPoint {
+        [
+            Vec4::from(self.color.as_linear_rgba_f32()),
+            Vec4::new(self.height, 0.0, 0.0, 0.0),
+        ]
+    }
+}
+
 impl Widget for &mut GradientPoint {
     fn ui(self, ui: &mut Ui) -> Response {
         ui.horizontal(|ui| {
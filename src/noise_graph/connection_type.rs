@@ -1,21 +1,42 @@
 use std::borrow::Cow;
 
 use bevy_egui::egui::Color32;
-use egui_node_graph::DataTypeTrait;
+use egui_node_graph::{AnyParameterId, DataTypeTrait};
 use serde::{Deserialize, Serialize};
 
-use super::NoiseGraphState;
+use super::{NoiseGraph, NoiseGraphState};
 
 /// `DataType`s are what defines the possible range of connections when
 /// attaching two ports together. The graph UI will make sure to not allow
 /// attaching incompatible datatypes.
-#[derive(PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
 pub enum ConnectionType {
     NoConnection,
     Noise,
     NoiseType,
     F64,
     Usize,
+    /// A field of 2D vectors, see `VectorFn`.
+    Vec2,
+}
+
+impl ConnectionType {
+    /// Whether an output of this type can be connected to an input of type
+    /// `input`, see `conversion`.
+    pub fn connects_to(self, input: ConnectionType) -> bool {
+        self == input || self.conversion(input).is_some()
+    }
+
+    /// How the values of an output of this type are converted for an input of
+    /// another type, `None` if they can't be.
+    pub fn conversion(self, input: ConnectionType) -> Option<&'static str> {
+        match (self, input) {
+            (ConnectionType::F64, ConnectionType::Noise) => Some("number as constant noise"),
+            (ConnectionType::F64, ConnectionType::Usize) => Some("rounded number"),
+            (ConnectionType::Usize, ConnectionType::F64) => Some("whole number"),
+            _ => None,
+        }
+    }
 }
 
 // A trait for the data types, to tell the library how to display them
@@ -27,6 +48,7 @@ impl DataTypeTrait<NoiseGraphState> for ConnectionType {
             ConnectionType::F64 => Color32::YELLOW,
             ConnectionType::NoiseType => Color32::LIGHT_RED,
             ConnectionType::Usize => Color32::BROWN,
+            ConnectionType::Vec2 => Color32::from_rgb(0, 200, 200),
         }
     }
 
@@ -34,3 +56,49 @@ impl DataTypeTrait<NoiseGraphState> for ConnectionType {
         Cow::Owned(self.to_string())
     }
 }
+
+/// The editor only lets ports of equal types be connected. While a connection
+/// is dragged from `dragged`, the ports it can be converted to or from take
+/// its type, so they accept it as well. Returns the original types for
+/// `restore_types`, which has to be called before anything else looks at the
+/// graph. The nodes are drawn from the graph before the drag in between.
+pub fn accept_conversions(
+    graph: &mut NoiseGraph,
+    dragged: AnyParameterId,
+) -> Vec<(AnyParameterId, ConnectionType)> {
+    let mut retagged = Vec::new();
+    match dragged {
+        AnyParameterId::Output(output_id) => {
+            let typ = graph[output_id].typ;
+            for (input_id, input) in graph.inputs.iter_mut() {
+                if input.typ != typ && typ.connects_to(input.typ) {
+                    retagged.push((AnyParameterId::Input(input_id), input.typ));
+                    input.typ = typ;
+                }
+            }
+        }
+        AnyParameterId::Input(input_id) => {
+            let typ = graph[input_id].typ;
+            for (output_id, output) in graph.outputs.iter_mut() {
+                if output.typ != typ && output.typ.connects_to(typ) {
+                    retagged.push((AnyParameterId::Output(output_id), output.typ));
+                    output.typ = typ;
+                }
+            }
+        }
+    }
+    retagged
+}
+
+/// Gives the ports changed by `accept_conversions` their types back.
+pub fn restore_types(graph: &mut NoiseGraph, retagged: Vec<(AnyParameterId, ConnectionType)>) {
+    for (param_id, typ) in retagged {
+        match param_id {
+            AnyParameterId::Input(input_id) => graph.inputs[input_id].typ = typ,
+            AnyParameterId::Output(output_id) => graph.outputs[output_id].typ = typ,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,54 @@
+use egui_node_graph::AnyParameterId;
+
+use super::{accept_conversions, restore_types, ConnectionType};
+use crate::noise_graph::{dsl, NoiseGraph};
+
+fn types(graph: &NoiseGraph) -> Vec<ConnectionType> {
+    let inputs = graph.inputs.values().map(|input| input.typ);
+    let outputs = graph.outputs.values().map(|output| output.typ);
+    inputs.chain(outputs).collect()
+}
+
+#[test]
+fn numbers_connect_to_noise_and_whole_numbers() {
+    use ConnectionType::*;
+
+    assert!(F64.connects_to(Noise));
+    assert!(F64.connects_to(Usize));
+    assert!(Usize.connects_to(F64));
+    assert!(Noise.connects_to(Noise));
+    assert!(!Noise.connects_to(F64));
+    assert!(!Vec2.connects_to(Noise));
+    assert_eq!(Noise.conversion(Noise), None);
+}
+
+#[test]
+fn dragged_numbers_are_accepted_by_convertible_inputs_until_restored() {
+    let mut resource = dsl::parse("n = float(value: 1); out = fbm(source: perlin());").unwrap();
+    let graph = &mut resource.state.graph;
+    let before = types(graph);
+    let (output_id, _) = graph
+        .outputs
+        .iter()
+        .find(|(_, output)| output.typ == ConnectionType::F64)
+        .unwrap();
+
+    let retagged = accept_conversions(graph, AnyParameterId::Output(output_id));
+    assert!(!retagged.is_empty());
+    for (param_id, typ) in &retagged {
+        let AnyParameterId::Input(input_id) = param_id else {
+            panic!("Outputs were retagged for a dragged output");
+        };
+        assert!(ConnectionType::F64.connects_to(*typ));
+        assert!(graph[*input_id].typ == ConnectionType::F64);
+    }
+    let noise_inputs = graph
+        .inputs
+        .values()
+        .filter(|input| input.typ == ConnectionType::Noise)
+        .count();
+    assert_eq!(noise_inputs, 0);
+
+    restore_types(graph, retagged);
+    assert!(types(graph) == before);
+}
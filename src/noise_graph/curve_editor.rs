@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy_egui::egui::{
+    self, emath::RectTransform, Color32, Painter, Pos2, Rect, Sense, Shape, Stroke,
+};
+use egui_node_graph::NodeId;
+use futures_lite::future;
+use noise::NoiseFn;
+
+use super::{
+    graph_ext::{EvaluationCache, GraphExt},
+    node_attribute::NodeAttribute,
+    node_template::NodeTemplate,
+    program::{sort_curve_points, sort_terrace_points, UnaryOp},
+    thumbnail::ThumbnailConfig,
+    DynNoiseFn, NoiseGraph,
+};
+
+/// The nodes whose control points are edited on a plot.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CurveEditor {
+    /// Control points with an input and an output, see `noise::Curve`.
+    Curve,
+    /// Control points on the input axis, see `noise::Terrace`.
+    Terrace,
+}
+
+/// How the values of a node's source are distributed, counted in bins of
+/// equal width between the smallest and largest value.
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    pub bins: Vec<usize>,
+}
+
+/// The histograms of the sources of all nodes with a curve editor, sampled
+/// over the window of the thumbnails.
+#[derive(Default)]
+pub struct SourceHistograms {
+    entries: HashMap<NodeId, SourceHistogram>,
+}
+
+struct SourceHistogram {
+    histogram: Option<Histogram>,
+    task: Option<Task<Option<Histogram>>>,
+    stale: bool,
+}
+
+impl Default for SourceHistogram {
+    fn default() -> Self {
+        Self {
+            histogram: None,
+            task: None,
+            stale: true,
+        }
+    }
+}
+
+impl Histogram {
+    /// Counts finite values into bins, `None` if there are no values.
+    pub fn new(values: &[f64], bins: usize) -> Option<Self> {
+        let min = values.iter().copied().reduce(f64::min)?;
+        let max = values.iter().copied().reduce(f64::max)?;
+        let width = (max - min) / bins as f64;
+        let mut counts = vec![0; bins];
+        for value in values {
+            let bin = if width > 0.0 {
+                ((value - min) / width) as usize
+            } else {
+                0
+            };
+            counts[bin.min(bins - 1)] += 1;
+        }
+
+        Some(Self {
+            min,
+            max,
+            bins: counts,
+        })
+    }
+
+    /// Draws the bins as bars standing on the bottom of the rect, scaled so
+    /// the fullest bin fills its height. `screen_x` places a value on the
+    /// screen.
+    pub fn paint(
+        &self,
+        painter: &Painter,
+        rect: Rect,
+        screen_x: impl Fn(f64) -> f32,
+        color: Color32,
+    ) {
+        let highest = self.bins.iter().copied().max().unwrap_or(0).max(1);
+        let width = (self.max - self.min) / self.bins.len() as f64;
+        for (i, count) in self.bins.iter().enumerate() {
+            let left = screen_x(self.min + i as f64 * width);
+            let right = screen_x(self.min + (i + 1) as f64 * width);
+            let height = rect.height() * *count as f32 / highest as f32;
+            painter.rect_filled(
+                Rect::from_min_max(
+                    Pos2::new(left, rect.bottom() - height),
+                    Pos2::new(right.max(left + 1.0), rect.bottom()),
+                ),
+                0.0,
+                color,
+            );
+        }
+    }
+}
+
+impl CurveEditor {
+    const SIZE: egui::Vec2 = egui::vec2(220.0, 140.0);
+    const POINT_RADIUS: f32 = 4.0;
+    /// The number of segments the curve is drawn with.
+    const SEGMENTS: usize = 100;
+
+    pub fn of(template: NodeTemplate) -> Option<Self> {
+        match template {
+            NodeTemplate::Curve => Some(CurveEditor::Curve),
+            NodeTemplate::Terrace => Some(CurveEditor::Terrace),
+            _ => None,
+        }
+    }
+
+    /// The number of distinct control points the node needs.
+    fn required_points(self) -> usize {
+        match self {
+            CurveEditor::Curve => 4,
+            CurveEditor::Terrace => 2,
+        }
+    }
+
+    /// Shows the control points on a plot of the curve, with the histogram of
+    /// the source in the background. Points are dragged around, added with a
+    /// double click and removed with a right click. Returns whether the
+    /// control points changed.
+    pub fn show(
+        self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        values: &mut Vec<NodeAttribute>,
+        histogram: Option<&Histogram>,
+    ) -> bool {
+        let mut changed = false;
+        let mut points: Vec<(f64, f64)> = values.iter().map(|value| self.point(value)).collect();
+        let (op, distinct) = self.op(&points);
+        let op = (distinct >= self.required_points()).then_some(op);
+
+        let (rect, response) = ui.allocate_exact_size(Self::SIZE, Sense::click());
+        let response = response.on_hover_text(
+            "Drag the points to move them, double click to add a point and right click a point \
+             to remove it",
+        );
+        let bounds = self.bounds(&points, op.as_ref(), histogram);
+        // Data coordinates grow upwards, screen coordinates downwards.
+        let to_screen = RectTransform::from_to(
+            Rect::from_min_max(
+                Pos2::new(bounds.min.x, bounds.max.y),
+                Pos2::new(bounds.max.x, bounds.min.y),
+            ),
+            rect,
+        );
+        let to_data = to_screen.inverse();
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+
+        painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+        if let Some(histogram) = histogram {
+            histogram.paint(
+                &painter,
+                rect,
+                |x| to_screen.transform_pos(Pos2::new(x as f32, 0.0)).x,
+                Color32::from_white_alpha(24),
+            );
+        }
+
+        let axis = Stroke::new(1.0, visuals.weak_text_color());
+        let origin = to_screen.transform_pos(Pos2::ZERO);
+        painter.hline(rect.x_range(), origin.y, axis);
+        painter.vline(origin.x, rect.y_range(), axis);
+
+        if let Some(op) = &op {
+            let curve = (0..=Self::SEGMENTS)
+                .map(|i| {
+                    let x = bounds.min.x + bounds.width() * i as f32 / Self::SEGMENTS as f32;
+                    to_screen.transform_pos(Pos2::new(x, op.apply(x as f64) as f32))
+                })
+                .collect();
+            painter.add(Shape::line(
+                curve,
+                Stroke::new(2.0, Color32::from_rgb(100, 180, 255)),
+            ));
+        }
+
+        let mut removed = None;
+        for (i, point) in points.iter_mut().enumerate() {
+            let position = to_screen.transform_pos(Pos2::new(point.0 as f32, point.1 as f32));
+            let handle =
+                Rect::from_center_size(position, egui::Vec2::splat(3.0 * Self::POINT_RADIUS));
+            // Terrace points are grabbed anywhere along their line.
+            let handle = match self {
+                CurveEditor::Curve => handle,
+                CurveEditor::Terrace => Rect::from_x_y_ranges(handle.x_range(), rect.y_range()),
+            };
+            let point_response = ui.interact(
+                handle,
+                egui::Id::new(("curve editor", node_id, i)),
+                Sense::click_and_drag(),
+            );
+
+            if point_response.dragged() {
+                if let Some(pointer) = point_response.interact_pointer_pos() {
+                    let dragged = to_data.transform_pos(pointer.clamp(rect.min, rect.max));
+                    *point = match self {
+                        CurveEditor::Curve => (dragged.x as f64, dragged.y as f64),
+                        CurveEditor::Terrace => (dragged.x as f64, dragged.x as f64),
+                    };
+                    changed = true;
+                }
+            }
+            if point_response.secondary_clicked() {
+                removed = Some(i);
+            }
+
+            let color = if point_response.hovered() || point_response.dragged() {
+                Color32::WHITE
+            } else {
+                Color32::GOLD
+            };
+            match self {
+                CurveEditor::Curve => painter.circle_filled(position, Self::POINT_RADIUS, color),
+                CurveEditor::Terrace => {
+                    painter.vline(position.x, rect.y_range(), Stroke::new(1.0, color));
+                    painter.circle_filled(position, Self::POINT_RADIUS, color);
+                }
+            }
+        }
+
+        if let Some(i) = removed {
+            points.remove(i);
+            changed = true;
+        }
+        if response.double_clicked() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let added = to_data.transform_pos(pointer);
+                points.push(match self {
+                    CurveEditor::Curve => (added.x as f64, added.y as f64),
+                    CurveEditor::Terrace => (added.x as f64, added.x as f64),
+                });
+                changed = true;
+            }
+        }
+
+        if changed {
+            *values = points
+                .into_iter()
+                .map(|(x, y)| match self {
+                    CurveEditor::Curve => NodeAttribute::F64Tuple(x, y),
+                    CurveEditor::Terrace => NodeAttribute::F64(x),
+                })
+                .collect();
+        }
+
+        if distinct < self.required_points() {
+            ui.colored_label(
+                Color32::LIGHT_RED,
+                format!(
+                    "{} distinct control points of {} needed",
+                    distinct,
+                    self.required_points()
+                ),
+            );
+        }
+
+        changed
+    }
+
+    /// A control point as a point on the plot, terrace points lie on the
+    /// diagonal.
+    fn point(self, value: &NodeAttribute) -> (f64, f64) {
+        match (self, value) {
+            (CurveEditor::Curve, NodeAttribute::F64Tuple(input, output)) => (*input, *output),
+            (CurveEditor::Terrace, NodeAttribute::F64(value)) => (*value, *value),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    /// The operation the node applies to its source, together with the number
+    /// of distinct control points it has.
+    fn op(self, points: &[(f64, f64)]) -> (UnaryOp, usize) {
+        match self {
+            CurveEditor::Curve => {
+                let sorted = sort_curve_points(points.iter().copied());
+                let distinct = sorted.len();
+                (UnaryOp::Curve(sorted), distinct)
+            }
+            CurveEditor::Terrace => {
+                let sorted = sort_terrace_points(points.iter().map(|point| point.0));
+                let distinct = sorted.len();
+                (UnaryOp::Terrace(sorted), distinct)
+            }
+        }
+    }
+
+    /// The part of the plane the plot shows, which contains the noise range,
+    /// the control points, the curve and the histogram.
+    fn bounds(
+        self,
+        points: &[(f64, f64)],
+        op: Option<&UnaryOp>,
+        histogram: Option<&Histogram>,
+    ) -> Rect {
+        let mut bounds = Rect::from_min_max(Pos2::new(-1.0, -1.0), Pos2::new(1.0, 1.0));
+        for (x, y) in points {
+            bounds.extend_with(Pos2::new(*x as f32, *y as f32));
+        }
+        if let Some(histogram) = histogram {
+            bounds.extend_with_x(histogram.min as f32);
+            bounds.extend_with_x(histogram.max as f32);
+        }
+        if let Some(op) = op {
+            for i in 0..=Self::SEGMENTS {
+                let x = bounds.min.x + bounds.width() * i as f32 / Self::SEGMENTS as f32;
+                let y = op.apply(x as f64) as f32;
+                if y.is_finite() {
+                    bounds.extend_with_y(y);
+                }
+            }
+        }
+
+        bounds.expand2(bounds.size() * 0.05)
+    }
+}
+
+impl SourceHistograms {
+    const RESOLUTION: usize = 64;
+    const BINS: usize = 32;
+
+    /// Starts sampling the source of a node if its histogram is missing or
+    /// outdated, and picks up the result once it's done.
+    pub fn update(
+        &mut self,
+        node_id: NodeId,
+        graph: &NoiseGraph,
+        evaluation_cache: &mut EvaluationCache,
+        config: ThumbnailConfig,
+    ) {
+        let entry = self.entries.entry(node_id).or_default();
+
+        if let Some(task) = &mut entry.task {
+            if let Some(histogram) = future::block_on(future::poll_once(task)) {
+                entry.histogram = histogram;
+                entry.task = None;
+            }
+        }
+
+        if entry.stale && entry.task.is_none() {
+            entry.stale = false;
+
+            let source = graph[node_id]
+                .get_input("source")
+                .ok()
+                .and_then(|input_id| graph.connection(input_id))
+                .and_then(|output_id| {
+                    graph
+                        .evaluate_cached(graph[output_id].node, evaluation_cache)
+                        .and_then(NodeAttribute::try_to_noise_function)
+                        .ok()
+                });
+            match source {
+                Some(noise) => {
+                    entry.task = Some(
+                        AsyncComputeTaskPool::get().spawn(async move { histogram(noise, config) }),
+                    )
+                }
+                None => entry.histogram = None,
+            }
+        }
+    }
+
+    pub fn get(&self, node_id: NodeId) -> Option<&Histogram> {
+        self.entries.get(&node_id)?.histogram.as_ref()
+    }
+
+    pub fn invalidate_all(&mut self) {
+        self.entries
+            .values_mut()
+            .for_each(|entry| entry.stale = true);
+    }
+
+    pub fn invalidate(&mut self, nodes: &HashSet<NodeId>) {
+        for node_id in nodes {
+            if let Some(entry) = self.entries.get_mut(node_id) {
+                entry.stale = true;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, node_id: NodeId) {
+        self.entries.remove(&node_id);
+    }
+}
+
+/// Samples the noise over the window of the thumbnails. There is no
+/// histogram if the noise has no finite values.
+fn histogram(noise: DynNoiseFn, config: ThumbnailConfig) -> Option<Histogram> {
+    let resolution = SourceHistograms::RESOLUTION;
+    let [center_x, center_y] = config.center;
+    let step = 2.0 * config.half_extent / (resolution - 1) as f64;
+
+    let mut values = Vec::with_capacity(resolution * resolution);
+    for y in 0..resolution {
+        for x in 0..resolution {
+            let value = noise.get([
+                center_x - config.half_extent + x as f64 * step,
+                center_y - config.half_extent + y as f64 * step,
+            ]);
+            if value.is_finite() {
+                values.push(value);
+            }
+        }
+    }
+
+    Histogram::new(&values, SourceHistograms::BINS)
+}
+
+#[cfg(test)]
+mod tests;
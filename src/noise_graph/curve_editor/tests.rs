@@ -0,0 +1,73 @@
+use noise::Constant;
+
+use super::{histogram, CurveEditor, SourceHistograms};
+use crate::noise_graph::{
+    node_attribute::NodeAttribute, program::UnaryOp, runtime::Shape, thumbnail::ThumbnailConfig,
+    DynNoiseFn,
+};
+
+#[test]
+fn histograms_count_the_values_over_the_thumbnail_window() {
+    let config = ThumbnailConfig {
+        center: [100.0, 0.0],
+        half_extent: 50.0,
+        ..ThumbnailConfig::default()
+    };
+    let histogram = histogram(DynNoiseFn::new(Shape::X), config).unwrap();
+
+    assert_eq!((histogram.min, histogram.max), (50.0, 150.0));
+    assert_eq!(histogram.bins.len(), SourceHistograms::BINS);
+    assert_eq!(
+        histogram.bins.iter().sum::<usize>(),
+        SourceHistograms::RESOLUTION * SourceHistograms::RESOLUTION
+    );
+    assert!(histogram.bins.iter().all(|count| *count > 0));
+}
+
+#[test]
+fn constant_sources_fill_the_first_bin() {
+    let histogram = histogram(
+        DynNoiseFn::new(Constant::new(0.5)),
+        ThumbnailConfig::default(),
+    )
+    .unwrap();
+
+    assert_eq!((histogram.min, histogram.max), (0.5, 0.5));
+    assert_eq!(
+        histogram.bins[0],
+        SourceHistograms::RESOLUTION * SourceHistograms::RESOLUTION
+    );
+}
+
+#[test]
+fn sources_without_finite_values_have_no_histogram() {
+    let noise = DynNoiseFn::new(Constant::new(f64::NAN));
+    assert!(histogram(noise, ThumbnailConfig::default()).is_none());
+}
+
+#[test]
+fn plots_use_the_distinct_control_points() {
+    let values = [(0.5, 0.0), (-1.0, -1.0), (0.5, 0.3), (1.0, 1.0), (0.0, 0.2)]
+        .map(|(input, output)| NodeAttribute::F64Tuple(input, output));
+    let points: Vec<_> = values
+        .iter()
+        .map(|value| CurveEditor::Curve.point(value))
+        .collect();
+
+    let (op, distinct) = CurveEditor::Curve.op(&points);
+    assert_eq!(distinct, CurveEditor::Curve.required_points());
+    match op {
+        UnaryOp::Curve(points) => {
+            assert_eq!(points, [(-1.0, -1.0), (0.0, 0.2), (0.5, 0.0), (1.0, 1.0)])
+        }
+        op => panic!("{op:?} instead of a curve"),
+    }
+
+    let values = [0.5, 0.5].map(NodeAttribute::F64);
+    let points: Vec<_> = values
+        .iter()
+        .map(|value| CurveEditor::Terrace.point(value))
+        .collect();
+    let (_, distinct) = CurveEditor::Terrace.op(&points);
+    assert!(distinct < CurveEditor::Terrace.required_points());
+}
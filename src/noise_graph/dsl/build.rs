@@ -0,0 +1,555 @@
+use std::{collections::HashMap, fmt::Display};
+
+use egui_node_graph::{InputId, InputParamKind, NodeId, NodeTemplateTrait, OutputId};
+use strum::IntoEnumIterator;
+
+use crate::noise_graph::{
+    connection_type::ConnectionType,
+    node_attribute::{NodeAttribute, Operator, WorleyDistanceFunction, WorleyReturnType},
+    node_template::NodeTemplate,
+    NoiseGraph, NoiseGraphResource,
+};
+
+use super::{
+    argument_name,
+    formula::Formula,
+    function_name,
+    parser::{parse_script, Argument, BinaryOperator, Expr, ExprKind},
+    template_by_name, DslError, Position, Result,
+};
+
+/// What an expression evaluates to.
+#[derive(Clone)]
+enum Value {
+    Number(f64),
+    Output(OutputId),
+    /// A node without outputs, like an output node.
+    Node(NodeId),
+    /// An identifier which isn't a variable, like the name of a noise type.
+    Word(String),
+    String(String),
+    List(Vec<(Value, Position)>),
+    /// An argument which is shown as a parameter in the side panel, written
+    /// as `expose("Sea level", 0.3)`.
+    Exposed(String, Box<(Value, Position)>),
+}
+
+/// Builds a graph from a script, see the module documentation for the syntax.
+pub fn parse(source: &str) -> Result<NoiseGraphResource> {
+    let statements = parse_script(source)?;
+    let mut builder = Builder {
+        resource: NoiseGraphResource::default(),
+        variables: HashMap::new(),
+    };
+    let mut active_node = None;
+
+    for statement in statements {
+        if builder.variables.contains_key(&statement.name) {
+            return Err(DslError::new(
+                statement.position,
+                format!("'{}' is already defined", statement.name),
+            ));
+        }
+
+        let value = builder.eval(&statement.value)?;
+        let node_id = match value {
+            Value::Output(output_id) => {
+                let node_id = builder.graph()[output_id].node;
+                active_node = Some(node_id);
+                Some(node_id)
+            }
+            Value::Node(node_id) => Some(node_id),
+            _ => None,
+        };
+        if let Some(node_id) = node_id {
+            if creates_node(&statement.value) && !names_node(&statement.value) {
+                builder.set_name(node_id, &statement.name);
+            }
+        }
+        builder.variables.insert(statement.name, value);
+    }
+
+    let mut resource = builder.resource;
+    resource.user_state.active_node = active_node;
+    resource.auto_layout();
+
+    Ok(resource)
+}
+
+/// Whether the expression results in a node of its own, rather than referring
+/// to an existing one.
+fn creates_node(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Call { .. } | ExprKind::Binary { .. } | ExprKind::Negate(_)
+    )
+}
+
+/// Whether the expression is a call with an explicit `name` argument.
+fn names_node(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Call { arguments, .. } => arguments
+            .iter()
+            .any(|argument| matches!(&argument.name, Some((name, _)) if name == "name")),
+        _ => false,
+    }
+}
+
+struct Builder {
+    resource: NoiseGraphResource,
+    variables: HashMap<String, Value>,
+}
+
+impl Builder {
+    fn graph(&mut self) -> &mut NoiseGraph {
+        &mut self.resource.state.graph
+    }
+
+    fn add_node(&mut self, template: NodeTemplate) -> NodeId {
+        let state = &mut self.resource.state;
+        let user_state = &mut self.resource.user_state;
+        let node_id = state.graph.add_node(
+            template.node_graph_label(user_state),
+            template.user_data(user_state),
+            |graph, node_id| template.build_node(graph, user_state, node_id),
+        );
+        state.node_order.push(node_id);
+        node_id
+    }
+
+    fn output(&mut self, node_id: NodeId) -> Value {
+        match self.graph()[node_id].output_ids().next() {
+            Some(output_id) => Value::Output(output_id),
+            None => Value::Node(node_id),
+        }
+    }
+
+    fn input(&mut self, node_id: NodeId, name: &str) -> InputId {
+        self.graph()[node_id]
+            .get_input(name)
+            .expect("The template has the input")
+    }
+
+    fn set_name(&mut self, node_id: NodeId, name: &str) {
+        let input_id = self.input(node_id, "name");
+        self.graph().inputs[input_id].value = NodeAttribute::Name(name.into());
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        match &expr.kind {
+            ExprKind::Number(number) => Ok(Value::Number(*number)),
+            ExprKind::String(string) => Ok(Value::String(string.clone())),
+            ExprKind::Ident(name) => Ok(self
+                .variables
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| Value::Word(name.clone()))),
+            ExprKind::List(values) => values
+                .iter()
+                .map(|value| Ok((self.eval(value)?, value.position)))
+                .collect::<Result<_>>()
+                .map(Value::List),
+            ExprKind::Call {
+                function,
+                arguments,
+            } => self.call(function, arguments, expr.position),
+            ExprKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left = (self.eval(left)?, left.position);
+                let right = (self.eval(right)?, right.position);
+                self.binary(*operator, left, right, expr.position)
+            }
+            ExprKind::Output { node, output } => {
+                let node_id = match self.eval(node)? {
+                    Value::Output(output_id) => self.graph()[output_id].node,
+                    _ => return Err(DslError::new(node.position, "Expected a node")),
+                };
+                let template = self.graph()[node_id].user_data.template;
+                self.graph()[node_id]
+                    .outputs
+                    .iter()
+                    .find(|(name, _)| argument_name(name) == *output)
+                    .map(|(_, output_id)| Value::Output(*output_id))
+                    .ok_or_else(|| {
+                        DslError::new(
+                            expr.position,
+                            format!("'{}' has no output '{output}'", function_name(template)),
+                        )
+                    })
+            }
+            ExprKind::Negate(value) => {
+                let position = value.position;
+                match self.eval(value)? {
+                    Value::Number(number) => Ok(Value::Number(-number)),
+                    value if self.is_noise(&value) => {
+                        let node_id = self.add_node(NodeTemplate::Negate);
+                        self.assign(node_id, "source", value, position)?;
+                        Ok(self.output(node_id))
+                    }
+                    value => self.arithmetic(
+                        Operator::Subtract,
+                        (Value::Number(0.0), expr.position),
+                        (value, position),
+                    ),
+                }
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        function: &str,
+        arguments: &[Argument],
+        position: Position,
+    ) -> Result<Value> {
+        if function == "expose" {
+            return self.expose(arguments, position);
+        }
+
+        let template = template_by_name(function)
+            .ok_or_else(|| DslError::new(position, format!("Unknown node '{function}'")))?;
+        let node_id = self.add_node(template);
+        let mut positional = 0;
+        let mut assigned = Vec::new();
+
+        for argument in arguments {
+            // Setting an input can change the inputs which come after it,
+            // like the formula of an expression does.
+            let inputs: Vec<String> = self.graph()[node_id]
+                .inputs
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect();
+            let input = match &argument.name {
+                Some((name, position)) => inputs
+                    .into_iter()
+                    .find(|input| argument_name(input) == *name)
+                    .ok_or_else(|| {
+                        DslError::new(*position, format!("'{function}' has no input '{name}'"))
+                    })?,
+                None => {
+                    positional += 1;
+                    inputs
+                        .into_iter()
+                        .filter(|name| name != "name")
+                        .nth(positional - 1)
+                        .ok_or_else(|| {
+                            DslError::new(
+                                argument.value.position,
+                                format!("Too many arguments for '{function}'"),
+                            )
+                        })?
+                }
+            };
+
+            if assigned.contains(&input) {
+                return Err(DslError::new(
+                    argument.value.position,
+                    format!("'{}' is given more than once", argument_name(&input)),
+                ));
+            }
+
+            let value = self.eval(&argument.value)?;
+            self.assign(node_id, &input, value, argument.value.position)?;
+            template.sync_inputs(self.graph(), node_id);
+            assigned.push(input);
+        }
+
+        Ok(self.output(node_id))
+    }
+
+    fn expose(&mut self, arguments: &[Argument], position: Position) -> Result<Value> {
+        let [name, value] = arguments else {
+            return Err(DslError::new(
+                position,
+                "Expected a name and a value, like expose(\"Sea level\", 0.3)",
+            ));
+        };
+        if let Some((_, position)) = name.name.as_ref().or(value.name.as_ref()) {
+            return Err(DslError::new(
+                *position,
+                "'expose' takes no named arguments",
+            ));
+        }
+
+        let name = match self.eval(&name.value)? {
+            Value::String(name) => name,
+            _ => {
+                return Err(DslError::new(
+                    name.value.position,
+                    "Expected a name in quotes",
+                ))
+            }
+        };
+        let value = (self.eval(&value.value)?, value.value.position);
+        Ok(Value::Exposed(name, Box::new(value)))
+    }
+
+    fn binary(
+        &mut self,
+        operator: BinaryOperator,
+        left: (Value, Position),
+        right: (Value, Position),
+        position: Position,
+    ) -> Result<Value> {
+        if let (Value::Number(a), Value::Number(b)) = (&left.0, &right.0) {
+            return Ok(Value::Number(match operator {
+                BinaryOperator::Add => a + b,
+                BinaryOperator::Subtract => a - b,
+                BinaryOperator::Multiply => a * b,
+                BinaryOperator::Divide => a / b,
+            }));
+        }
+
+        if !self.is_noise(&left.0) && !self.is_noise(&right.0) {
+            let operator = match operator {
+                BinaryOperator::Add => Operator::Add,
+                BinaryOperator::Subtract => Operator::Subtract,
+                BinaryOperator::Multiply => Operator::Multiply,
+                BinaryOperator::Divide => Operator::Divide,
+            };
+            return self.arithmetic(operator, left, right);
+        }
+
+        match operator {
+            BinaryOperator::Add => self.combine(NodeTemplate::Add, left, right),
+            BinaryOperator::Multiply => self.combine(NodeTemplate::Multiply, left, right),
+            BinaryOperator::Subtract => {
+                let negate = self.add_node(NodeTemplate::Negate);
+                let right_position = right.1;
+                self.assign(negate, "source", right.0, right_position)?;
+                let negated = self.output(negate);
+                self.combine(NodeTemplate::Add, left, (negated, right_position))
+            }
+            BinaryOperator::Divide => Err(DslError::new(
+                position,
+                "Noise can't be divided, multiply it instead",
+            )),
+        }
+    }
+
+    /// Creates a node combining two noise sources, like `add`.
+    fn combine(
+        &mut self,
+        template: NodeTemplate,
+        (a, a_position): (Value, Position),
+        (b, b_position): (Value, Position),
+    ) -> Result<Value> {
+        let node_id = self.add_node(template);
+        self.assign(node_id, "source 1", a, a_position)?;
+        self.assign(node_id, "source 2", b, b_position)?;
+        Ok(self.output(node_id))
+    }
+
+    fn arithmetic(
+        &mut self,
+        operator: Operator,
+        (a, a_position): (Value, Position),
+        (b, b_position): (Value, Position),
+    ) -> Result<Value> {
+        let node_id = self.add_node(NodeTemplate::Arithmetic);
+        let operator_id = self.input(node_id, "operator");
+        self.graph().inputs[operator_id].value = NodeAttribute::Operator(operator);
+        self.assign(node_id, "a", a, a_position)?;
+        self.assign(node_id, "b", b, b_position)?;
+        Ok(self.output(node_id))
+    }
+
+    fn is_noise(&mut self, value: &Value) -> bool {
+        match value {
+            Value::Output(output_id) => self.graph()[*output_id].typ == ConnectionType::Noise,
+            _ => false,
+        }
+    }
+
+    /// Connects an input to a value or sets its constant value. Numbers
+    /// given in place of noise become `constant` nodes.
+    fn assign(
+        &mut self,
+        node_id: NodeId,
+        input: &str,
+        value: Value,
+        position: Position,
+    ) -> Result<()> {
+        if let Value::Exposed(name, value) = value {
+            let (value, position) = *value;
+            self.assign(node_id, input, value, position)?;
+            self.resource
+                .user_state
+                .exposed
+                .expose(node_id, input, name);
+            return Ok(());
+        }
+
+        let input_id = self.input(node_id, input);
+        let graph = self.graph();
+        let typ = graph[input_id].typ;
+        let kind = graph[input_id].kind();
+
+        match value {
+            Value::Output(output_id) => {
+                if matches!(kind, InputParamKind::ConstantOnly) {
+                    return Err(DslError::new(
+                        position,
+                        format!("'{}' can't be connected", argument_name(input)),
+                    ));
+                }
+                if !graph[output_id].typ.connects_to(typ) {
+                    return Err(DslError::new(
+                        position,
+                        format!(
+                            "Expected {}, found {}",
+                            describe(typ),
+                            describe(graph[output_id].typ)
+                        ),
+                    ));
+                }
+                graph.add_connection(output_id, input_id);
+            }
+            Value::Number(number)
+                if typ == ConnectionType::Noise
+                    && !matches!(graph[input_id].value, NodeAttribute::F64(_)) =>
+            {
+                let constant = self.add_node(NodeTemplate::Constant);
+                self.assign(constant, "value", Value::Number(number), position)?;
+                let output = self.output(constant);
+                self.assign(node_id, input, output, position)?;
+            }
+            Value::Word(word) if matches!(kind, InputParamKind::ConnectionOnly) => {
+                return Err(DslError::new(
+                    position,
+                    format!("Unknown variable '{word}'"),
+                ));
+            }
+            _ if matches!(kind, InputParamKind::ConnectionOnly) => {
+                return Err(DslError::new(
+                    position,
+                    format!("Expected {}", describe(typ)),
+                ));
+            }
+            value => {
+                let constant = constant(&graph[input_id].value, value, position)?;
+                graph.inputs[input_id].value = constant;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts a value into the constant value of an input, taking the type from
+/// its current value.
+fn constant(current: &NodeAttribute, value: Value, position: Position) -> Result<NodeAttribute> {
+    let constant = match (current, value) {
+        (NodeAttribute::F64(_), Value::Number(number)) => NodeAttribute::F64(number),
+        (NodeAttribute::Usize(_), Value::Number(number))
+            if number >= 0.0 && number.fract() == 0.0 && number <= usize::MAX as f64 =>
+        {
+            NodeAttribute::Usize(number as usize)
+        }
+        (NodeAttribute::Bool(_), Value::Word(word)) if word == "true" || word == "false" => {
+            NodeAttribute::Bool(word == "true")
+        }
+        (NodeAttribute::NoiseType(_), Value::Word(word)) => {
+            NodeAttribute::NoiseType(variant(&word, position)?)
+        }
+        (NodeAttribute::Operator(_), Value::Word(word)) => {
+            NodeAttribute::Operator(variant(&word, position)?)
+        }
+        (NodeAttribute::ReturnType(_), Value::Word(word)) => {
+            NodeAttribute::ReturnType(variant::<WorleyReturnType>(&word, position)?)
+        }
+        (NodeAttribute::DistanceFunction(_), Value::Word(word)) => {
+            NodeAttribute::DistanceFunction(variant::<WorleyDistanceFunction>(&word, position)?)
+        }
+        (NodeAttribute::Name(_), Value::String(name)) => NodeAttribute::Name(name),
+        (NodeAttribute::Formula(_), Value::String(formula)) => {
+            if let Err(e) = Formula::parse(&formula) {
+                // Points into the string, which starts after its quote.
+                let position = Position {
+                    line: position.line + e.position.line - 1,
+                    column: match e.position.line {
+                        1 => position.column + e.position.column,
+                        _ => e.position.column,
+                    },
+                };
+                return Err(DslError::new(
+                    position,
+                    format!("Invalid formula, {}", e.message),
+                ));
+            }
+            NodeAttribute::Formula(formula)
+        }
+        (NodeAttribute::Vec { template, .. }, Value::List(values)) => NodeAttribute::Vec {
+            values: values
+                .into_iter()
+                .map(|(value, position)| constant(template, value, position))
+                .collect::<Result<_>>()?,
+            template: template.clone(),
+        },
+        (NodeAttribute::F64Tuple(..), Value::List(values)) => match values.as_slice() {
+            [(Value::Number(first), _), (Value::Number(second), _)] => {
+                NodeAttribute::F64Tuple(*first, *second)
+            }
+            _ => return Err(DslError::new(position, "Expected two numbers like [0, 1]")),
+        },
+        (_, Value::Word(word)) => {
+            return Err(DslError::new(
+                position,
+                format!("Unknown variable '{word}'"),
+            ))
+        }
+        (current, _) => {
+            return Err(DslError::new(
+                position,
+                format!("Expected {}", expected(current)),
+            ))
+        }
+    };
+
+    Ok(constant)
+}
+
+/// Looks up an option like a noise type by its name, ignoring case.
+fn variant<T: IntoEnumIterator + Display>(word: &str, position: Position) -> Result<T> {
+    T::iter()
+        .find(|variant| variant.to_string().eq_ignore_ascii_case(word))
+        .ok_or_else(|| {
+            let options: Vec<String> = T::iter().map(|variant| variant.to_string()).collect();
+            DslError::new(
+                position,
+                format!("Expected one of {}, found '{word}'", options.join(", ")),
+            )
+        })
+}
+
+fn expected(current: &NodeAttribute) -> &'static str {
+    match current {
+        NodeAttribute::F64(_) => "a number",
+        NodeAttribute::Usize(_) => "a whole number",
+        NodeAttribute::Bool(_) => "true or false",
+        NodeAttribute::NoiseType(_) => "a noise type",
+        NodeAttribute::Operator(_) => "an operator",
+        NodeAttribute::ReturnType(_) => "a return type",
+        NodeAttribute::DistanceFunction(_) => "a distance function",
+        NodeAttribute::Name(_) => "a string",
+        NodeAttribute::Formula(_) => "a formula in quotes",
+        NodeAttribute::Vec { .. } => "a list",
+        NodeAttribute::F64Tuple(..) => "two numbers like [0, 1]",
+        _ => "a connection",
+    }
+}
+
+fn describe(typ: ConnectionType) -> &'static str {
+    match typ {
+        ConnectionType::Noise => "noise",
+        ConnectionType::F64 => "a number",
+        ConnectionType::Usize => "a whole number",
+        ConnectionType::NoiseType => "a noise type",
+        ConnectionType::Vec2 => "a vector field",
+        ConnectionType::NoConnection => "a constant",
+    }
+}
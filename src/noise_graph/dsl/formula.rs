@@ -0,0 +1,396 @@
+use std::f64::consts::PI;
+
+use strum::IntoEnumIterator;
+
+use super::{
+    parser::{parse_expression, BinaryOperator, Expr, ExprKind},
+    DslError, Position, Result,
+};
+
+/// The most variables a formula can use.
+pub const MAX_VARIABLES: usize = 16;
+
+/// How many values a formula can have to keep around at once while being
+/// evaluated, which limits how deeply it can be nested.
+const MAX_DEPTH: usize = 32;
+
+/// Names which can't be variables, since the expression node already has
+/// inputs with these names.
+const RESERVED: [&str; 2] = ["name", "formula"];
+
+/// A formula like `smoothstep(0.2, 0.6, a) * b + sin(x * 0.01)`, compiled into
+/// a list of operations on a stack.
+///
+/// Formulas are written like the expressions of the graph language, with
+/// numbers, `+`, `-`, `*`, `/` and parentheses. `x` and `y` are the coordinates
+/// of the sample point, `pi` is the constant and calls go to the functions of
+/// [`Function`]. Every other name is a variable, numbered in the order of their
+/// first use.
+#[derive(Clone)]
+pub struct Formula {
+    source: String,
+    ops: Vec<Op>,
+    variables: Vec<String>,
+    uses_point: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Constant(f64),
+    Variable(usize),
+    X,
+    Y,
+    Negate,
+    Binary(BinaryOperator),
+    Call(Function),
+}
+
+/// The functions a formula can call, named like their GLSL counterparts.
+#[derive(Clone, Copy, Debug, strum::EnumIter, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Function {
+    Abs,
+    Sign,
+    Floor,
+    Ceil,
+    Fract,
+    Sqrt,
+    Exp,
+    Ln,
+    Sin,
+    Cos,
+    Tan,
+    Min,
+    Max,
+    Pow,
+    Atan2,
+    Clamp,
+    Mix,
+    Step,
+    Smoothstep,
+}
+
+impl Formula {
+    pub fn parse(source: &str) -> Result<Self> {
+        let expr = parse_expression(source)?;
+        let mut compiler = Compiler {
+            formula: Formula {
+                source: source.into(),
+                ops: Vec::new(),
+                variables: Vec::new(),
+                uses_point: false,
+            },
+            depth: 0,
+        };
+        compiler.expr(&expr)?;
+
+        Ok(compiler.formula)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The names of the variables, in the order `evaluate` takes their values.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Whether the formula refers to `x` or `y`, otherwise its value only
+    /// depends on the variables.
+    pub fn uses_point(&self) -> bool {
+        self.uses_point
+    }
+
+    pub fn evaluate(&self, variables: &[f64], [x, y]: [f64; 2]) -> f64 {
+        let mut stack = [0.0; MAX_DEPTH];
+        let mut len = 0;
+
+        for op in &self.ops {
+            let (operands, value) = match *op {
+                Op::Constant(value) => (0, value),
+                Op::Variable(index) => (0, variables[index]),
+                Op::X => (0, x),
+                Op::Y => (0, y),
+                Op::Negate => (1, -stack[len - 1]),
+                Op::Binary(operator) => {
+                    let (a, b) = (stack[len - 2], stack[len - 1]);
+                    let value = match operator {
+                        BinaryOperator::Add => a + b,
+                        BinaryOperator::Subtract => a - b,
+                        BinaryOperator::Multiply => a * b,
+                        BinaryOperator::Divide => a / b,
+                    };
+                    (2, value)
+                }
+                Op::Call(function) => {
+                    let arity = function.arity();
+                    (arity, function.apply(&stack[len - arity..len]))
+                }
+            };
+            len -= operands;
+            stack[len] = value;
+            len += 1;
+        }
+
+        stack[0]
+    }
+
+    /// Writes the formula as Rust code computing exactly what `evaluate`
+    /// computes, given the expressions of the values of the variables and of
+    /// the coordinates. Every operation becomes a statement of its own and the
+    /// last line is the value.
+    pub fn to_rust(&self, variables: &[String], [x, y]: [&str; 2]) -> String {
+        let mut statements = Vec::new();
+        let mut stack: Vec<String> = Vec::new();
+
+        for (index, op) in self.ops.iter().enumerate() {
+            let value = match *op {
+                // Numbers of the source are never negative, but too large
+                // ones parse to infinity.
+                Op::Constant(value) if value.is_infinite() => "f64::INFINITY".into(),
+                Op::Constant(value) => format!("{value:?}"),
+                Op::Variable(index) => variables[index].clone(),
+                Op::X => x.into(),
+                Op::Y => y.into(),
+                Op::Negate => {
+                    let value = stack.pop().expect("Operand of a negation");
+                    format!("-{value}")
+                }
+                Op::Binary(operator) => {
+                    let b = stack.pop().expect("Right operand");
+                    let a = stack.pop().expect("Left operand");
+                    let operator = match operator {
+                        BinaryOperator::Add => "+",
+                        BinaryOperator::Subtract => "-",
+                        BinaryOperator::Multiply => "*",
+                        BinaryOperator::Divide => "/",
+                    };
+                    format!("{a} {operator} {b}")
+                }
+                Op::Call(function) => {
+                    let arguments = stack.split_off(stack.len() - function.arity());
+                    function.to_rust(&arguments)
+                }
+            };
+
+            let last = index + 1 == self.ops.len();
+            match op {
+                Op::Constant(_) | Op::Variable(_) | Op::X | Op::Y => stack.push(value),
+                _ if last => stack.push(value),
+                _ => {
+                    let temporary = format!("t{index}");
+                    statements.push(format!("let {temporary} = {value};"));
+                    stack.push(temporary);
+                }
+            }
+        }
+
+        statements.extend(stack.pop());
+        statements.join("\n")
+    }
+}
+
+impl Function {
+    fn arity(self) -> usize {
+        match self {
+            Function::Abs
+            | Function::Sign
+            | Function::Floor
+            | Function::Ceil
+            | Function::Fract
+            | Function::Sqrt
+            | Function::Exp
+            | Function::Ln
+            | Function::Sin
+            | Function::Cos
+            | Function::Tan => 1,
+            Function::Min | Function::Max | Function::Pow | Function::Atan2 | Function::Step => 2,
+            Function::Clamp | Function::Mix | Function::Smoothstep => 3,
+        }
+    }
+
+    fn apply(self, arguments: &[f64]) -> f64 {
+        match self {
+            Function::Abs => arguments[0].abs(),
+            Function::Sign => {
+                if arguments[0] == 0.0 {
+                    0.0
+                } else {
+                    arguments[0].signum()
+                }
+            }
+            Function::Floor => arguments[0].floor(),
+            Function::Ceil => arguments[0].ceil(),
+            Function::Fract => arguments[0] - arguments[0].floor(),
+            Function::Sqrt => arguments[0].sqrt(),
+            Function::Exp => arguments[0].exp(),
+            Function::Ln => arguments[0].ln(),
+            Function::Sin => arguments[0].sin(),
+            Function::Cos => arguments[0].cos(),
+            Function::Tan => arguments[0].tan(),
+            Function::Min => arguments[0].min(arguments[1]),
+            Function::Max => arguments[0].max(arguments[1]),
+            Function::Pow => arguments[0].powf(arguments[1]),
+            Function::Atan2 => arguments[0].atan2(arguments[1]),
+            // Unlike `f64::clamp`, this doesn't panic for swapped bounds.
+            Function::Clamp => arguments[0].max(arguments[1]).min(arguments[2]),
+            Function::Mix => {
+                let [a, b, alpha] = [arguments[0], arguments[1], arguments[2]];
+                alpha.mul_add(b - a, a)
+            }
+            Function::Step => {
+                if arguments[1] < arguments[0] {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Function::Smoothstep => {
+                let [lower, upper, value] = [arguments[0], arguments[1], arguments[2]];
+                let t = ((value - lower) / (upper - lower)).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }
+        }
+    }
+
+    /// The Rust expression applying the function, written out like `apply`.
+    fn to_rust(self, arguments: &[String]) -> String {
+        match (self, arguments) {
+            (Function::Sign, [a]) => format!("if {a} == 0.0 {{ 0.0 }} else {{ f64::signum({a}) }}"),
+            (Function::Fract, [a]) => format!("{a} - f64::floor({a})"),
+            (Function::Pow, [a, b]) => format!("f64::powf({a}, {b})"),
+            (Function::Clamp, [a, b, c]) => format!("f64::min(f64::max({a}, {b}), {c})"),
+            (Function::Mix, [a, b, alpha]) => format!("f64::mul_add({alpha}, {b} - {a}, {a})"),
+            (Function::Step, [edge, value]) => {
+                format!("if {value} < {edge} {{ 0.0 }} else {{ 1.0 }}")
+            }
+            (Function::Smoothstep, [lower, upper, value]) => format!(
+                "{{\n    let t = f64::clamp(({value} - {lower}) / ({upper} - {lower}), 0.0, 1.0);\n    \
+                 t * t * (3.0 - 2.0 * t)\n}}"
+            ),
+            (function, arguments) => format!("f64::{function}({})", arguments.join(", ")),
+        }
+    }
+}
+
+struct Compiler {
+    formula: Formula,
+    /// The number of values on the stack at this point of the formula.
+    depth: usize,
+}
+
+impl Compiler {
+    fn expr(&mut self, expr: &Expr) -> Result<()> {
+        match &expr.kind {
+            ExprKind::Number(number) => self.push(Op::Constant(*number), expr.position),
+            ExprKind::Ident(name) => match name.as_str() {
+                "x" => {
+                    self.formula.uses_point = true;
+                    self.push(Op::X, expr.position)
+                }
+                "y" => {
+                    self.formula.uses_point = true;
+                    self.push(Op::Y, expr.position)
+                }
+                "pi" => self.push(Op::Constant(PI), expr.position),
+                name => {
+                    let index = self.variable(name, expr.position)?;
+                    self.push(Op::Variable(index), expr.position)
+                }
+            },
+            ExprKind::Call {
+                function,
+                arguments,
+            } => {
+                let op = Function::iter()
+                    .find(|available| available.to_string() == *function)
+                    .ok_or_else(|| {
+                        DslError::new(expr.position, format!("Unknown function '{function}'"))
+                    })?;
+                let arity = op.arity();
+                if arguments.len() != arity {
+                    return Err(DslError::new(
+                        expr.position,
+                        format!(
+                            "'{function}' takes {arity} argument{}, found {}",
+                            if arity == 1 { "" } else { "s" },
+                            arguments.len()
+                        ),
+                    ));
+                }
+
+                for argument in arguments {
+                    if let Some((_, position)) = &argument.name {
+                        return Err(DslError::new(
+                            *position,
+                            "Functions don't take named arguments",
+                        ));
+                    }
+                    self.expr(&argument.value)?;
+                }
+                self.apply(Op::Call(op), arity);
+                Ok(())
+            }
+            ExprKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                self.expr(left)?;
+                self.expr(right)?;
+                self.apply(Op::Binary(*operator), 2);
+                Ok(())
+            }
+            ExprKind::Negate(value) => {
+                self.expr(value)?;
+                self.apply(Op::Negate, 1);
+                Ok(())
+            }
+            ExprKind::String(_) | ExprKind::List(_) | ExprKind::Output { .. } => {
+                Err(DslError::new(expr.position, "Expected a number"))
+            }
+        }
+    }
+
+    /// The index of a variable, adding it if it's used for the first time.
+    fn variable(&mut self, name: &str, position: Position) -> Result<usize> {
+        let variables = &mut self.formula.variables;
+        if let Some(index) = variables.iter().position(|variable| variable == name) {
+            return Ok(index);
+        }
+
+        if RESERVED.contains(&name) {
+            return Err(DslError::new(
+                position,
+                format!("'{name}' can't be used as a variable"),
+            ));
+        }
+        if variables.len() == MAX_VARIABLES {
+            return Err(DslError::new(
+                position,
+                format!("A formula can't use more than {MAX_VARIABLES} variables"),
+            ));
+        }
+
+        variables.push(name.into());
+        Ok(variables.len() - 1)
+    }
+
+    /// Pushes a new value onto the stack.
+    fn push(&mut self, op: Op, position: Position) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(DslError::new(position, "The formula is nested too deeply"));
+        }
+
+        self.formula.ops.push(op);
+        Ok(())
+    }
+
+    /// Replaces the topmost `operands` values of the stack with one.
+    fn apply(&mut self, op: Op, operands: usize) {
+        self.depth -= operands - 1;
+        self.formula.ops.push(op);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{iter::Peekable, str::Chars};
+
+use super::{DslError, Position, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Ident(String),
+    Number(f64),
+    String(String),
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Colon,
+    Dot,
+    Semicolon,
+    Equals,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Eof,
+}
+
+impl Token {
+    /// How the token is called in error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("'{name}'"),
+            Token::Number(number) => format!("'{number}'"),
+            Token::String(_) => "a string".into(),
+            Token::LeftParen => "'('".into(),
+            Token::RightParen => "')'".into(),
+            Token::LeftBracket => "'['".into(),
+            Token::RightBracket => "']'".into(),
+            Token::Comma => "','".into(),
+            Token::Colon => "':'".into(),
+            Token::Dot => "'.'".into(),
+            Token::Semicolon => "';'".into(),
+            Token::Equals => "'='".into(),
+            Token::Plus => "'+'".into(),
+            Token::Minus => "'-'".into(),
+            Token::Star => "'*'".into(),
+            Token::Slash => "'/'".into(),
+            Token::Eof => "the end of the script".into(),
+        }
+    }
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<(Token, Position)>> {
+    let mut lexer = Lexer {
+        chars: source.chars().peekable(),
+        position: Position { line: 1, column: 1 },
+    };
+    let mut tokens = Vec::new();
+
+    loop {
+        lexer.skip_whitespace_and_comments();
+        let position = lexer.position;
+        let Some(char) = lexer.next() else {
+            tokens.push((Token::Eof, position));
+            return Ok(tokens);
+        };
+
+        let token = match char {
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            ';' => Token::Semicolon,
+            '=' => Token::Equals,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '"' => lexer.string(position)?,
+            '.' if !lexer.chars.peek().is_some_and(char::is_ascii_digit) => Token::Dot,
+            '0'..='9' | '.' => lexer.number(char, position)?,
+            char if char.is_alphabetic() || char == '_' => lexer.ident(char),
+            char => return Err(DslError::new(position, format!("Unexpected '{char}'"))),
+        };
+        tokens.push((token, position));
+    }
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: Position,
+}
+
+impl<'a> Lexer<'a> {
+    fn next(&mut self) -> Option<char> {
+        let char = self.chars.next()?;
+        if char == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(char)
+    }
+
+    fn next_if(&mut self, predicate: impl Fn(char) -> bool) -> Option<char> {
+        match self.chars.peek() {
+            Some(&char) if predicate(char) => self.next(),
+            _ => None,
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            if self.next_if(char::is_whitespace).is_some() {
+                continue;
+            }
+            if self.next_if(|char| char == '#').is_some() {
+                while self.next_if(|char| char != '\n').is_some() {}
+                continue;
+            }
+            return;
+        }
+    }
+
+    fn ident(&mut self, first: char) -> Token {
+        let mut ident = String::from(first);
+        while let Some(char) = self.next_if(|char| char.is_alphanumeric() || char == '_') {
+            ident.push(char);
+        }
+        Token::Ident(ident)
+    }
+
+    fn number(&mut self, first: char, position: Position) -> Result<Token> {
+        let mut number = String::from(first);
+        while let Some(char) = self.next_if(|char| char.is_ascii_digit() || char == '.') {
+            number.push(char);
+        }
+        if let Some(char) = self.next_if(|char| char == 'e' || char == 'E') {
+            number.push(char);
+            if let Some(sign) = self.next_if(|char| char == '+' || char == '-') {
+                number.push(sign);
+            }
+            while let Some(char) = self.next_if(|char| char.is_ascii_digit()) {
+                number.push(char);
+            }
+        }
+
+        number
+            .parse()
+            .map(Token::Number)
+            .map_err(|_| DslError::new(position, format!("Invalid number '{number}'")))
+    }
+
+    fn string(&mut self, position: Position) -> Result<Token> {
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(Token::String(string)),
+                Some('\\') => match self.next() {
+                    Some('n') => string.push('\n'),
+                    Some(char @ ('"' | '\\')) => string.push(char),
+                    _ => return Err(DslError::new(self.position, "Invalid escape sequence")),
+                },
+                Some(char) => string.push(char),
+                None => return Err(DslError::new(position, "Unterminated string")),
+            }
+        }
+    }
+}
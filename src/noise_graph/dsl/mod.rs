@@ -0,0 +1,113 @@
+//! A small language for writing noise graphs as text:
+//!
+//! ```text
+//! # Mountains rise out of gentle plains.
+//! mountains = ridged_multi(octaves: 6, frequency: 0.002) * 300;
+//! plains = billow(frequency: 0.001) * 20;
+//! out = select(mountains, plains, control: fbm(frequency: 0.0005));
+//! ```
+//!
+//! Every statement binds a name to an expression. Calls create a node of the
+//! template with the same name in snake case, taking arguments in the order of
+//! the template's inputs or by name, with spaces in input names written as
+//! underscores. The node created by a statement is named after its variable,
+//! an explicit `name: "..."` argument takes precedence. The node of the last
+//! statement becomes the active node, `out = mountains;` makes an existing
+//! node the active one.
+//!
+//! Numbers in place of noise become `constant` nodes, unless the input takes a
+//! constant while nothing is connected, `+`, `-` and `*` on noise
+//! become `add`, `negate` and `multiply` nodes, and arithmetic on number nodes
+//! becomes `arithmetic` nodes. Number nodes can be passed to noise and whole
+//! number inputs as well, see `ConnectionType::conversion`. Lists are written
+//! as `[1, 2]`, switches as `true` or `false` and options like the noise type
+//! by their name, e.g. `noise_type: Simplex`.
+//!
+//! Wrapping an argument in `expose("Sea level", 0.3)` shows the input in the
+//! side panel under the given name, see `ExposedParameters`.
+//!
+//! Outputs besides `out` are referred to by their name after a dot, like
+//! `worley().cell_value`. Output nodes are named after their variable too, so
+//! `height = output(mountains);` defines the height channel.
+//!
+//! The formula of an `expression` node is a string in the same expression
+//! syntax, followed by the noise for each of its variables:
+//! `expression("a * b + sin(x)", perlin(), billow())`, see `Formula`.
+
+use std::fmt;
+
+use strum::IntoEnumIterator;
+
+use super::node_template::NodeTemplate;
+
+pub use self::{
+    build::parse,
+    formula::{Formula, MAX_VARIABLES},
+    print::print,
+};
+
+mod build;
+mod formula;
+mod lexer;
+mod parser;
+mod print;
+
+/// A position in the source text, both starting at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub struct DslError {
+    pub position: Position,
+    pub message: String,
+}
+
+impl DslError {
+    fn new(position: Position, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for DslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.position.line, self.position.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for DslError {}
+
+type Result<T> = std::result::Result<T, DslError>;
+
+/// The name a template is called by, e.g. `ridged_multi`.
+pub(super) fn function_name(template: NodeTemplate) -> String {
+    let mut name = String::new();
+    for (i, char) in template.to_string().chars().enumerate() {
+        if char.is_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(char.to_ascii_lowercase());
+    }
+    name
+}
+
+fn template_by_name(name: &str) -> Option<NodeTemplate> {
+    NodeTemplate::iter().find(|template| function_name(*template) == name)
+}
+
+/// The name of an input as an argument, e.g. `bounds_lower`.
+fn argument_name(input: &str) -> String {
+    input.replace(' ', "_")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,315 @@
+use super::{
+    lexer::{tokenize, Token},
+    DslError, Position, Result,
+};
+
+pub struct Statement {
+    pub name: String,
+    pub position: Position,
+    pub value: Expr,
+}
+
+pub struct Expr {
+    pub kind: ExprKind,
+    pub position: Position,
+}
+
+pub enum ExprKind {
+    Number(f64),
+    String(String),
+    Ident(String),
+    List(Vec<Expr>),
+    Call {
+        function: String,
+        arguments: Vec<Argument>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Negate(Box<Expr>),
+    /// One of the other outputs of a node, like `worley().cell_value`.
+    Output {
+        node: Box<Expr>,
+        output: String,
+    },
+}
+
+pub struct Argument {
+    pub name: Option<(String, Position)>,
+    pub value: Expr,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// Parses the statements of a script.
+///
+/// ```text
+/// script    = { statement } ;
+/// statement = ident "=" expr ";" ;
+/// expr      = term { ( "+" | "-" ) term } ;
+/// term      = unary { ( "*" | "/" ) unary } ;
+/// unary     = "-" unary | output ;
+/// output    = primary { "." ident } ;
+/// primary   = number | string | ident [ "(" arguments ")" ] | "(" expr ")"
+///           | "[" [ expr { "," expr } ] "]" ;
+/// arguments = [ argument { "," argument } ] ;
+/// argument  = [ ident ":" ] expr ;
+/// ```
+pub fn parse_script(source: &str) -> Result<Vec<Statement>> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        index: 0,
+    };
+    let mut statements = Vec::new();
+
+    while parser.peek() != &Token::Eof {
+        statements.push(parser.statement()?);
+    }
+
+    Ok(statements)
+}
+
+/// Parses a single expression, like the formula of an expression node.
+pub fn parse_expression(source: &str) -> Result<Expr> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        index: 0,
+    };
+    let expr = parser.expr()?;
+    if parser.peek() != &Token::Eof {
+        return Err(parser.unexpected("an operator"));
+    }
+
+    Ok(expr)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Position)>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index].0
+    }
+
+    fn position(&self) -> Position {
+        self.tokens[self.index].1
+    }
+
+    fn advance(&mut self) -> (Token, Position) {
+        let token = self.tokens[self.index].clone();
+        if token.0 != Token::Eof {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&token.describe()))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> DslError {
+        DslError::new(
+            self.position(),
+            format!("Expected {expected}, found {}", self.peek().describe()),
+        )
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        let position = self.position();
+        let name = match self.advance().0 {
+            Token::Ident(name) => name,
+            _ => {
+                return Err(DslError::new(
+                    position,
+                    "Expected a statement like 'name = ...'",
+                ))
+            }
+        };
+        self.expect(&Token::Equals)?;
+        let value = self.expr()?;
+        // The semicolon is optional after the last statement.
+        if self.peek() != &Token::Eof {
+            self.expect(&Token::Semicolon)?;
+        }
+
+        Ok(Statement {
+            name,
+            position,
+            value,
+        })
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut left = self.term()?;
+        loop {
+            let operator = match self.peek() {
+                Token::Plus => BinaryOperator::Add,
+                Token::Minus => BinaryOperator::Subtract,
+                _ => return Ok(left),
+            };
+            left = self.binary(left, operator, Self::term)?;
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = match self.peek() {
+                Token::Star => BinaryOperator::Multiply,
+                Token::Slash => BinaryOperator::Divide,
+                _ => return Ok(left),
+            };
+            left = self.binary(left, operator, Self::unary)?;
+        }
+    }
+
+    fn binary(
+        &mut self,
+        left: Expr,
+        operator: BinaryOperator,
+        right: fn(&mut Self) -> Result<Expr>,
+    ) -> Result<Expr> {
+        let position = self.advance().1;
+        let right = right(self)?;
+
+        Ok(Expr {
+            kind: ExprKind::Binary {
+                operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+            position,
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let position = self.position();
+        if self.eat(&Token::Minus) {
+            let value = self.unary()?;
+            Ok(Expr {
+                kind: ExprKind::Negate(Box::new(value)),
+                position,
+            })
+        } else {
+            self.output()
+        }
+    }
+
+    fn output(&mut self) -> Result<Expr> {
+        let mut node = self.primary()?;
+        while self.peek() == &Token::Dot {
+            let position = self.advance().1;
+            let output = match self.peek().clone() {
+                Token::Ident(output) => {
+                    self.advance();
+                    output
+                }
+                _ => return Err(self.unexpected("the name of an output")),
+            };
+            node = Expr {
+                kind: ExprKind::Output {
+                    node: Box::new(node),
+                    output,
+                },
+                position,
+            };
+        }
+
+        Ok(node)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let position = self.position();
+        let kind = match self.peek().clone() {
+            Token::Number(number) => {
+                self.advance();
+                ExprKind::Number(number)
+            }
+            Token::String(string) => {
+                self.advance();
+                ExprKind::String(string)
+            }
+            Token::Ident(name) => {
+                self.advance();
+                if self.eat(&Token::LeftParen) {
+                    ExprKind::Call {
+                        function: name,
+                        arguments: self.arguments()?,
+                    }
+                } else {
+                    ExprKind::Ident(name)
+                }
+            }
+            Token::LeftParen => {
+                self.advance();
+                let expr = self.expr()?;
+                self.expect(&Token::RightParen)?;
+                return Ok(expr);
+            }
+            Token::LeftBracket => {
+                self.advance();
+                let mut values = Vec::new();
+                while !self.eat(&Token::RightBracket) {
+                    values.push(self.expr()?);
+                    if !self.eat(&Token::Comma) {
+                        self.expect(&Token::RightBracket)?;
+                        break;
+                    }
+                }
+                ExprKind::List(values)
+            }
+            _ => return Err(self.unexpected("a value")),
+        };
+
+        Ok(Expr { kind, position })
+    }
+
+    /// Parses the arguments of a call after the opening parenthesis.
+    fn arguments(&mut self) -> Result<Vec<Argument>> {
+        let mut arguments = Vec::new();
+        while !self.eat(&Token::RightParen) {
+            let next = self.tokens.get(self.index + 1).map(|(token, _)| token);
+            let name = match (self.peek().clone(), next) {
+                (Token::Ident(name), Some(Token::Colon)) => {
+                    let position = self.advance().1;
+                    self.advance();
+                    Some((name, position))
+                }
+                _ => None,
+            };
+            arguments.push(Argument {
+                name,
+                value: self.expr()?,
+            });
+            if !self.eat(&Token::Comma) {
+                self.expect(&Token::RightParen)?;
+                break;
+            }
+        }
+
+        Ok(arguments)
+    }
+}
@@ -0,0 +1,463 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::bail;
+use egui_node_graph::{InputId, NodeId, NodeTemplateTrait};
+
+use crate::noise_graph::{
+    graph_ext::GraphExt,
+    node_attribute::{NodeAttribute, Operator},
+    node_template::{NodeTemplate, DEFAULT_NODE_NAME},
+    parameters::ExposedParameters,
+    NoiseGraph, NoiseGraphResource, NoiseGraphState,
+};
+
+use super::{argument_name, function_name};
+
+/// Binding strength of the printed expressions, used to decide where
+/// parentheses are needed.
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const UNARY: u8 = 3;
+const PRIMARY: u8 = 4;
+
+/// Prints a graph as a script which `parse` turns back into the same graph.
+///
+/// Unnamed nodes which are used exactly once are written inline, all other
+/// nodes get a statement of their own, ordered so that every node is defined
+/// before it's used. Fails if an input is NaN, which scripts have no way to
+/// write.
+pub fn print(resource: &NoiseGraphResource) -> anyhow::Result<String> {
+    let graph = &resource.state.graph;
+    for node_id in graph.iter_nodes() {
+        for (name, input_id) in &graph[node_id].inputs {
+            if graph.connection(*input_id).is_none() && is_nan(graph[*input_id].value()) {
+                bail!(
+                    "The input '{name}' of '{}' is not a number",
+                    graph.node_name(node_id)
+                );
+            }
+        }
+    }
+    let active_node = resource
+        .user_state
+        .active_node
+        .filter(|node_id| graph.nodes.contains_key(*node_id));
+
+    let mut uses = HashMap::new();
+    for (_, output_id) in graph.iter_connections() {
+        *uses.entry(graph[output_id].node).or_insert(0) += 1;
+    }
+    let inline = graph
+        .iter_nodes()
+        .filter(|node_id| {
+            Some(*node_id) != active_node
+                && uses.get(node_id) == Some(&1)
+                && graph.node_name(*node_id) == DEFAULT_NODE_NAME
+        })
+        .collect();
+
+    let mut printer = Printer {
+        graph,
+        exposed: &resource.user_state.exposed,
+        inline,
+        identifiers: HashMap::new(),
+        defaults: HashMap::new(),
+    };
+
+    let remaining = graph
+        .iter_nodes()
+        .filter(|node_id| !resource.state.node_order.contains(node_id));
+    let order = resource
+        .state
+        .node_order
+        .iter()
+        .copied()
+        .filter(|node_id| graph.nodes.contains_key(*node_id))
+        .chain(remaining)
+        .filter(|node_id| Some(*node_id) != active_node)
+        .chain(active_node);
+
+    let mut statements = Vec::new();
+    let mut visited = HashSet::new();
+    for node_id in order {
+        printer.collect_statements(node_id, &mut visited, &mut statements);
+    }
+
+    let mut taken = HashSet::new();
+    for &node_id in &statements {
+        let identifier = unique(identifier(&graph.node_name(node_id)), &mut taken);
+        printer.identifiers.insert(node_id, identifier);
+    }
+
+    let mut script = String::new();
+    for &node_id in &statements {
+        let identifier = printer.identifiers[&node_id].clone();
+        let expr = printer.node(node_id, Some(&identifier)).text;
+        script.push_str(&format!("{identifier} = {expr};\n"));
+    }
+
+    // The last statement is the active node, so refer to it once more if
+    // other nodes had to be defined after it.
+    if let Some(active_node) = active_node.filter(|node_id| statements.last() != Some(node_id)) {
+        let out = unique("out".into(), &mut taken);
+        script.push_str(&format!("{out} = {};\n", printer.identifiers[&active_node]));
+    }
+
+    Ok(script)
+}
+
+struct Printer<'a> {
+    graph: &'a NoiseGraph,
+    exposed: &'a ExposedParameters,
+    inline: HashSet<NodeId>,
+    identifiers: HashMap<NodeId, String>,
+    /// The default values of the inputs of each template, keyed by its name.
+    defaults: HashMap<String, HashMap<String, NodeAttribute>>,
+}
+
+struct Printed {
+    text: String,
+    precedence: u8,
+    /// Whether the expression is a plain number, which the parser would fold
+    /// together with other numbers instead of creating a node.
+    literal: bool,
+}
+
+impl Printed {
+    fn expr(text: String, precedence: u8) -> Self {
+        Self {
+            text,
+            precedence,
+            literal: false,
+        }
+    }
+
+    fn literal(value: f64) -> Self {
+        let text = number(value);
+        Self {
+            precedence: if text.starts_with('-') {
+                UNARY
+            } else {
+                PRIMARY
+            },
+            text,
+            literal: true,
+        }
+    }
+
+    /// The text, in parentheses if it binds weaker than required.
+    fn at(&self, precedence: u8) -> String {
+        if self.precedence < precedence {
+            format!("({})", self.text)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+impl<'a> Printer<'a> {
+    /// Collects the nodes which need a statement, dependencies first.
+    fn collect_statements(
+        &self,
+        node_id: NodeId,
+        visited: &mut HashSet<NodeId>,
+        statements: &mut Vec<NodeId>,
+    ) {
+        if !visited.insert(node_id) {
+            return;
+        }
+
+        for input_id in self.graph[node_id].input_ids() {
+            if let Some(output_id) = self.graph.connection(input_id) {
+                self.collect_statements(self.graph[output_id].node, visited, statements);
+            }
+        }
+
+        if !self.inline.contains(&node_id) {
+            statements.push(node_id);
+        }
+    }
+
+    /// Prints a node, either inline or as the value of the statement with the
+    /// given identifier.
+    fn node(&mut self, node_id: NodeId, identifier: Option<&str>) -> Printed {
+        let graph = self.graph;
+        let needs_name =
+            identifier.is_some_and(|identifier| graph.node_name(node_id) != identifier);
+
+        // Exposed inputs are written out in full, so they can be wrapped in
+        // `expose`.
+        let exposes = graph[node_id]
+            .inputs
+            .iter()
+            .any(|(name, _)| self.exposed.get(node_id, name).is_some());
+        if !needs_name && !exposes {
+            if let Some(printed) = self.operator(node_id, identifier.is_none()) {
+                return printed;
+            }
+        }
+
+        let template = graph[node_id].user_data.template;
+        let mut arguments = Vec::new();
+        let mut positional = true;
+
+        for (name, input_id) in &graph[node_id].inputs {
+            if name == "name" {
+                continue;
+            }
+
+            let exposed = self.exposed.get(node_id, name);
+            let value = match graph.connection(*input_id) {
+                Some(_) => Some(self.input(*input_id).text),
+                None if exposed.is_some() => literal(graph[*input_id].value()),
+                None => {
+                    let value = graph[*input_id].value();
+                    let default = self.default(template, name);
+                    literal(value).filter(|literal| Some(literal) != default.as_ref())
+                }
+            };
+            let value = match exposed {
+                Some(parameter) => {
+                    value.map(|value| format!("expose({}, {value})", string(&parameter.name)))
+                }
+                None => value,
+            };
+
+            match value {
+                Some(value) if positional => arguments.push(value),
+                Some(value) => arguments.push(format!("{}: {value}", argument_name(name))),
+                None => positional = false,
+            }
+        }
+
+        if needs_name {
+            arguments.push(format!("name: {}", string(&graph.node_name(node_id))));
+        }
+
+        Printed::expr(
+            format!("{}({})", function_name(template), arguments.join(", ")),
+            PRIMARY,
+        )
+    }
+
+    /// Prints nodes which have an operator, like `add` as `a + b`. Constants
+    /// are only written as plain numbers inline, since a statement like
+    /// `a = 1` defines a number rather than a node.
+    fn operator(&mut self, node_id: NodeId, inline: bool) -> Option<Printed> {
+        let graph = self.graph;
+        let node = &graph[node_id];
+        let input = |name: &str| node.get_input(name).ok();
+        let connected = |input_id: InputId| graph.connection(input_id).map(|_| input_id);
+
+        match node.user_data.template {
+            NodeTemplate::Constant if inline => match graph[input("value")?].value() {
+                NodeAttribute::F64(value) => Some(Printed::literal(*value)),
+                _ => None,
+            },
+            NodeTemplate::Add => {
+                let left = self.input(connected(input("source 1")?)?);
+                let right_id = connected(input("source 2")?)?;
+
+                // `a - b` is parsed as `a + -b`.
+                let right_node = graph[graph.connection(right_id)?].node;
+                if self.is_inline(right_node, NodeTemplate::Negate) {
+                    let negated = graph[right_node].get_input("source").ok();
+                    if let Some(negated) = negated.and_then(connected) {
+                        let right = self.input(negated);
+                        return binary(left, "-", right, SUM);
+                    }
+                }
+
+                binary(left, "+", self.input(right_id), SUM)
+            }
+            NodeTemplate::Multiply => {
+                let left = self.input(connected(input("source 1")?)?);
+                let right = self.input(connected(input("source 2")?)?);
+                binary(left, "*", right, PRODUCT)
+            }
+            NodeTemplate::Negate => {
+                let source = self.input(connected(input("source")?)?);
+                (!source.literal).then(|| Printed::expr(format!("-{}", source.at(UNARY)), UNARY))
+            }
+            NodeTemplate::Arithmetic => {
+                let operator = match graph[input("operator")?].value() {
+                    NodeAttribute::Operator(operator) => *operator,
+                    _ => return None,
+                };
+                let a = self.operand(input("a")?)?;
+                let b = self.operand(input("b")?)?;
+                let (symbol, precedence) = match operator {
+                    Operator::Add => ("+", SUM),
+                    Operator::Subtract => ("-", SUM),
+                    Operator::Multiply => ("*", PRODUCT),
+                    Operator::Divide => ("/", PRODUCT),
+                };
+                binary(a, symbol, b, precedence)
+            }
+            _ => None,
+        }
+    }
+
+    fn is_inline(&self, node_id: NodeId, template: NodeTemplate) -> bool {
+        self.inline.contains(&node_id)
+            && function_name(self.graph[node_id].user_data.template) == function_name(template)
+    }
+
+    /// Prints a number input of an arithmetic node, connected or constant.
+    fn operand(&mut self, input_id: InputId) -> Option<Printed> {
+        if self.graph.connection(input_id).is_some() {
+            return Some(self.input(input_id));
+        }
+
+        match self.graph[input_id].value() {
+            NodeAttribute::F64(value) => Some(Printed::literal(*value)),
+            _ => None,
+        }
+    }
+
+    /// Prints what a connected input is connected to.
+    fn input(&mut self, input_id: InputId) -> Printed {
+        let output_id = self.graph.connection(input_id).expect("Input is connected");
+        let node_id = self.graph[output_id].node;
+
+        let printed = match self.identifiers.get(&node_id) {
+            Some(identifier) => Printed::expr(identifier.clone(), PRIMARY),
+            None => self.node(node_id, None),
+        };
+        match self.graph.output_name(output_id) {
+            "out" => printed,
+            output => Printed::expr(
+                format!("{}.{}", printed.at(PRIMARY), argument_name(output)),
+                PRIMARY,
+            ),
+        }
+    }
+
+    fn default(&mut self, template: NodeTemplate, input: &str) -> Option<String> {
+        let defaults = self
+            .defaults
+            .entry(template.to_string())
+            .or_insert_with(|| default_values(template));
+        defaults.get(input).and_then(literal)
+    }
+}
+
+fn default_values(template: NodeTemplate) -> HashMap<String, NodeAttribute> {
+    let mut graph = NoiseGraph::new();
+    let mut user_state = NoiseGraphState::default();
+    let node_id = graph.add_node(
+        String::new(),
+        template.user_data(&mut user_state),
+        |graph, node_id| template.build_node(graph, &mut user_state, node_id),
+    );
+
+    graph[node_id]
+        .inputs
+        .iter()
+        .map(|(name, input_id)| (name.clone(), graph[*input_id].value().clone()))
+        .collect()
+}
+
+/// Prints `left symbol right` for left associative operators, unless both
+/// sides are plain numbers, which would be folded into a single number.
+fn binary(left: Printed, symbol: &str, right: Printed, precedence: u8) -> Option<Printed> {
+    if left.literal && right.literal {
+        return None;
+    }
+
+    Some(Printed::expr(
+        format!(
+            "{} {symbol} {}",
+            left.at(precedence),
+            right.at(precedence + 1)
+        ),
+        precedence,
+    ))
+}
+
+/// Prints a constant value, `None` for inputs without one.
+fn literal(value: &NodeAttribute) -> Option<String> {
+    match value {
+        NodeAttribute::F64(value) => Some(number(*value)),
+        NodeAttribute::Usize(value) => Some(value.to_string()),
+        NodeAttribute::Bool(value) => Some(value.to_string()),
+        NodeAttribute::NoiseType(noise_type) => Some(noise_type.to_string()),
+        NodeAttribute::Operator(operator) => Some(operator.to_string()),
+        NodeAttribute::ReturnType(return_type) => Some(return_type.to_string()),
+        NodeAttribute::DistanceFunction(distance_function) => Some(distance_function.to_string()),
+        NodeAttribute::Name(name) | NodeAttribute::Formula(name) => Some(string(name)),
+        NodeAttribute::Vec { values, .. } => {
+            let values: Vec<String> = values.iter().filter_map(literal).collect();
+            Some(format!("[{}]", values.join(", ")))
+        }
+        NodeAttribute::F64Tuple(first, second) => {
+            Some(format!("[{}, {}]", number(*first), number(*second)))
+        }
+        NodeAttribute::Perlin
+        | NodeAttribute::ScaleBias
+        | NodeAttribute::NoiseFunction(_)
+        | NodeAttribute::VectorFunction(_)
+        | NodeAttribute::NoInput => None,
+    }
+}
+
+/// Prints a number so the lexer reads back the same value. Infinities are
+/// written as a number too large for an `f64`, which parses to infinity.
+fn number(value: f64) -> String {
+    if value == f64::INFINITY {
+        "1e999".into()
+    } else if value == f64::NEG_INFINITY {
+        "-1e999".into()
+    } else {
+        value.to_string()
+    }
+}
+
+fn is_nan(value: &NodeAttribute) -> bool {
+    match value {
+        NodeAttribute::F64(value) => value.is_nan(),
+        NodeAttribute::F64Tuple(first, second) => first.is_nan() || second.is_nan(),
+        NodeAttribute::Vec { values, .. } => values.iter().any(is_nan),
+        _ => false,
+    }
+}
+
+fn string(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// Turns a node name into a valid identifier.
+fn identifier(name: &str) -> String {
+    let identifier: String = name
+        .chars()
+        .map(|char| {
+            if char.is_alphanumeric() || char == '_' {
+                char
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if identifier.starts_with(|char: char| char.is_alphabetic() || char == '_') {
+        identifier
+    } else {
+        format!("_{identifier}")
+    }
+}
+
+fn unique(identifier: String, taken: &mut HashSet<String>) -> String {
+    let mut unique = identifier.clone();
+    let mut counter = 1;
+    while !taken.insert(unique.clone()) {
+        counter += 1;
+        unique = format!("{identifier}_{counter}");
+    }
+    unique
+}
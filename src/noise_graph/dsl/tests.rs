@@ -0,0 +1,119 @@
+use noise::NoiseFn;
+
+use super::{parse, print, Position};
+use crate::noise_graph::{
+    graph_ext::{EvaluationCache, GraphExt},
+    node_attribute::NodeAttribute,
+    NoiseGraphResource,
+};
+
+/// The values of the active node at a few points.
+fn samples(resource: &NoiseGraphResource) -> Vec<f64> {
+    let noise = resource
+        .state
+        .graph
+        .evaluate_cached(
+            resource.user_state.active_node.unwrap(),
+            &mut EvaluationCache::default(),
+        )
+        .and_then(NodeAttribute::try_to_noise_function)
+        .unwrap();
+
+    [[0.3, 0.7], [12.5, -4.25], [-100.1, 33.3]]
+        .iter()
+        .map(|point| noise.get(*point))
+        .collect()
+}
+
+/// Prints a script and parses it again, which has to print the same script
+/// and give the same values.
+fn assert_round_trip(source: &str) -> String {
+    let resource = parse(source).unwrap();
+    let printed = print(&resource).unwrap();
+    let parsed = parse(&printed).unwrap_or_else(|e| panic!("{e} in\n{printed}"));
+
+    assert_eq!(print(&parsed).unwrap(), printed);
+    assert_eq!(samples(&parsed), samples(&resource), "{printed}");
+    printed
+}
+
+/// The position of the error of a script and its message.
+fn error(source: &str) -> (Position, String) {
+    let error = parse(source).err().expect("the script should fail");
+    (error.position, error.message)
+}
+
+#[test]
+fn printed_scripts_parse_to_the_same_graph() {
+    assert_round_trip(
+        "# Mountains rise out of gentle plains.\n\
+         mountains = ridged_multi(octaves: 6, frequency: 0.002) * 300;\n\
+         plains = billow(frequency: 0.001) * 20 - 1.5;\n\
+         cells = worley(frequency: 0.05).cell_value;\n\
+         land = select(mountains, plains + cells, control: fbm(frequency: 0.0005), bounds_lower: -0.25);\n\
+         out = curve(land, control_points: [[-1, -1], [0, 0.25], [0.5, 1e3], [1, 1]]);",
+    );
+    assert_round_trip("sea = float(value: expose(\"Sea level\", -0.3));\nout = sea + x();");
+    assert_round_trip("out = expression(\"a * b + sin(x)\", perlin(), -billow());");
+}
+
+#[test]
+fn infinite_numbers_are_printed_readably() {
+    let mut resource = parse("limit = float(value: 1);\nout = limit + x();").unwrap();
+    let graph = &mut resource.state.graph;
+    let limit = graph
+        .iter_nodes()
+        .find(|node_id| graph.node_name(*node_id) == "limit")
+        .unwrap();
+    let value = graph[limit].get_input("value").unwrap();
+
+    for infinity in [f64::INFINITY, f64::NEG_INFINITY] {
+        resource.state.graph[value].value = NodeAttribute::F64(infinity);
+        let printed = print(&resource).unwrap();
+        let parsed = parse(&printed).unwrap_or_else(|e| panic!("{e} in\n{printed}"));
+        assert_eq!(samples(&parsed), samples(&resource), "{printed}");
+    }
+
+    resource.state.graph[value].value = NodeAttribute::F64(f64::NAN);
+    let error = print(&resource).unwrap_err().to_string();
+    assert_eq!(error, "The input 'value' of 'limit' is not a number");
+}
+
+#[test]
+fn errors_point_at_their_line_and_column() {
+    let cases = [
+        ("a = perlin();\n  b = $;", 2, 7, "Unexpected '$'"),
+        (
+            "a = perlin(frequency: \"fast);",
+            1,
+            23,
+            "Unterminated string",
+        ),
+        (
+            "a = perlin(frequency: 1.2.3);",
+            1,
+            23,
+            "Invalid number '1.2.3'",
+        ),
+        (
+            "a = perlin();\nb = perlin(speed: 2);",
+            2,
+            12,
+            "'perlin' has no input 'speed'",
+        ),
+        (
+            "a = perlin();\n\nb = mystery();",
+            3,
+            5,
+            "Unknown node 'mystery'",
+        ),
+    ];
+
+    for (source, line, column, message) in cases {
+        assert_eq!(
+            error(source),
+            (Position { line, column }, message.to_string()),
+            "{source}"
+        );
+    }
+}
@@ -0,0 +1,1120 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use egui_node_graph::{InputId, NodeId, OutputId};
+
+use super::{
+    connection_type::ConnectionType,
+    dsl::{function_name, Formula},
+    graph_ext::{check_cycle, GraphExt},
+    node_attribute::{NodeAttribute, NoiseType, WorleyReturnType},
+    node_template::{check_step, clamp_octaves, NodeTemplate, Normalize, DEFAULT_NODE_NAME},
+    program::compile_input,
+    NoiseGraph,
+};
+
+/// Turns the noise function produced by a node into Rust source code, which
+/// builds the same composition of `noise` functions as evaluating the graph
+/// and therefore gives the exact same values.
+///
+/// The code consists of a single function named after the node. Every node
+/// becomes a variable, nodes used more than once are shared through a
+/// [`DynNoiseFn`](super::DynNoiseFn), just like the evaluator does. Number
+/// nodes are replaced by the numbers they compute. The code only depends on
+/// the `noise` crate, the types of the editor it uses are included as a
+/// `runtime` module at the end.
+pub fn export_rust(graph: &NoiseGraph, node_id: NodeId) -> anyhow::Result<String> {
+    if graph[node_id]
+        .outputs(graph)
+        .any(|output| output.typ == ConnectionType::Vec2)
+    {
+        anyhow::bail!(
+            "'{}' outputs a vector, not a noise function",
+            graph.node_name(node_id)
+        );
+    }
+
+    let mut exporter = Exporter {
+        graph,
+        uses: HashMap::new(),
+        variables: HashMap::new(),
+        taken: HashSet::new(),
+        statements: Vec::new(),
+        imports: BTreeSet::from(["noise::NoiseFn"]),
+        path: Vec::new(),
+    };
+    exporter.count_uses(node_id, &mut HashSet::new());
+    let output = exporter.node(node_id)?;
+
+    let mut code = format!(
+        "// Exported from the noise graph node '{}'.\n\n",
+        graph.node_name(node_id)
+    );
+    code.push_str(&imports(&exporter.imports));
+    code.push_str(&format!(
+        "\npub fn {}() -> impl NoiseFn<f64, 2> {{\n",
+        node_identifier(graph, node_id)
+    ));
+    for statement in &exporter.statements {
+        code.push_str(&indent(statement));
+        code.push('\n');
+    }
+    code.push_str(&indent(&output));
+    code.push_str("\n}\n");
+    code.push_str(&runtime(&exporter.imports));
+
+    Ok(code)
+}
+
+struct Exporter<'a> {
+    graph: &'a NoiseGraph,
+    /// How many inputs each node is connected to.
+    uses: HashMap<NodeId, usize>,
+    /// The variables holding the nodes which were already exported.
+    variables: HashMap<NodeId, String>,
+    taken: HashSet<String>,
+    statements: Vec<String>,
+    imports: BTreeSet<&'static str>,
+    path: Vec<NodeId>,
+}
+
+impl<'a> Exporter<'a> {
+    fn count_uses(&mut self, node_id: NodeId, visited: &mut HashSet<NodeId>) {
+        if !visited.insert(node_id) {
+            return;
+        }
+
+        for input_id in self.graph[node_id].input_ids() {
+            if let Some(output_id) = self.graph.connection(input_id) {
+                let source = self.graph[output_id].node;
+                *self.uses.entry(source).or_default() += 1;
+                self.count_uses(source, visited);
+            }
+        }
+    }
+
+    /// Exports a node and returns the expression which refers to it.
+    fn node(&mut self, node_id: NodeId) -> anyhow::Result<String> {
+        if let Some(variable) = self.variables.get(&node_id) {
+            return Ok(format!("{variable}.clone()"));
+        }
+
+        check_cycle(self.graph, &self.path, node_id)?;
+        self.path.push(node_id);
+        let expression = self.expression(node_id);
+        self.path.pop();
+        let expression = expression?;
+
+        if self.path.is_empty() {
+            return Ok(expression);
+        }
+
+        let shared = self.uses.get(&node_id).copied().unwrap_or(0) > 1;
+        let variable = self.variable(node_id);
+        if shared {
+            let expression = if self.outputs_vector(node_id) {
+                self.imports.insert("self::runtime::dyn_fn::DynVectorFn");
+                call("DynVectorFn::new", [expression])
+            } else {
+                self.imports.insert("self::runtime::dyn_fn::DynNoiseFn");
+                call("DynNoiseFn::new", [expression])
+            };
+            self.statements
+                .push(format!("let {variable} = {expression};"));
+            self.variables.insert(node_id, variable.clone());
+            Ok(format!("{variable}.clone()"))
+        } else {
+            self.statements
+                .push(format!("let {variable} = {expression};"));
+            Ok(variable)
+        }
+    }
+
+    /// Exports one of the outputs of a node. Outputs besides "out" are
+    /// written inline rather than shared through a variable.
+    fn output(&mut self, output_id: OutputId) -> anyhow::Result<String> {
+        let node_id = self.graph[output_id].node;
+        let name = self.graph.output_name(output_id);
+        if name == "out" {
+            return self.node(node_id);
+        }
+
+        check_cycle(self.graph, &self.path, node_id)?;
+        self.path.push(node_id);
+        let expression = match self.graph[node_id].user_data.template {
+            NodeTemplate::Worley if name == "cell value" => {
+                self.worley(node_id, WorleyReturnType::Value)
+            }
+            _ => Err(anyhow::anyhow!(
+                "'{}' has no output '{name}'",
+                self.graph.node_name(node_id)
+            )),
+        };
+        self.path.pop();
+        expression
+    }
+
+    fn outputs_vector(&self, node_id: NodeId) -> bool {
+        self.graph[node_id]
+            .outputs(self.graph)
+            .any(|output| output.typ == ConnectionType::Vec2)
+    }
+
+    fn variable(&mut self, node_id: NodeId) -> String {
+        let name = node_identifier(self.graph, node_id);
+        let mut variable = name.clone();
+        let mut counter = 1;
+        while !self.taken.insert(variable.clone()) {
+            counter += 1;
+            variable = format!("{name}_{counter}");
+        }
+        variable
+    }
+
+    /// The expression constructing the noise function of a node, with the
+    /// same setters in the same order as the node's `evaluate`.
+    fn expression(&mut self, node_id: NodeId) -> anyhow::Result<String> {
+        let template = self.graph[node_id].user_data.template;
+        let expression = match template {
+            NodeTemplate::Abs => {
+                let source = self.noise(node_id, "source")?;
+                self.call("Abs::new", [source])
+            }
+            NodeTemplate::Add => {
+                let sources = self.noises(node_id, ["source 1", "source 2"])?;
+                self.call("Add::new", sources)
+            }
+            NodeTemplate::Arithmetic | NodeTemplate::Float => anyhow::bail!(
+                "'{}' outputs a number, not a noise function",
+                self.graph.node_name(node_id)
+            ),
+            NodeTemplate::BasicMulti => self.fractal(node_id, "BasicMulti")?,
+            NodeTemplate::Billow => self.fractal(node_id, "Billow")?,
+            NodeTemplate::Blend => {
+                let sources = self.noises(node_id, ["source 1", "source 2", "control"])?;
+                self.call("Blend::new", sources)
+            }
+            NodeTemplate::Cache => {
+                let source = self.noise(node_id, "source")?;
+                self.imports.insert("self::runtime::cache::SyncCache");
+                format!("SyncCache::new({source})")
+            }
+            NodeTemplate::Checkerboard => {
+                let size = self.attribute(node_id, "size")?.try_to_usize()?;
+                self.call("Checkerboard::new", [size.to_string()])
+            }
+            NodeTemplate::Clamp => {
+                let source = self.noise(node_id, "source")?;
+                let lower = self.number(node_id, "bounds lower")?;
+                let upper = self.number(node_id, "bounds upper")?;
+                chain(
+                    self.call("Clamp::new", [source]),
+                    [format!("set_bounds({lower}, {upper})")],
+                )
+            }
+            NodeTemplate::Constant => {
+                let value = self.number(node_id, "value")?;
+                self.call("Constant::new", [value])
+            }
+            NodeTemplate::Cos => self.shaping(node_id, "Cos", ["source", "frequency", "phase"])?,
+            NodeTemplate::Curl => self.gradient(node_id, "Curl")?,
+            NodeTemplate::Curve => {
+                let source = self.noise(node_id, "source")?;
+                let control_points = self.attribute(node_id, "control points")?.try_to_vec()?;
+                if control_points.len() < 4 {
+                    anyhow::bail!("Curve needs at least 4 control points");
+                }
+                let mut setters = Vec::new();
+                for control_point in control_points {
+                    let (input, output) = control_point.try_to_f64_tuple()?;
+                    setters.push(format!(
+                        "add_control_point({}, {})",
+                        number(input),
+                        number(output)
+                    ));
+                }
+                chain(self.call("Curve::new", [source]), setters)
+            }
+            NodeTemplate::Cylinders => {
+                let frequency = self.number(node_id, "frequency")?;
+                chain(
+                    self.call("Cylinders::new", []),
+                    [format!("set_frequency({frequency})")],
+                )
+            }
+            NodeTemplate::Displace => {
+                let sources = self.noises(node_id, ["source", "x", "y", "z", "u"])?;
+                self.call("Displace::new", sources)
+            }
+            NodeTemplate::DistanceToLine => {
+                let start_x = self.number(node_id, "start x")?;
+                let start_y = self.number(node_id, "start y")?;
+                let end_x = self.number(node_id, "end x")?;
+                let end_y = self.number(node_id, "end y")?;
+                self.shape(format!(
+                    "DistanceToLine {{ start: [{start_x}, {start_y}], end: [{end_x}, {end_y}] }}"
+                ))
+            }
+            NodeTemplate::DistanceToPoint => {
+                let x = self.number(node_id, "x")?;
+                let y = self.number(node_id, "y")?;
+                self.shape(format!("DistanceToPoint {{ point: [{x}, {y}] }}"))
+            }
+            NodeTemplate::DomainWarp => {
+                let source = self.noise(node_id, "source")?;
+                let warp = self.vector(node_id, "warp")?;
+                let strength = self.number(node_id, "strength")?;
+                let iterations = self.attribute(node_id, "iterations")?.try_to_usize()?;
+                self.imports
+                    .insert("self::runtime::domain_warp::DomainWarp");
+                call(
+                    "DomainWarp::new",
+                    [source, warp, strength, iterations.to_string()],
+                )
+            }
+            NodeTemplate::ErodedFbm => {
+                let seed = self.seed(node_id)?;
+                let octaves = self.attribute(node_id, "octaves")?.try_to_usize()?;
+                let mut setters = vec![format!("set_octaves({octaves})")];
+                for name in ["gain", "lacunarity", "gradient strength"] {
+                    let value = self.number(node_id, name)?;
+                    setters.push(format!("set_{}({value})", name.replace(' ', "_")));
+                }
+                self.imports.insert("self::runtime::eroded_fbm::ErodedFbm");
+                let generator = chain(format!("ErodedFbm::new({seed})"), setters);
+                self.generator(node_id, generator)?
+            }
+            NodeTemplate::Exponent => {
+                let source = self.noise(node_id, "source")?;
+                let exponent = self.number(node_id, "exponent")?;
+                chain(
+                    self.call("Exponent::new", [source]),
+                    [format!("set_exponent({exponent})")],
+                )
+            }
+            NodeTemplate::Expression => {
+                let formula = self.attribute(node_id, "formula")?.try_to_formula()?;
+                let sources = formula
+                    .variables()
+                    .iter()
+                    .map(|variable| self.noise(node_id, variable))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                self.expression_fn(&formula, sources)
+            }
+            NodeTemplate::Fbm => self.fractal(node_id, "Fbm")?,
+            NodeTemplate::Gradient => self.gradient(node_id, "Gradient")?,
+            NodeTemplate::HybridMulti => self.fractal(node_id, "HybridMulti")?,
+            NodeTemplate::Lerp => {
+                self.shaping(node_id, "Lerp", ["source 1", "source 2", "factor"])?
+            }
+            NodeTemplate::LinearGradient => {
+                let start_x = self.number(node_id, "start x")?;
+                let start_y = self.number(node_id, "start y")?;
+                let end_x = self.number(node_id, "end x")?;
+                let end_y = self.number(node_id, "end y")?;
+                let falloff = self.number(node_id, "falloff")?;
+                self.shape(format!(
+                    "LinearGradient {{ start: [{start_x}, {start_y}], end: [{end_x}, {end_y}], \
+                     falloff: {falloff} }}"
+                ))
+            }
+            NodeTemplate::Max => {
+                let sources = self.noises(node_id, ["source 1", "source 2"])?;
+                self.call("Max::new", sources)
+            }
+            NodeTemplate::Min => {
+                let sources = self.noises(node_id, ["source 1", "source 2"])?;
+                self.call("Min::new", sources)
+            }
+            NodeTemplate::Modulo => self.shaping(node_id, "Modulo", ["source", "divisor"])?,
+            NodeTemplate::Multiply => {
+                let sources = self.noises(node_id, ["source 1", "source 2"])?;
+                self.call("Multiply::new", sources)
+            }
+            NodeTemplate::Negate => {
+                let source = self.noise(node_id, "source")?;
+                self.call("Negate::new", [source])
+            }
+            // The range is estimated while exporting, so the code only maps
+            // the range it found.
+            NodeTemplate::Normalize => {
+                let source = self.noise(node_id, "source")?;
+                let to_min = self.attribute(node_id, "to min")?.try_to_f64()?;
+                let to_max = self.attribute(node_id, "to max")?.try_to_f64()?;
+                let clamp = self.attribute(node_id, "clamp")?.try_to_bool()?;
+                let range = self.normalize_range(node_id)?;
+
+                let (scale, bias) = Normalize::scale_bias(range, to_min, to_max);
+                let scaled = chain(
+                    self.call("ScaleBias::new", [source]),
+                    [
+                        format!("set_scale({})", number(scale)),
+                        format!("set_bias({})", number(bias)),
+                    ],
+                );
+                if clamp {
+                    let (lower, upper) = Normalize::bounds(to_min, to_max);
+                    chain(
+                        self.call("Clamp::new", [scaled]),
+                        [format!("set_bounds({}, {})", number(lower), number(upper))],
+                    )
+                } else {
+                    scaled
+                }
+            }
+            NodeTemplate::OpenSimplex => {
+                let seed = self.seed(node_id)?;
+                let generator = self.call("OpenSimplex::new", [seed]);
+                self.generator(node_id, generator)?
+            }
+            NodeTemplate::Output => self.noise(node_id, "source")?,
+            NodeTemplate::Overlay => self.shaping(node_id, "Overlay", ["base", "blend"])?,
+            NodeTemplate::Perlin => {
+                let seed = self.seed(node_id)?;
+                let generator = self.call("Perlin::new", [seed]);
+                self.generator(node_id, generator)?
+            }
+            NodeTemplate::PerlinSurflet => {
+                let seed = self.seed(node_id)?;
+                let generator = self.call("PerlinSurflet::new", [seed]);
+                self.generator(node_id, generator)?
+            }
+            NodeTemplate::Power => {
+                let sources = self.noises(node_id, ["source 1", "source 2"])?;
+                self.call("Power::new", sources)
+            }
+            NodeTemplate::Quantize => self.shaping(node_id, "Quantize", ["source", "step size"])?,
+            NodeTemplate::RadialGradient => {
+                let center_x = self.number(node_id, "center x")?;
+                let center_y = self.number(node_id, "center y")?;
+                let radius = self.number(node_id, "radius")?;
+                let falloff = self.number(node_id, "falloff")?;
+                self.shape(format!(
+                    "RadialGradient {{ center: [{center_x}, {center_y}], radius: {radius}, \
+                     falloff: {falloff} }}"
+                ))
+            }
+            NodeTemplate::Remap => self.shaping(
+                node_id,
+                "Remap",
+                ["source", "from min", "from max", "to min", "to max"],
+            )?,
+            NodeTemplate::RidgedMulti => {
+                let attenuation = self.number(node_id, "attenuation")?;
+                self.fractal(
+                    node_id,
+                    &format!("RidgedMulti {{ attenuation: {attenuation} }}"),
+                )?
+            }
+            NodeTemplate::RotatePoint => {
+                let source = self.noise(node_id, "source")?;
+                let angles = self.numbers(node_id, ["x angle", "y angle", "z angle", "u angle"])?;
+                chain(
+                    self.call("RotatePoint::new", [source]),
+                    [format!("set_angles({})", angles.join(", "))],
+                )
+            }
+            NodeTemplate::Scale => {
+                let source = self.noise(node_id, "source")?;
+                let scale = self.number(node_id, "scale")?;
+                self.imports.insert("self::runtime::scale::Scale");
+                format!("Scale::new({source}, {scale})")
+            }
+            NodeTemplate::ScaleBias => {
+                let scale = self.number(node_id, "scale")?;
+                let bias = self.number(node_id, "bias")?;
+                let source = self.noise(node_id, "source")?;
+                chain(
+                    self.call("ScaleBias::new", [source]),
+                    [format!("set_scale({scale})"), format!("set_bias({bias})")],
+                )
+            }
+            NodeTemplate::ScalePoint => {
+                let source = self.noise(node_id, "source")?;
+                let scales = self.numbers(node_id, ["x", "y", "z", "u"])?;
+                chain(
+                    self.call("ScalePoint::new", [source]),
+                    [format!("set_all_scales({})", scales.join(", "))],
+                )
+            }
+            NodeTemplate::Screen => self.shaping(node_id, "Screen", ["base", "blend"])?,
+            NodeTemplate::Select => {
+                let sources = self.noises(node_id, ["source 1", "source 2", "control"])?;
+                let lower = self.number(node_id, "bounds lower")?;
+                let upper = self.number(node_id, "bounds upper")?;
+                let falloff = self.number(node_id, "falloff")?;
+                chain(
+                    self.call("Select::new", sources),
+                    [
+                        format!("set_bounds({lower}, {upper})"),
+                        format!("set_falloff({falloff})"),
+                    ],
+                )
+            }
+            NodeTemplate::Simplex => {
+                let seed = self.seed(node_id)?;
+                let generator = self.call("Simplex::new", [seed]);
+                self.generator(node_id, generator)?
+            }
+            NodeTemplate::Sin => self.shaping(node_id, "Sin", ["source", "frequency", "phase"])?,
+            NodeTemplate::Smoothstep => self.shaping(
+                node_id,
+                "Smoothstep",
+                ["source", "lower edge", "upper edge"],
+            )?,
+            NodeTemplate::SoftLight => self.shaping(node_id, "SoftLight", ["base", "blend"])?,
+            NodeTemplate::Step => self.shaping(node_id, "Step", ["source", "threshold"])?,
+            NodeTemplate::SuperSimplex => {
+                let seed = self.seed(node_id)?;
+                let generator = self.call("SuperSimplex::new", [seed]);
+                self.generator(node_id, generator)?
+            }
+            NodeTemplate::Terrace => {
+                let source = self.noise(node_id, "source")?;
+                let control_points = self.attribute(node_id, "control points")?.try_to_vec()?;
+                if control_points.len() < 2 {
+                    anyhow::bail!("Terrace requires at least 2 control points");
+                }
+                let mut setters = Vec::new();
+                for control_point in control_points {
+                    let control_point = control_point.try_to_f64()?;
+                    setters.push(format!("add_control_point({})", number(control_point)));
+                }
+                chain(self.call("Terrace::new", [source]), setters)
+            }
+            NodeTemplate::TranslatePoint => {
+                let source = self.noise(node_id, "source")?;
+                let translations = self.numbers(node_id, ["x", "y", "z", "u"])?;
+                chain(
+                    self.call("TranslatePoint::new", [source]),
+                    [format!("set_all_translations({})", translations.join(", "))],
+                )
+            }
+            NodeTemplate::Turbulence => {
+                let source = self.noise(node_id, "source")?;
+                let frequency = self.number(node_id, "frequency")?;
+                let power = self.number(node_id, "power")?;
+                let roughness = self.attribute(node_id, "roughness")?.try_to_usize()?;
+                let noise_type = self.noise_type(node_id)?;
+                self.imports.insert("noise::Turbulence");
+                chain(
+                    format!("Turbulence::<_, {noise_type}>::new({source})"),
+                    [
+                        format!("set_frequency({frequency})"),
+                        format!("set_power({power})"),
+                        format!("set_roughness({roughness})"),
+                    ],
+                )
+            }
+            NodeTemplate::Value => {
+                let seed = self.seed(node_id)?;
+                let generator = self.call("Value::new", [seed]);
+                self.generator(node_id, generator)?
+            }
+            NodeTemplate::Vector => {
+                let sources = self.noises(node_id, ["x", "y"])?;
+                self.imports.insert("self::runtime::vector::Vector");
+                call("Vector::new", sources)
+            }
+            NodeTemplate::Worley => {
+                let return_type = self
+                    .attribute(node_id, "return type")?
+                    .try_to_return_type()?;
+                self.worley(node_id, return_type)?
+            }
+            NodeTemplate::X => self.shape("X".into()),
+            NodeTemplate::Y => self.shape("Y".into()),
+        };
+
+        Ok(expression)
+    }
+
+    /// Calls a function of a `noise` type, e.g. `Add::new`.
+    fn call(
+        &mut self,
+        function: &'static str,
+        arguments: impl IntoIterator<Item = String>,
+    ) -> String {
+        let (ty, _) = function.split_once("::").expect("Function of a type");
+        self.imports.insert(noise_import(ty));
+
+        call(function, arguments)
+    }
+
+    /// Samples a generator at the frequency of its node and applies the
+    /// amplitude and offset, like `NodeEvaluator::output_generator`.
+    fn generator(&mut self, node_id: NodeId, generator: String) -> anyhow::Result<String> {
+        let frequency = self.number(node_id, "frequency")?;
+        let amplitude = self.number(node_id, "amplitude")?;
+        let offset = self.number(node_id, "offset")?;
+
+        let scaled = chain(
+            self.call("ScalePoint::new", [generator]),
+            [format!("set_scale({frequency})")],
+        );
+        Ok(chain(
+            self.call("ScaleBias::new", [scaled]),
+            [
+                format!("set_scale({amplitude})"),
+                format!("set_bias({offset})"),
+            ],
+        ))
+    }
+
+    fn seed(&mut self, node_id: NodeId) -> anyhow::Result<String> {
+        Ok(self.attribute(node_id, "seed")?.try_to_seed()?.to_string())
+    }
+
+    fn fractal(&mut self, node_id: NodeId, kind: &str) -> anyhow::Result<String> {
+        let octaves = clamp_octaves(self.attribute(node_id, "octaves")?.try_to_usize()?);
+        let input_id = self.graph[node_id].get_input("source")?;
+        let sources = match self.graph.connection(input_id) {
+            Some(_) => {
+                let source = self.noise(node_id, "source")?;
+                self.imports.insert("self::runtime::dyn_fn::DynNoiseFn");
+                format!("vec![{}; {octaves}]", call("DynNoiseFn::new", [source]))
+            }
+            None => {
+                let noise_type = self.noise_type(node_id)?;
+                format!("(0..{octaves}).map({noise_type}::new).collect()")
+            }
+        };
+        let mut setters = Vec::new();
+        for name in ["frequency", "lacunarity", "persistence"] {
+            let value = self.number(node_id, name)?;
+            setters.push(format!("set_{name}({value})"));
+        }
+
+        self.imports.insert("self::runtime::fractal::Fractal");
+        self.imports.insert("self::runtime::fractal::FractalKind");
+        Ok(chain(
+            call("Fractal::new", [format!("FractalKind::{kind}"), sources]),
+            setters,
+        ))
+    }
+
+    /// An expression node as a closure evaluating its formula, which takes
+    /// the values of the sources from a tuple.
+    fn expression_fn(&mut self, formula: &Formula, sources: Vec<String>) -> String {
+        let values: Vec<String> = (0..sources.len())
+            .map(|index| format!("values[{index}]"))
+            .collect();
+        let mut body = Vec::new();
+        if !sources.is_empty() {
+            let values = (0..sources.len()).map(|index| format!("sources.{index}.get(point)"));
+            body.push(format!("let values = {};", list("", values)));
+        }
+        body.push(formula.to_rust(&values, ["point[0]", "point[1]"]));
+
+        self.imports.insert("self::runtime::from_fn::FromFn");
+        let closure = format!(
+            "FromFn(move |point: [f64; 2]| {{\n{}\n}})",
+            indent(&body.join("\n"))
+        );
+        if sources.is_empty() {
+            return closure;
+        }
+
+        let single = sources.len() == 1;
+        let mut sources = call("", sources);
+        if single && !sources.contains('\n') {
+            sources.insert(sources.len() - 1, ',');
+        }
+        format!(
+            "{{\n{}\n}}",
+            indent(&format!("let sources = {sources};\n{closure}"))
+        )
+    }
+
+    fn worley(&mut self, node_id: NodeId, return_type: WorleyReturnType) -> anyhow::Result<String> {
+        let seed = self.seed(node_id)?;
+        let return_type = match return_type {
+            WorleyReturnType::Distance => "ReturnType::Distance",
+            WorleyReturnType::Value => "ReturnType::Value",
+        };
+        let distance_function = self
+            .attribute(node_id, "distance function")?
+            .try_to_distance_function()?
+            .to_string()
+            .to_lowercase();
+        self.imports.insert("self::runtime::worley::SyncWorley");
+        self.imports.insert("noise::core::worley::ReturnType");
+        self.imports
+            .insert("noise::core::worley::distance_functions");
+        let generator = chain(
+            format!("SyncWorley::new({seed})"),
+            [
+                format!("set_distance_function(distance_functions::{distance_function})"),
+                format!("set_return_type({return_type})"),
+            ],
+        );
+        self.generator(node_id, generator)
+    }
+
+    /// A gradient node or one of the vector fields derived from it.
+    fn gradient(&mut self, node_id: NodeId, ty: &'static str) -> anyhow::Result<String> {
+        let source = self.noise(node_id, "source")?;
+        let step = self.attribute(node_id, "step")?.try_to_f64()?;
+        check_step(step)?;
+        self.imports.insert(match ty {
+            "Curl" => "self::runtime::curl::Curl",
+            _ => "self::runtime::gradient::Gradient",
+        });
+        Ok(call(&format!("{ty}::new"), [source, number(step)]))
+    }
+
+    /// A variant of `Shape`, written out with its fields.
+    fn shape(&mut self, shape: String) -> String {
+        self.imports.insert("self::runtime::shape::Shape");
+        format!("Shape::{shape}")
+    }
+
+    /// A node applying one of the shaping functions, see `Shaping`.
+    fn shaping<const N: usize>(
+        &mut self,
+        node_id: NodeId,
+        shaping: &str,
+        inputs: [&str; N],
+    ) -> anyhow::Result<String> {
+        let sources = self
+            .noises(node_id, inputs)?
+            .into_iter()
+            .map(|source| call("DynNoiseFn::new", [source]));
+        let sources = list("vec!", sources);
+
+        self.imports.insert("self::runtime::dyn_fn::DynNoiseFn");
+        self.imports.insert("self::runtime::shaping::Shaped");
+        self.imports.insert("self::runtime::shaping::Shaping");
+        Ok(call(
+            "Shaped::new",
+            [format!("Shaping::{shaping}"), sources],
+        ))
+    }
+
+    /// The expression of the node connected to a noise input, or of its
+    /// constant.
+    fn noise(&mut self, node_id: NodeId, name: &str) -> anyhow::Result<String> {
+        let input_id = self.graph[node_id].get_input(name)?;
+        match self.graph.connection(input_id) {
+            Some(output_id) if self.graph[output_id].typ == ConnectionType::F64 => {
+                let value = self.unconverted(input_id)?.try_to_f64()?;
+                Ok(self.call("Constant::new", [number(value)]))
+            }
+            Some(output_id) => self.output(output_id),
+            None => match self.graph[input_id].value {
+                NodeAttribute::F64(_) => {
+                    let value = self.unconverted(input_id)?.try_to_f64()?;
+                    Ok(self.call("Constant::new", [number(value)]))
+                }
+                _ => anyhow::bail!(
+                    "'{}' has nothing connected to '{name}'",
+                    self.graph.node_name(node_id)
+                ),
+            },
+        }
+    }
+
+    /// The expression of the node connected to a vector input.
+    fn vector(&mut self, node_id: NodeId, name: &str) -> anyhow::Result<String> {
+        let input_id = self.graph[node_id].get_input(name)?;
+        match self.graph.connection(input_id) {
+            Some(output_id) => self.node(self.graph[output_id].node),
+            None => anyhow::bail!(
+                "'{}' has nothing connected to '{name}'",
+                self.graph.node_name(node_id)
+            ),
+        }
+    }
+
+    fn noises<const N: usize>(
+        &mut self,
+        node_id: NodeId,
+        names: [&str; N],
+    ) -> anyhow::Result<Vec<String>> {
+        names
+            .into_iter()
+            .map(|name| self.noise(node_id, name))
+            .collect()
+    }
+
+    /// The value of an input, computed from the connected number node if
+    /// there is one.
+    fn attribute(&mut self, node_id: NodeId, name: &str) -> anyhow::Result<NodeAttribute> {
+        let input_id = self.graph[node_id].get_input(name)?;
+        let value = self.unconverted(input_id)?;
+        match self.graph.connection(input_id) {
+            Some(_) => Ok(value.convert_to(self.graph[input_id].typ)),
+            None => Ok(value),
+        }
+    }
+
+    /// The value of an input before it is converted to the type of the input,
+    /// checked against the range of the input.
+    fn unconverted(&mut self, input_id: InputId) -> anyhow::Result<NodeAttribute> {
+        let value = match self.graph.connection(input_id) {
+            Some(output_id) => NodeAttribute::F64(self.fold_output(output_id)?),
+            None => self.graph[input_id].value.clone(),
+        };
+        self.graph.check_input(input_id, &value)?;
+        Ok(value)
+    }
+
+    /// A number input written as a literal.
+    fn number(&mut self, node_id: NodeId, name: &str) -> anyhow::Result<String> {
+        Ok(number(self.attribute(node_id, name)?.try_to_f64()?))
+    }
+
+    fn numbers<const N: usize>(
+        &mut self,
+        node_id: NodeId,
+        names: [&str; N],
+    ) -> anyhow::Result<Vec<String>> {
+        names
+            .into_iter()
+            .map(|name| self.number(node_id, name))
+            .collect()
+    }
+
+    fn noise_type(&mut self, node_id: NodeId) -> anyhow::Result<&'static str> {
+        let noise_type = self.attribute(node_id, "noise type")?.try_to_noise_type()?;
+        let ty = match noise_type {
+            NoiseType::Perlin => "Perlin",
+            NoiseType::Simplex => "Simplex",
+            NoiseType::OpenSimplex => "OpenSimplex",
+            NoiseType::SuperSimplex => "SuperSimplex",
+            NoiseType::Value => "Value",
+            NoiseType::PerlinSurflet => "PerlinSurflet",
+            NoiseType::Worley => {
+                self.imports.insert("self::runtime::worley::SyncWorley");
+                return Ok("SyncWorley");
+            }
+        };
+        self.imports.insert(noise_import(ty));
+        Ok(ty)
+    }
+
+    /// Computes a number output.
+    fn fold_output(&mut self, output_id: OutputId) -> anyhow::Result<f64> {
+        let node_id = self.graph[output_id].node;
+        let name = self.graph.output_name(output_id);
+        if let NodeTemplate::Normalize = self.graph[node_id].user_data.template {
+            check_cycle(self.graph, &self.path, node_id)?;
+            self.path.push(node_id);
+            let range = self.normalize_range(node_id);
+            self.path.pop();
+            let (min, max) = range?;
+            match name {
+                "min" => return Ok(min),
+                "max" => return Ok(max),
+                _ => {}
+            }
+        }
+
+        self.fold(node_id)
+    }
+
+    /// Estimates the range of the source of a `Normalize` node, the same way
+    /// evaluating it does. Expects the node to be on the path.
+    fn normalize_range(&mut self, node_id: NodeId) -> anyhow::Result<(f64, f64)> {
+        let extent = self.attribute(node_id, "extent")?.try_to_f64()?;
+        let samples = self.attribute(node_id, "samples")?.try_to_usize()?;
+        let source = compile_input(self.graph, node_id, "source", &self.path)?;
+        Normalize::estimate_range(&source, extent, samples)
+    }
+
+    /// Computes the output of a number node.
+    fn fold(&mut self, node_id: NodeId) -> anyhow::Result<f64> {
+        check_cycle(self.graph, &self.path, node_id)?;
+        self.path.push(node_id);
+        let value = match self.graph[node_id].user_data.template {
+            NodeTemplate::Float => self
+                .attribute(node_id, "value")
+                .and_then(NodeAttribute::try_to_f64),
+            NodeTemplate::Arithmetic => self.arithmetic(node_id),
+            _ => Err(anyhow::anyhow!(
+                "'{}' doesn't output a number",
+                self.graph.node_name(node_id)
+            )),
+        };
+        self.path.pop();
+        value
+    }
+
+    fn arithmetic(&mut self, node_id: NodeId) -> anyhow::Result<f64> {
+        let operator = self.attribute(node_id, "operator")?.try_to_operator()?;
+        let a = self.attribute(node_id, "a")?.try_to_f64()?;
+        let b = self.attribute(node_id, "b")?.try_to_f64()?;
+        Ok(operator.apply(a, b))
+    }
+}
+
+/// The modules of the editor's `runtime` with the noise functions which the
+/// `noise` crate doesn't have, included in the code using them.
+const RUNTIME: [(&str, &str); 13] = [
+    ("cache", include_str!("runtime/cache.rs")),
+    ("curl", include_str!("runtime/curl.rs")),
+    ("domain_warp", include_str!("runtime/domain_warp.rs")),
+    ("dyn_fn", include_str!("runtime/dyn_fn.rs")),
+    ("eroded_fbm", include_str!("runtime/eroded_fbm.rs")),
+    ("fractal", include_str!("runtime/fractal.rs")),
+    ("from_fn", FROM_FN),
+    ("gradient", include_str!("runtime/gradient.rs")),
+    ("scale", include_str!("runtime/scale.rs")),
+    ("shape", include_str!("runtime/shape.rs")),
+    ("shaping", include_str!("runtime/shaping.rs")),
+    ("vector", include_str!("runtime/vector.rs")),
+    ("worley", include_str!("runtime/worley.rs")),
+];
+
+/// Expression nodes become closures, which the editor evaluates with a
+/// `Formula` instead.
+const FROM_FN: &str = "\
+use noise::NoiseFn;
+
+/// A noise function computed by a closure.
+pub struct FromFn<F>(pub F);
+
+impl<F: Fn([f64; 2]) -> f64> NoiseFn<f64, 2> for FromFn<F> {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        (self.0)(point)
+    }
+}
+";
+
+/// The `runtime` module with the runtime modules the imports refer to and
+/// the ones these depend on, if there are any.
+fn runtime(imports: &BTreeSet<&str>) -> String {
+    let mut modules = BTreeSet::new();
+    let mut queue: VecDeque<&str> = imports
+        .iter()
+        .filter_map(|path| path.strip_prefix("self::runtime::"))
+        .filter_map(|path| path.split_once("::").map(|(module, _)| module))
+        .collect();
+    while let Some(module) = queue.pop_front() {
+        if modules.insert(module) {
+            queue.extend(dependencies(runtime_source(module)));
+        }
+    }
+    if modules.is_empty() {
+        return String::new();
+    }
+
+    let modules: Vec<String> = modules
+        .into_iter()
+        .map(|module| {
+            let source = indent(runtime_source(module).trim_end());
+            indent(&format!("pub mod {module} {{\n{source}\n}}"))
+        })
+        .collect();
+    format!(
+        "\n/// The noise functions of the editor which the `noise` crate doesn't have.\n\
+         #[allow(dead_code)]\nmod runtime {{\n{}\n}}\n",
+        modules.join("\n\n")
+    )
+}
+
+fn runtime_source(module: &str) -> &'static str {
+    RUNTIME
+        .iter()
+        .find(|(name, _)| *name == module)
+        .map(|(_, source)| *source)
+        .unwrap_or_else(|| unreachable!("'{module}' is not a runtime module"))
+}
+
+/// The sibling modules a runtime module imports through `use super::...;`.
+fn dependencies(source: &str) -> impl Iterator<Item = &str> {
+    source
+        .match_indices("use super::")
+        .flat_map(move |(start, prefix)| {
+            let tree = &source[start + prefix.len()..];
+            let tree = &tree[..tree.find(';').unwrap_or(tree.len())];
+            tree.split(|char: char| !char.is_alphanumeric() && char != '_' && char != ':')
+                .filter_map(|path| path.split_once("::").map(|(module, _)| module))
+        })
+}
+
+/// The `use` path of a type from the `noise` crate.
+fn noise_import(ty: &str) -> &'static str {
+    match ty {
+        "Abs" => "noise::Abs",
+        "Add" => "noise::Add",
+        "Blend" => "noise::Blend",
+        "Checkerboard" => "noise::Checkerboard",
+        "Clamp" => "noise::Clamp",
+        "Constant" => "noise::Constant",
+        "Curve" => "noise::Curve",
+        "Cylinders" => "noise::Cylinders",
+        "Displace" => "noise::Displace",
+        "Exponent" => "noise::Exponent",
+        "Max" => "noise::Max",
+        "Min" => "noise::Min",
+        "Multiply" => "noise::Multiply",
+        "Negate" => "noise::Negate",
+        "OpenSimplex" => "noise::OpenSimplex",
+        "Perlin" => "noise::Perlin",
+        "PerlinSurflet" => "noise::PerlinSurflet",
+        "Power" => "noise::Power",
+        "RotatePoint" => "noise::RotatePoint",
+        "ScaleBias" => "noise::ScaleBias",
+        "ScalePoint" => "noise::ScalePoint",
+        "Select" => "noise::Select",
+        "Simplex" => "noise::Simplex",
+        "SuperSimplex" => "noise::SuperSimplex",
+        "Terrace" => "noise::Terrace",
+        "TranslatePoint" => "noise::TranslatePoint",
+        "Value" => "noise::Value",
+        _ => unreachable!("'{ty}' is not exported"),
+    }
+}
+
+/// Writes a call, with every argument on a line of its own if one of them
+/// spans multiple lines or they don't fit on a single line.
+fn call(function: &str, arguments: impl IntoIterator<Item = String>) -> String {
+    delimited(function, ("(", ")"), arguments)
+}
+
+/// Writes a list like `vec![a, b]`, split over lines just like `call`.
+fn list(prefix: &str, items: impl IntoIterator<Item = String>) -> String {
+    delimited(prefix, ("[", "]"), items)
+}
+
+fn delimited(
+    function: &str,
+    (open, close): (&str, &str),
+    arguments: impl IntoIterator<Item = String>,
+) -> String {
+    let arguments: Vec<String> = arguments.into_iter().collect();
+    let width = function.len()
+        + arguments
+            .iter()
+            .map(|argument| argument.len() + 2)
+            .sum::<usize>();
+    if width > 80 || arguments.iter().any(|argument| argument.contains('\n')) {
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|argument| format!("{},", indent(argument)))
+            .collect();
+        format!("{function}{open}\n{}\n{close}", arguments.join("\n"))
+    } else {
+        format!("{function}{open}{}{close}", arguments.join(", "))
+    }
+}
+
+/// Groups the imports by module, e.g. `use noise::{Add, Perlin};`.
+fn imports(paths: &BTreeSet<&str>) -> String {
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+    for path in paths {
+        let (root, item) = path.rsplit_once("::").expect("Import of a crate item");
+        match groups.last_mut() {
+            Some((last, items)) if *last == root => items.push(item),
+            _ => groups.push((root, vec![item])),
+        }
+    }
+
+    let mut code = String::new();
+    for (root, items) in groups {
+        match items.as_slice() {
+            [item] => code.push_str(&format!("use {root}::{item};\n")),
+            items if root.len() + items.join(", ").len() < 90 => {
+                code.push_str(&format!("use {root}::{{{}}};\n", items.join(", ")))
+            }
+            items => {
+                let mut line = String::from("   ");
+                let mut lines = Vec::new();
+                for item in items {
+                    if line.len() + item.len() + 2 > 100 {
+                        lines.push(line);
+                        line = String::from("   ");
+                    }
+                    line.push_str(&format!(" {item},"));
+                }
+                lines.push(line);
+                code.push_str(&format!("use {root}::{{\n{}\n}};\n", lines.join("\n")));
+            }
+        }
+    }
+    code
+}
+
+/// Appends method calls to an expression, one per line. They are indented
+/// unless the expression ends with a closing parenthesis of its own line.
+fn chain(expression: String, methods: impl IntoIterator<Item = String>) -> String {
+    let indent = if expression.ends_with("\n)") {
+        ""
+    } else {
+        "    "
+    };
+    let mut chain = expression;
+    for method in methods {
+        chain.push_str(&format!("\n{indent}.{method}"));
+    }
+    chain
+}
+
+fn indent(code: &str) -> String {
+    code.lines()
+        .map(|line| match line {
+            "" => String::new(),
+            line => format!("    {line}"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A float literal which parses back to exactly the same value.
+fn number(value: f64) -> String {
+    if value.is_nan() {
+        "f64::NAN".into()
+    } else if value.is_infinite() {
+        if value > 0.0 {
+            "f64::INFINITY"
+        } else {
+            "f64::NEG_INFINITY"
+        }
+        .into()
+    } else {
+        format!("{value:?}")
+    }
+}
+
+/// The identifier for a node, named after its template unless it was given a
+/// name.
+fn node_identifier(graph: &NoiseGraph, node_id: NodeId) -> String {
+    match graph.node_name(node_id) {
+        name if name == DEFAULT_NODE_NAME => function_name(graph[node_id].user_data.template),
+        name => identifier(&name),
+    }
+}
+
+/// Turns a node name into a snake case identifier.
+fn identifier(name: &str) -> String {
+    let mut identifier = String::new();
+    for char in name.trim().chars() {
+        if char.is_alphanumeric() {
+            identifier.extend(char.to_lowercase());
+        } else if !identifier.ends_with('_') {
+            identifier.push('_');
+        }
+    }
+    let identifier = identifier.trim_matches('_');
+
+    if identifier.is_empty() {
+        "noise".into()
+    } else if identifier.starts_with(|char: char| char.is_ascii_digit()) || is_keyword(identifier) {
+        format!("_{identifier}")
+    } else {
+        identifier.into()
+    }
+}
+
+fn is_keyword(identifier: &str) -> bool {
+    [
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ]
+    .contains(&identifier)
+}
+
+#[cfg(test)]
+mod tests;
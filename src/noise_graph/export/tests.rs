@@ -0,0 +1,104 @@
+use std::{env, fs, path::Path, process::Command};
+
+use noise::NoiseFn;
+
+use super::export_rust;
+use crate::noise_graph::{
+    dsl,
+    graph_ext::{EvaluationCache, GraphExt},
+};
+
+/// The points at which the exported code is compared with the evaluator.
+const POINTS: [[f64; 2]; 6] = [
+    [0.0, 0.0],
+    [0.4, -0.7],
+    [37.7, 29.2],
+    [-186.1, 90.3],
+    [250.5, -311.9],
+    [-1000.3, -999.6],
+];
+
+/// A graph using every runtime module, shared nodes and normalized ranges.
+const TERRAIN: &str = "
+    hills = fbm(frequency: 0.01, octaves: 5);
+    cells = worley(seed: 3, frequency: 0.02, distance_function: Manhattan);
+    ridges = ridged_multi(source: cache(cells.cell_value), octaves: 3, frequency: 0.5);
+    eroded = eroded_fbm(seed: 7, octaves: 4, frequency: 0.004);
+    shaped = expression(\"mix(a, b, smoothstep(0.2, 0.6, a)) + sign(b) * 0.1 - fract(x * 0.001)\", hills, eroded);
+    warped = domain_warp(hills, vector(perlin(frequency: 0.03) * 20, simplex(frequency: 0.03) * 20));
+    swirled = domain_warp(warped, curl(hills, step: 0.5), strength: 10, iterations: 2);
+    n = normalize(swirled + ridges, 0, 1, extent: 200, samples: 16);
+    sloped = domain_warp(value(frequency: 0.05), gradient(hills), iterations: 1);
+    blended = lerp(shaped, sloped, smoothstep(cells, lower_edge: 0, upper_edge: 0.5));
+    island = radial_gradient(radius: 400) * smoothstep(scale(x(), scale: 0.01));
+    only_point = expression(\"sin(y * 0.02)\");
+    out = scale(blended, scale: 40) + ridges * cells + turbulence(only_point, power: 2) + expression(\"-abs(c)\", n) + n.min + island;";
+
+#[test]
+fn exported_code_matches_the_evaluator() {
+    let resource = dsl::parse(TERRAIN).unwrap();
+    let node_id = resource.user_state.active_node.unwrap();
+    let code = export_rust(&resource.state.graph, node_id).unwrap();
+    assert!(!code.contains("crate::"), "The code depends on the editor");
+
+    let expected = resource
+        .state
+        .graph
+        .evaluate_cached(node_id, &mut EvaluationCache::default())
+        .and_then(|value| value.try_to_noise_function())
+        .unwrap();
+    let actual = run_exported("terrain", &code, "out");
+
+    for (point, actual) in POINTS.into_iter().zip(actual) {
+        let expected = expected.get(point);
+        assert!(
+            actual.to_bits() == expected.to_bits(),
+            "{actual} instead of {expected} at {point:?}"
+        );
+    }
+}
+
+/// Builds the exported `code` as a crate of its own, which only depends on the
+/// `noise` crate, and returns the values of `function` at `POINTS`.
+fn run_exported(name: &str, code: &str, function: &str) -> Vec<f64> {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let dir = env::temp_dir().join(format!("world-generator-export-{name}"));
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{name}\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+             [dependencies]\nnoise = \"0.8.2\"\n\n[workspace]\n"
+        ),
+    )
+    .unwrap();
+    // Lets the build pick the versions the editor uses, without a registry.
+    fs::copy(manifest_dir.join("Cargo.lock"), dir.join("Cargo.lock")).unwrap();
+    fs::write(
+        dir.join("src/main.rs"),
+        format!(
+            "{code}\nfn main() {{\n    let noise = {function}();\n    \
+             for point in {POINTS:?} {{\n        \
+             println!(\"{{}}\", f64::to_bits(noise.get(point)));\n    }}\n}}\n"
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO"))
+        .args(["run", "--offline", "--quiet"])
+        .current_dir(&dir)
+        .env("CARGO_TARGET_DIR", manifest_dir.join("target/exported"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "The exported code doesn't build:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|bits| f64::from_bits(bits.parse().unwrap()))
+        .collect()
+}
@@ -0,0 +1,363 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use bevy::prelude::error;
+use bevy_egui::egui::{self, Color32, ScrollArea, TextEdit, Widget};
+use strum::IntoEnumIterator;
+
+use super::{format::FileFormat, NoiseGraphResource};
+
+/// Where graph files are listed from and saved to.
+const ASSETS_DIRECTORY: &str = "assets";
+
+/// The graph file which is loaded on startup, see `LoadedAssets`.
+const DEFAULT_FILE: &str = "noise_graph.json";
+
+/// Keeps track of the file the graph belongs to and of the file dialogs.
+pub struct GraphFile {
+    /// The file the graph was loaded from or last saved to, `None` for graphs
+    /// which were never saved.
+    path: Option<PathBuf>,
+    /// The format the graph was loaded in, which is kept when saving.
+    pub(super) format: FileFormat,
+    pub(super) unsaved_changes: bool,
+    dialog: Option<Dialog>,
+    error: Option<String>,
+}
+
+enum Dialog {
+    Open {
+        files: Vec<String>,
+    },
+    SaveAs {
+        name: String,
+        format: FileFormat,
+        files: Vec<String>,
+    },
+    Confirm(Action),
+}
+
+/// An action which needs to be confirmed, because it would lose data.
+enum Action {
+    New,
+    Open(PathBuf),
+    Overwrite(PathBuf, FileFormat),
+}
+
+impl Default for GraphFile {
+    fn default() -> Self {
+        Self {
+            path: Some(Path::new(ASSETS_DIRECTORY).join(DEFAULT_FILE)),
+            format: FileFormat::default(),
+            unsaved_changes: false,
+            dialog: None,
+            error: None,
+        }
+    }
+}
+
+impl GraphFile {
+    pub fn mark_changed(&mut self) {
+        self.unsaved_changes = true;
+    }
+
+    fn display_name(&self) -> String {
+        let name = self
+            .path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Untitled".into());
+
+        if self.unsaved_changes {
+            format!("{name}*")
+        } else {
+            name
+        }
+    }
+}
+
+impl NoiseGraphResource {
+    fn save_to(&mut self, path: PathBuf, format: FileFormat) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&path)
+                .context("Unable to open file")?,
+        );
+        self.write(&mut writer, format)?;
+        writer.flush().context("Unable to save file")?;
+
+        self.file.path = Some(path);
+        self.file.format = format;
+        self.file.unsaved_changes = false;
+        Ok(())
+    }
+
+    fn open(&mut self, path: PathBuf) -> anyhow::Result<()> {
+        let graph = NoiseGraphResource::read_file(&path)?;
+
+        *self = graph;
+        self.file.path = Some(path);
+        Ok(())
+    }
+
+    fn new_graph(&mut self) {
+        *self = NoiseGraphResource::default();
+        self.file.path = None;
+    }
+
+    fn run(&mut self, action: Action) -> anyhow::Result<()> {
+        match action {
+            Action::New => {
+                self.new_graph();
+                Ok(())
+            }
+            Action::Open(path) => self.open(path),
+            Action::Overwrite(path, format) => self.save_to(path, format),
+        }
+    }
+
+    /// Runs an action right away, or asks for confirmation first if it would
+    /// throw away unsaved changes or overwrite another file.
+    fn request(&mut self, action: Action) -> anyhow::Result<()> {
+        let needs_confirmation = match &action {
+            Action::New | Action::Open(_) => self.file.unsaved_changes,
+            Action::Overwrite(path, _) => {
+                path.exists() && self.file.path.as_deref() != Some(path.as_path())
+            }
+        };
+
+        if needs_confirmation {
+            self.file.dialog = Some(Dialog::Confirm(action));
+            Ok(())
+        } else {
+            self.file.dialog = None;
+            self.run(action)
+        }
+    }
+}
+
+/// Lists the names of all graph files in the assets directory.
+fn graph_files() -> anyhow::Result<Vec<String>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(ASSETS_DIRECTORY).context("Unable to read assets directory")? {
+        let path = entry?.path();
+        let is_graph_file = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => is_json_graph_file(&path),
+            Some(extension) => extension == FileFormat::Script.extension(),
+            None => false,
+        };
+
+        if let Some(name) = path.file_name().filter(|_| is_graph_file) {
+            files.push(name.to_string_lossy().into_owned());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Other assets are stored as json as well, so peek at the contents.
+fn is_json_graph_file(path: &Path) -> bool {
+    File::open(path)
+        .ok()
+        .and_then(|file| serde_json::from_reader::<_, serde_json::Value>(BufReader::new(file)).ok())
+        .is_some_and(|value| FileFormat::detect(&value).is_some())
+}
+
+/// The path to save a graph to, with the extension of the format appended if
+/// the name doesn't have it already.
+fn file_path(name: &str, format: FileFormat) -> PathBuf {
+    let name = name.trim();
+    let path = Path::new(ASSETS_DIRECTORY).join(name);
+    if path.extension().and_then(|extension| extension.to_str()) == Some(format.extension()) {
+        path
+    } else {
+        Path::new(ASSETS_DIRECTORY).join(format!("{name}.{}", format.extension()))
+    }
+}
+
+/// Side panel menu to create, open and save noise graphs.
+pub struct GraphFileMenu<'a>(pub &'a mut NoiseGraphResource);
+
+impl<'a> Widget for GraphFileMenu<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let graph = self.0;
+
+        ui.heading("Noise Graph");
+        let response = ui
+            .vertical(|ui| {
+                ui.label(graph.file.display_name());
+
+                ui.horizontal(|ui| {
+                    if ui.button("New").clicked() {
+                        let result = graph.request(Action::New);
+                        report(graph, result);
+                    }
+                    if ui.button("Open").clicked() {
+                        match graph_files() {
+                            Ok(files) => graph.file.dialog = Some(Dialog::Open { files }),
+                            Err(e) => report(graph, Err(e)),
+                        }
+                    }
+                    if ui.button("Save").clicked() {
+                        match graph.file.path.clone() {
+                            Some(path) => {
+                                let result = graph.save_to(path, graph.file.format);
+                                report(graph, result);
+                            }
+                            None => open_save_as(graph),
+                        }
+                    }
+                    if ui.button("Save As").clicked() {
+                        open_save_as(graph);
+                    }
+                });
+
+                if let Some(error) = &graph.file.error {
+                    ui.colored_label(Color32::LIGHT_RED, error);
+                }
+            })
+            .response;
+
+        show_dialog(ui.ctx(), graph);
+
+        response
+    }
+}
+
+fn open_save_as(graph: &mut NoiseGraphResource) {
+    let name = graph
+        .file
+        .path
+        .as_deref()
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    match graph_files() {
+        Ok(files) => {
+            graph.file.dialog = Some(Dialog::SaveAs {
+                name,
+                format: graph.file.format,
+                files,
+            })
+        }
+        Err(e) => report(graph, Err(e)),
+    }
+}
+
+fn report(graph: &mut NoiseGraphResource, result: anyhow::Result<()>) {
+    match result {
+        Ok(()) => graph.file.error = None,
+        Err(e) => {
+            error!("{e:#}");
+            graph.file.error = Some(format!("{e:#}"));
+        }
+    }
+}
+
+fn show_dialog(ctx: &egui::Context, graph: &mut NoiseGraphResource) {
+    let Some(dialog) = &mut graph.file.dialog else {
+        return;
+    };
+
+    let mut close = false;
+    let mut action = None;
+
+    match dialog {
+        Dialog::Open { files } => {
+            egui::Window::new("Open noise graph")
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for file in files.iter() {
+                            if ui.selectable_label(false, file).clicked() {
+                                action = Some(Action::Open(Path::new(ASSETS_DIRECTORY).join(file)));
+                            }
+                        }
+                    });
+                    if files.is_empty() {
+                        ui.label("No noise graphs found");
+                    }
+                    close = ui.button("Cancel").clicked();
+                });
+        }
+        Dialog::SaveAs {
+            name,
+            format,
+            files,
+        } => {
+            egui::Window::new("Save noise graph as")
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for file in files.iter() {
+                            if ui.selectable_label(file == name, file).clicked() {
+                                *name = file.clone();
+                            }
+                        }
+                    });
+                    ui.add(TextEdit::singleline(name).hint_text("file name"));
+                    ui.horizontal(|ui| {
+                        ui.label("format");
+                        for available in FileFormat::iter() {
+                            ui.radio_value(format, available, available.to_string());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let valid = !name.trim().is_empty();
+                        if ui.add_enabled(valid, egui::Button::new("Save")).clicked() {
+                            action = Some(Action::Overwrite(file_path(name, *format), *format));
+                        }
+                        close = ui.button("Cancel").clicked();
+                    });
+                });
+        }
+        Dialog::Confirm(pending) => {
+            let (title, text, confirm) = match pending {
+                Action::New | Action::Open(_) => (
+                    "Unsaved changes",
+                    format!("{} has unsaved changes.", graph.file.display_name()),
+                    "Discard changes",
+                ),
+                Action::Overwrite(path, _) => (
+                    "Overwrite file",
+                    format!("{} already exists.", path.display()),
+                    "Overwrite",
+                ),
+            };
+
+            egui::Window::new(title)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(text);
+                    ui.horizontal(|ui| {
+                        if ui.button(confirm).clicked() {
+                            if let Some(Dialog::Confirm(pending)) = graph.file.dialog.take() {
+                                let result = graph.run(pending);
+                                report(graph, result);
+                            }
+                        }
+                        close = ui.button("Cancel").clicked();
+                    });
+                });
+        }
+    }
+
+    if let Some(action) = action {
+        let result = graph.request(action);
+        report(graph, result);
+    } else if close {
+        graph.file.dialog = None;
+    }
+}
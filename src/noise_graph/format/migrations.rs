@@ -0,0 +1,304 @@
+//! The upgrades from one version of the file format to the next. They work on
+//! the json as it was written by the version they upgrade and hardcode the
+//! names and values they need, so they keep reading old files the same way
+//! however the node templates change later on.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+use serde_json::{json, Map, Value};
+
+use super::FileFormat;
+
+/// Version 1 files predate the generator inputs of Worley nodes. They get the
+/// seed, amplitude, offset and distance function they used before they had
+/// these inputs, their frequency input was already there.
+pub fn keep_worley(value: &mut Value) -> anyhow::Result<()> {
+    let inputs = [
+        NewInput::number("seed", "Usize", json!(0)),
+        NewInput::number("amplitude", "F64", json!(1.0)),
+        NewInput::number("offset", "F64", json!(0.0)),
+        NewInput {
+            name: "distance function",
+            typ: "NoConnection",
+            kind: "ConstantOnly",
+            variant: "DistanceFunction",
+            value: json!("Euclidean"),
+        },
+    ];
+
+    for_each_node(value, &inputs, |template, values| {
+        if template == "Worley" {
+            for input in &inputs {
+                values
+                    .entry(input.name.into())
+                    .or_insert_with(|| input.value.clone());
+            }
+        }
+    })
+}
+
+/// Version 2 files predate the ranges of number inputs, and values outside of
+/// them fail to evaluate now. They are moved to the nearest value inside.
+pub fn clamp_to_ranges(value: &mut Value) -> anyhow::Result<()> {
+    // The inputs with a range as of version 3, by template.
+    const FRACTAL: [(&str, f64, f64); 4] = [
+        ("octaves", 1.0, 32.0),
+        ("frequency", 0.0, f64::INFINITY),
+        ("lacunarity", 0.0, f64::INFINITY),
+        ("persistence", 0.0, f64::INFINITY),
+    ];
+    const FREQUENCY: [(&str, f64, f64); 1] = [("frequency", 0.0, f64::INFINITY)];
+    let ranges = |template: &str| -> &[(&str, f64, f64)] {
+        match template {
+            "BasicMulti" | "Billow" | "Fbm" | "HybridMulti" => &FRACTAL,
+            "RidgedMulti" => &[
+                ("octaves", 1.0, 32.0),
+                ("frequency", 0.0, f64::INFINITY),
+                ("lacunarity", 0.0, f64::INFINITY),
+                ("persistence", 0.0, f64::INFINITY),
+                ("attenuation", 0.0, f64::INFINITY),
+            ],
+            "ErodedFbm" => &[
+                ("frequency", 0.0, f64::INFINITY),
+                ("octaves", 1.0, 32.0),
+                ("gain", 0.0, f64::INFINITY),
+                ("lacunarity", 0.0, f64::INFINITY),
+                ("gradient strength", 0.0, f64::INFINITY),
+            ],
+            "Cylinders" | "OpenSimplex" | "Perlin" | "PerlinSurflet" | "Simplex"
+            | "SuperSimplex" | "Value" | "Worley" => &FREQUENCY,
+            "Turbulence" => &[("frequency", 0.0, f64::INFINITY), ("roughness", 1.0, 32.0)],
+            "Checkerboard" => &[("size", 0.0, 30.0)],
+            "DomainWarp" => &[("iterations", 0.0, 8.0)],
+            "LinearGradient" | "Select" => &[("falloff", 0.0, f64::INFINITY)],
+            "RadialGradient" => &[
+                ("radius", 0.0, f64::INFINITY),
+                ("falloff", 0.0, f64::INFINITY),
+            ],
+            _ => &[],
+        }
+    };
+
+    for_each_node(value, &[], |template, values| {
+        for &(name, min, max) in ranges(template) {
+            let Some(value) = values.get_mut(name) else {
+                continue;
+            };
+            // Whole numbers stay whole, values connected in the readable
+            // format aren't numbers.
+            if let Some(number) = value.as_i64() {
+                *value = json!((number as f64).clamp(min, max) as i64);
+            } else if let Some(number) = value.as_f64() {
+                *value = json!(number.clamp(min, max));
+            }
+        }
+    })
+}
+
+/// Version 3 readable files write connections as `"node.output -> node.input"`
+/// and exposed parameters as `"node.input"`, which can't be read back if a node
+/// name contains `->` or a dot. They are written as objects naming the node
+/// and the parameter now. Editor files are unchanged.
+pub fn structure_connections(value: &mut Value) -> anyhow::Result<()> {
+    if FileFormat::detect(value) != Some(FileFormat::Readable) {
+        return Ok(());
+    }
+
+    // Node names may contain dots but parameter names don't, so the
+    // endpoints are split at their last dot.
+    fn endpoint(endpoint: &str) -> anyhow::Result<(&str, &str)> {
+        endpoint
+            .trim()
+            .rsplit_once('.')
+            .with_context(|| format!("Expected 'node.parameter', got '{}'", endpoint.trim()))
+    }
+
+    if let Some(connections) = value.get_mut("connections").and_then(Value::as_array_mut) {
+        for connection in connections {
+            let text = connection.as_str().context("Invalid connection")?;
+            let (output, input) = text
+                .split_once("->")
+                .context("Expected 'node.output -> node.input'")?;
+            let ((from, output), (to, input)) = (endpoint(output)?, endpoint(input)?);
+            *connection = json!({
+                "from": { "node": from, "output": output },
+                "to": { "node": to, "input": input },
+            });
+        }
+    }
+
+    if let Some(exposed) = value.get_mut("exposed").and_then(Value::as_array_mut) {
+        for parameter in exposed {
+            let text = parameter["input"]
+                .as_str()
+                .context("Invalid exposed input")?;
+            let (node, input) = endpoint(text)?;
+            let (node, input) = (node.to_string(), input.to_string());
+            let parameter = parameter.as_object_mut().context("Invalid exposed input")?;
+            parameter.insert("node".into(), json!(node));
+            parameter.insert("input".into(), json!(input));
+        }
+    }
+
+    Ok(())
+}
+
+/// The constant values of a node's inputs by name. The editor format wraps
+/// them into the variant of the attribute, e.g. `{"F64": 1.0}`, these are the
+/// values inside, which for numbers are the ones the readable format writes.
+type Values = BTreeMap<String, Value>;
+
+/// An input a migration adds to nodes, with what the editor format keeps
+/// about it besides the value.
+struct NewInput {
+    name: &'static str,
+    typ: &'static str,
+    kind: &'static str,
+    variant: &'static str,
+    value: Value,
+}
+
+impl NewInput {
+    /// An input of a number, which can be connected.
+    fn number(name: &'static str, variant: &'static str, value: Value) -> Self {
+        Self {
+            name,
+            typ: variant,
+            kind: "ConnectionOrConstant",
+            variant,
+            value,
+        }
+    }
+}
+
+/// Lets a migration change the constant values of the inputs of every node,
+/// whichever json format the file has. The migration gets the name of the
+/// node's template and its values, values it adds need to be one of the
+/// `new_inputs`.
+fn for_each_node<F>(
+    value: &mut Value,
+    new_inputs: &[NewInput],
+    mut migrate: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(&str, &mut Values),
+{
+    match FileFormat::detect(value) {
+        Some(FileFormat::Editor) => {
+            let graph = value.pointer_mut("/state/graph").context("Missing graph")?;
+            let node_count = graph["nodes"].as_array().map_or(0, Vec::len);
+
+            for index in 0..node_count {
+                let node = graph["nodes"][index]["value"].clone();
+                if node.is_null() {
+                    continue;
+                }
+                let template = node["user_data"]["template"]
+                    .as_str()
+                    .context("Invalid node template")?;
+                let input_keys = node["inputs"].as_array().context("Invalid inputs")?;
+
+                let mut variants = BTreeMap::new();
+                let mut values = Values::new();
+                for input in input_keys {
+                    let (Some(name), key) = (input[0].as_str(), &input[1]) else {
+                        bail!("Invalid input of a {template} node");
+                    };
+                    // Attributes without a value, like the ones of noise
+                    // inputs, are strings.
+                    if let Some((variant, value)) = slot(graph, "inputs", key)?["value"]
+                        .as_object()
+                        .and_then(|attribute| attribute.iter().next())
+                    {
+                        variants.insert(name.to_string(), (variant.clone(), key.clone()));
+                        values.insert(name.to_string(), value.clone());
+                    }
+                }
+
+                migrate(template, &mut values);
+
+                // New inputs are added in the order the migration gives them.
+                for (name, value) in &values {
+                    if let Some((variant, key)) = variants.get(name) {
+                        slot(graph, "inputs", key)?["value"] = json!({ variant: value });
+                    } else if !new_inputs.iter().any(|input| input.name == name) {
+                        bail!("A {template} node has no input '{name}'");
+                    }
+                }
+                for new_input in new_inputs {
+                    let Some(value) = values.get(new_input.name) else {
+                        continue;
+                    };
+                    if variants.contains_key(new_input.name) {
+                        continue;
+                    }
+
+                    let key = add_slot(
+                        graph,
+                        "inputs",
+                        json!({
+                            "typ": new_input.typ,
+                            "value": { new_input.variant: value },
+                            "kind": new_input.kind,
+                            "node": node["id"],
+                            "shown_inline": true,
+                        }),
+                    )?;
+                    graph["nodes"][index]["value"]["inputs"]
+                        .as_array_mut()
+                        .context("Invalid inputs")?
+                        .push(json!([new_input.name, key]));
+                }
+            }
+        }
+        Some(FileFormat::Readable) => {
+            let nodes = value
+                .get_mut("nodes")
+                .and_then(Value::as_object_mut)
+                .context("Invalid nodes")?;
+
+            for (name, node) in nodes {
+                let node = node
+                    .as_object_mut()
+                    .with_context(|| format!("Invalid node '{name}'"))?;
+                let template = match node.remove("template") {
+                    Some(Value::String(template)) => template,
+                    _ => bail!("Invalid template of '{name}'"),
+                };
+
+                let mut values: Values = std::mem::take(node).into_iter().collect();
+                migrate(&template, &mut values);
+
+                *node = Map::from_iter(values);
+                node.insert("template".into(), Value::String(template));
+            }
+        }
+        Some(FileFormat::Script) | None => {}
+    }
+
+    Ok(())
+}
+
+/// The value in a slot map of the editor format, like an input, by its key.
+fn slot<'a>(graph: &'a mut Value, map: &str, key: &Value) -> anyhow::Result<&'a mut Value> {
+    let index = key["idx"].as_u64().context("Invalid key")?;
+    graph[map]
+        .get_mut(index as usize)
+        .map(|slot| &mut slot["value"])
+        .filter(|value| !value.is_null())
+        .with_context(|| format!("Missing {map} entry {index}"))
+}
+
+/// Adds a value to a slot map of the editor format, returning its key. The
+/// value's own key is set to the new key.
+fn add_slot(graph: &mut Value, map: &str, mut value: Value) -> anyhow::Result<Value> {
+    let slots = graph[map]
+        .as_array_mut()
+        .with_context(|| format!("Invalid {map}"))?;
+    // Slot maps mark occupied slots by odd versions.
+    let key = json!({ "idx": slots.len(), "version": 1 });
+    value["id"] = key.clone();
+    slots.push(json!({ "value": value, "version": 1 }));
+    Ok(key)
+}
@@ -0,0 +1,263 @@
+use std::{fs, io::Write, mem::discriminant, path::Path};
+
+use anyhow::{bail, Context};
+use egui_node_graph::{InputId, NodeId, NodeTemplateTrait, OutputId};
+use serde::Serialize;
+use serde_json::Value;
+
+use self::readable::ReadableGraph;
+
+use super::{dsl, NoiseGraph, NoiseGraphResource, NoiseGraphState};
+
+mod migrations;
+mod readable;
+
+/// The version of the graph file format written by this build. Bump it together
+/// with a new entry in `MIGRATIONS` whenever a change would break loading
+/// existing graph files, e.g. renaming a node template or an input.
+pub const CURRENT_VERSION: u64 = 4;
+
+/// `MIGRATIONS[n]` upgrades the json of a graph file from version `n` to
+/// version `n + 1`. Files of both formats go through the migrations, use
+/// `FileFormat::detect` to tell them apart.
+const MIGRATIONS: [fn(&mut Value) -> anyhow::Result<()>; CURRENT_VERSION as usize] = [
+    // Version 0 files predate the version field, their contents are unchanged.
+    |_| Ok(()),
+    migrations::keep_worley,
+    migrations::clamp_to_ranges,
+    migrations::structure_connections,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, strum::Display, strum::EnumIter)]
+pub enum FileFormat {
+    /// The complete editor state as persisted by `egui_node_graph`.
+    #[default]
+    Editor,
+    /// Nodes keyed by name with their parameters inline, made for reviewing
+    /// graphs in diffs, see `ReadableGraph`.
+    Readable,
+    /// A script in the graph language, see `dsl`. Node positions aren't
+    /// saved, the nodes are laid out automatically when loading.
+    Script,
+}
+
+impl FileFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Editor | FileFormat::Readable => "json",
+            FileFormat::Script => "noise",
+        }
+    }
+
+    /// Tells the json formats apart by their top-level keys, `None` if the
+    /// json isn't a graph file at all.
+    pub fn detect(value: &Value) -> Option<Self> {
+        if value.get("state").is_some() {
+            Some(Self::Editor)
+        } else if value.get("nodes").is_some() {
+            Some(Self::Readable)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct VersionedGraph<'a> {
+    version: u64,
+    #[serde(flatten)]
+    graph: &'a NoiseGraphResource,
+}
+
+impl NoiseGraphResource {
+    /// Writes the graph in the current version of the given format.
+    pub fn write(&self, mut writer: impl Write, format: FileFormat) -> anyhow::Result<()> {
+        match format {
+            FileFormat::Editor => serde_json::to_writer_pretty(
+                writer,
+                &VersionedGraph {
+                    version: CURRENT_VERSION,
+                    graph: self,
+                },
+            )
+            .context("Unable to save to json"),
+            FileFormat::Readable => {
+                let json = serde_json::to_string_pretty(&ReadableGraph::from_graph(self))
+                    .context("Unable to save to json")?;
+                writer
+                    .write_all(readable::compact_number_arrays(&json).as_bytes())
+                    .context("Unable to save file")
+            }
+            FileFormat::Script => writer
+                .write_all(dsl::print(self)?.as_bytes())
+                .context("Unable to save file"),
+        }
+    }
+
+    /// Loads a graph file, which is a script if it has the script extension
+    /// and json otherwise.
+    pub fn read_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path).context("Unable to open file")?;
+
+        if path.extension().and_then(|extension| extension.to_str())
+            == Some(FileFormat::Script.extension())
+        {
+            let mut graph = dsl::parse(&contents).context("Unable to parse script")?;
+            graph.file.format = FileFormat::Script;
+            Ok(graph)
+        } else {
+            let value = serde_json::from_str(&contents).context("Unable to read json")?;
+            Self::from_json(value)
+        }
+    }
+
+    /// Loads a graph file of either format and any version up to
+    /// `CURRENT_VERSION`, migrating it first and then filling in inputs and
+    /// outputs which were added to the node templates since the file was saved.
+    pub fn from_json(mut value: Value) -> anyhow::Result<Self> {
+        let format = FileFormat::detect(&value).context("Not a noise graph")?;
+
+        let version = match value.get("version") {
+            None => 0,
+            Some(version) => version.as_u64().context("Invalid format version")?,
+        };
+
+        if version > CURRENT_VERSION {
+            bail!(
+                "The graph was saved in format version {version}, but only versions up to {CURRENT_VERSION} are supported"
+            );
+        }
+
+        for (from, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            migrate(&mut value).with_context(|| {
+                format!(
+                    "Unable to migrate graph from version {from} to {}",
+                    from + 1
+                )
+            })?;
+        }
+
+        let mut graph = match format {
+            FileFormat::Editor => serde_json::from_value::<NoiseGraphResource>(value)
+                .context("Unable to parse noise graph")?,
+            FileFormat::Readable => serde_json::from_value::<ReadableGraph>(value)
+                .context("Unable to parse noise graph")?
+                .into_graph()?,
+            FileFormat::Script => unreachable!("scripts aren't json"),
+        };
+        sync_with_templates(&mut graph.state.graph);
+        graph.file.format = format;
+
+        Ok(graph)
+    }
+}
+
+/// Brings the inputs and outputs of every node in line with its template.
+/// Missing parameters are added with their default values, parameters the
+/// template no longer has are removed together with their connections, and
+/// the order is the one of the template.
+fn sync_with_templates(graph: &mut NoiseGraph) {
+    let mut defaults = NoiseGraph::new();
+    let mut user_state = NoiseGraphState::default();
+
+    let node_ids: Vec<NodeId> = graph.iter_nodes().collect();
+    for node_id in node_ids {
+        let template = graph[node_id].user_data.template;
+        let default_id = defaults.add_node(
+            String::new(),
+            template.user_data(&mut user_state),
+            |defaults, default_id| template.build_node(defaults, &mut user_state, default_id),
+        );
+
+        // Inputs which depend on other inputs, like the variables of an
+        // expression, follow the values of the node rather than the defaults.
+        for (name, default_input) in defaults[default_id].inputs.clone() {
+            if let Ok(input_id) = graph[node_id].get_input(&name) {
+                let value = &graph[input_id].value;
+                if discriminant(value) == discriminant(&defaults[default_input].value) {
+                    defaults.inputs[default_input].value = value.clone();
+                }
+            }
+        }
+        template.sync_inputs(&mut defaults, default_id);
+        graph[node_id].user_data.params =
+            std::mem::take(&mut defaults[default_id].user_data.params);
+        let default = &defaults[default_id];
+
+        let mut inputs = Vec::with_capacity(default.inputs.len());
+        for (name, default_input) in &default.inputs {
+            let default_input = &defaults[*default_input];
+            let input_id = match graph[node_id].get_input(name) {
+                Ok(input_id) => {
+                    let input = &mut graph.inputs[input_id];
+                    if discriminant(&input.value) != discriminant(&default_input.value) {
+                        input.value = default_input.value.clone();
+                    }
+                    input.typ = default_input.typ;
+                    input.kind = default_input.kind;
+                    input.shown_inline = default_input.shown_inline;
+                    disconnect_mismatched(graph, input_id);
+                    input_id
+                }
+                Err(_) => graph.add_input_param(
+                    node_id,
+                    name.clone(),
+                    default_input.typ,
+                    default_input.value.clone(),
+                    default_input.kind,
+                    default_input.shown_inline,
+                ),
+            };
+            inputs.push((name.clone(), input_id));
+        }
+
+        let stale_inputs: Vec<InputId> = graph[node_id]
+            .inputs
+            .iter()
+            .filter(|(_, input_id)| !inputs.iter().any(|(_, id)| id == input_id))
+            .map(|(_, input_id)| *input_id)
+            .collect();
+        for input_id in stale_inputs {
+            graph.remove_input_param(input_id);
+        }
+        graph[node_id].inputs = inputs;
+
+        let mut outputs = Vec::with_capacity(default.outputs.len());
+        for (name, default_output) in &default.outputs {
+            let typ = defaults[*default_output].typ;
+            let output_id = match graph[node_id].get_output(name) {
+                Ok(output_id) if graph[output_id].typ == typ => output_id,
+                Ok(output_id) => {
+                    graph.remove_output_param(output_id);
+                    graph.add_output_param(node_id, name.clone(), typ)
+                }
+                Err(_) => graph.add_output_param(node_id, name.clone(), typ),
+            };
+            outputs.push((name.clone(), output_id));
+        }
+
+        let stale_outputs: Vec<OutputId> = graph[node_id]
+            .outputs
+            .iter()
+            .filter(|(_, output_id)| !outputs.iter().any(|(_, id)| id == output_id))
+            .map(|(_, output_id)| *output_id)
+            .collect();
+        for output_id in stale_outputs {
+            graph.remove_output_param(output_id);
+        }
+        graph[node_id].outputs = outputs;
+    }
+}
+
+/// Removes the connection of an input whose type changed, so it can no longer
+/// take the values of the output it is connected to.
+fn disconnect_mismatched(graph: &mut NoiseGraph, input_id: InputId) {
+    if let Some(output_id) = graph.connection(input_id) {
+        if !graph[output_id].typ.connects_to(graph[input_id].typ) {
+            graph.remove_connection(input_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
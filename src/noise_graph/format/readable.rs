@@ -0,0 +1,445 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use anyhow::{anyhow, bail, Context};
+use bevy_egui::egui::pos2;
+use egui_node_graph::{InputId, InputParamKind, NodeId, NodeTemplateTrait};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+
+use crate::noise_graph::{
+    graph_ext::GraphExt, node_attribute::NodeAttribute, node_template::NodeTemplate,
+    thumbnail::ThumbnailConfig, NoiseGraph, NoiseGraphResource,
+};
+
+use super::CURRENT_VERSION;
+
+/// The readable file format. Nodes are keyed by their name, connections refer
+/// to the nodes by name and the positions of the nodes in the editor are kept
+/// apart from the graph itself, so changes to a graph show up as small diffs.
+#[derive(Serialize, Deserialize)]
+pub struct ReadableGraph {
+    version: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active: Option<String>,
+    #[serde(default)]
+    thumbnails: ThumbnailConfig,
+    nodes: BTreeMap<String, ReadableNode>,
+    #[serde(default)]
+    connections: Vec<ReadableConnection>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exposed: Vec<ReadableParameter>,
+    #[serde(default)]
+    layout: BTreeMap<String, [f32; 2]>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReadableNode {
+    template: NodeTemplate,
+    #[serde(flatten)]
+    parameters: Parameters,
+}
+
+/// A connection, written as
+/// `{"from": {"node": .., "output": ..}, "to": {"node": .., "input": ..}}` so
+/// node names can contain any character.
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct ReadableConnection {
+    from: OutputEndpoint,
+    to: InputEndpoint,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct OutputEndpoint {
+    node: String,
+    output: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct InputEndpoint {
+    node: String,
+    input: String,
+}
+
+/// An exposed parameter, written as `{"name": .., "node": .., "input": ..}`.
+#[derive(Serialize, Deserialize)]
+struct ReadableParameter {
+    name: String,
+    #[serde(flatten)]
+    input: InputEndpoint,
+}
+
+impl fmt::Display for ReadableConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{} -> {}.{}",
+            self.from.node, self.from.output, self.to.node, self.to.input
+        )
+    }
+}
+
+/// The constant values of a node's inputs, written in the order of the
+/// template's inputs.
+struct Parameters(Vec<(String, Value)>);
+
+impl Serialize for Parameters {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Parameters {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        BTreeMap::<String, Value>::deserialize(deserializer)
+            .map(|parameters| Self(parameters.into_iter().collect()))
+    }
+}
+
+impl ReadableGraph {
+    pub fn from_graph(resource: &NoiseGraphResource) -> Self {
+        let state = &resource.state;
+        let graph = &state.graph;
+        let names = unique_names(graph, &state.node_order);
+
+        let nodes = graph
+            .iter_nodes()
+            .map(|node_id| {
+                let node = &graph[node_id];
+                let parameters = node
+                    .inputs
+                    .iter()
+                    .filter(|(name, input_id)| {
+                        name != "name" && graph.connection(*input_id).is_none()
+                    })
+                    .filter_map(|(name, input_id)| {
+                        encode(graph[*input_id].value()).map(|value| (name.clone(), value))
+                    })
+                    .collect();
+
+                (
+                    names[&node_id].clone(),
+                    ReadableNode {
+                        template: node.user_data.template,
+                        parameters: Parameters(parameters),
+                    },
+                )
+            })
+            .collect();
+
+        let mut connections: Vec<ReadableConnection> = graph
+            .iter_connections()
+            .map(|(input_id, output_id)| ReadableConnection {
+                from: OutputEndpoint {
+                    node: names[&graph[output_id].node].clone(),
+                    output: graph.output_name(output_id).into(),
+                },
+                to: InputEndpoint {
+                    node: names[&graph[input_id].node].clone(),
+                    input: input_name(graph, input_id).into(),
+                },
+            })
+            .collect();
+        connections.sort();
+
+        let exposed = resource
+            .user_state
+            .exposed
+            .iter()
+            .filter_map(|parameter| {
+                Some(ReadableParameter {
+                    name: parameter.name.clone(),
+                    input: InputEndpoint {
+                        node: names.get(&parameter.node)?.clone(),
+                        input: parameter.input.clone(),
+                    },
+                })
+            })
+            .collect();
+
+        let layout = state
+            .node_positions
+            .iter()
+            .filter_map(|(node_id, position)| {
+                let name = names.get(&node_id)?;
+                Some((name.clone(), [position.x.round(), position.y.round()]))
+            })
+            .collect();
+
+        Self {
+            version: CURRENT_VERSION,
+            active: resource
+                .user_state
+                .active_node
+                .and_then(|node_id| names.get(&node_id).cloned()),
+            thumbnails: resource.user_state.thumbnail_config,
+            nodes,
+            connections,
+            exposed,
+            layout,
+        }
+    }
+
+    pub fn into_graph(self) -> anyhow::Result<NoiseGraphResource> {
+        let mut resource = NoiseGraphResource::default();
+        let state = &mut resource.state;
+        let user_state = &mut resource.user_state;
+        let mut ids = HashMap::with_capacity(self.nodes.len());
+
+        for (name, node) in self.nodes {
+            let template = node.template;
+            let node_id = state.graph.add_node(
+                template.node_graph_label(user_state),
+                template.user_data(user_state),
+                |graph, node_id| template.build_node(graph, user_state, node_id),
+            );
+
+            for (parameter, value) in node.parameters.0 {
+                let input_id = state.graph[node_id]
+                    .get_input(&parameter)
+                    .map_err(|_| anyhow!("Node '{name}' has no input '{parameter}'"))?;
+                let input = &mut state.graph.inputs[input_id];
+                input.value = decode(&input.value, value)
+                    .with_context(|| format!("Invalid value for '{name}.{parameter}'"))?;
+            }
+            template.sync_inputs(&mut state.graph, node_id);
+
+            if let Ok(input_id) = state.graph[node_id].get_input("name") {
+                state.graph.inputs[input_id].value = NodeAttribute::Name(name.clone());
+            }
+
+            state.node_order.push(node_id);
+            ids.insert(name, node_id);
+        }
+
+        for connection in &self.connections {
+            connect(&mut state.graph, &ids, connection)
+                .with_context(|| format!("Invalid connection '{connection}'"))?;
+        }
+
+        for parameter in self.exposed {
+            let InputEndpoint { node, input } = &parameter.input;
+            let node_id = node_id(&ids, node)?;
+            if state.graph[node_id].get_input(input).is_err() {
+                bail!(
+                    "Exposed parameter '{}' refers to unknown input '{input}'",
+                    parameter.name
+                );
+            }
+            user_state.exposed.expose(node_id, input, parameter.name);
+        }
+
+        for (name, [x, y]) in self.layout {
+            let node_id = *ids
+                .get(&name)
+                .with_context(|| format!("Layout refers to unknown node '{name}'"))?;
+            state.node_positions.insert(node_id, pos2(x, y));
+        }
+
+        // Nodes without a position are lined up below all the others.
+        let bottom = state
+            .node_positions
+            .values()
+            .map(|position| position.y)
+            .fold(0.0, f32::max);
+        let mut x = 0.0;
+        for &node_id in &state.node_order {
+            if !state.node_positions.contains_key(node_id) {
+                state
+                    .node_positions
+                    .insert(node_id, pos2(x, bottom + UNPLACED_OFFSET));
+                x += UNPLACED_OFFSET;
+            }
+        }
+
+        user_state.thumbnail_config = self.thumbnails;
+        user_state.active_node = match self.active {
+            Some(name) => Some(
+                *ids.get(&name)
+                    .with_context(|| format!("Active node '{name}' doesn't exist"))?,
+            ),
+            None => None,
+        };
+
+        Ok(resource)
+    }
+}
+
+/// Puts arrays of numbers like positions and control points on a single line
+/// of the pretty printed json, which would otherwise spread every number over
+/// its own line.
+pub fn compact_number_arrays(pretty: &str) -> String {
+    let lines: Vec<&str> = pretty.lines().collect();
+    let mut compacted = String::with_capacity(pretty.len());
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+
+        if line.ends_with('[') {
+            let numbers: Vec<&str> = lines[i..]
+                .iter()
+                .map(|line| line.trim().trim_end_matches(','))
+                .take_while(|value| value.parse::<f64>().is_ok())
+                .collect();
+            let closing = lines.get(i + numbers.len()).map(|line| line.trim());
+            if !numbers.is_empty() && matches!(closing, Some("]" | "],")) {
+                compacted.push_str(line);
+                compacted.push_str(&numbers.join(", "));
+                compacted.push_str(closing.unwrap_or_default());
+                compacted.push('\n');
+                i += numbers.len() + 1;
+                continue;
+            }
+        }
+
+        compacted.push_str(line);
+        compacted.push('\n');
+    }
+
+    compacted
+}
+
+/// Spacing between nodes which are placed automatically.
+const UNPLACED_OFFSET: f32 = 300.0;
+
+/// Node names are used as keys, so duplicates get a number appended.
+fn unique_names(graph: &NoiseGraph, node_order: &[NodeId]) -> HashMap<NodeId, String> {
+    let mut names = HashMap::new();
+    let mut taken = HashSet::new();
+
+    let remaining = graph
+        .iter_nodes()
+        .filter(|node_id| !node_order.contains(node_id));
+    let ordered = node_order
+        .iter()
+        .copied()
+        .filter(|node_id| graph.nodes.contains_key(*node_id));
+    for node_id in ordered.chain(remaining) {
+        let name = graph.node_name(node_id);
+        let mut unique = name.clone();
+        let mut counter = 1;
+        while !taken.insert(unique.clone()) {
+            counter += 1;
+            unique = format!("{name} {counter}");
+        }
+        names.insert(node_id, unique);
+    }
+
+    names
+}
+
+fn input_name(graph: &NoiseGraph, input_id: InputId) -> &str {
+    graph[graph[input_id].node]
+        .inputs
+        .iter()
+        .find(|(_, id)| *id == input_id)
+        .map(|(name, _)| name.as_str())
+        .unwrap_or_default()
+}
+
+/// Connects the two parameters of a connection.
+fn connect(
+    graph: &mut NoiseGraph,
+    ids: &HashMap<String, NodeId>,
+    connection: &ReadableConnection,
+) -> anyhow::Result<()> {
+    let (output_node, output) = (
+        node_id(ids, &connection.from.node)?,
+        &connection.from.output,
+    );
+    let (input_node, input) = (node_id(ids, &connection.to.node)?, &connection.to.input);
+
+    let output_id = graph[output_node]
+        .get_output(output)
+        .map_err(|_| anyhow!("Node has no output '{output}'"))?;
+    let input_id = graph[input_node]
+        .get_input(input)
+        .map_err(|_| anyhow!("Node has no input '{input}'"))?;
+
+    if matches!(graph[input_id].kind(), InputParamKind::ConstantOnly) {
+        bail!("Input '{input}' can't be connected");
+    }
+    if !graph[output_id].typ.connects_to(graph[input_id].typ) {
+        bail!(
+            "Can't connect {} to {}",
+            graph[output_id].typ,
+            graph[input_id].typ
+        );
+    }
+    if graph.connection(input_id).is_some() {
+        bail!("Input '{input}' is already connected");
+    }
+
+    graph.add_connection(output_id, input_id);
+    Ok(())
+}
+
+fn node_id(ids: &HashMap<String, NodeId>, node: &str) -> anyhow::Result<NodeId> {
+    ids.get(node)
+        .copied()
+        .with_context(|| format!("Unknown node '{node}'"))
+}
+
+/// Writes a constant value without the enum tags of the editor format, since
+/// the template already knows the type of every input. `None` for inputs
+/// without a constant value.
+fn encode(value: &NodeAttribute) -> Option<Value> {
+    match value {
+        NodeAttribute::F64(value) => Some(json!(value)),
+        NodeAttribute::Usize(value) => Some(json!(value)),
+        NodeAttribute::Bool(value) => Some(json!(value)),
+        NodeAttribute::NoiseType(noise_type) => Some(json!(noise_type)),
+        NodeAttribute::Operator(operator) => Some(json!(operator)),
+        NodeAttribute::Name(name) => Some(json!(name)),
+        NodeAttribute::Formula(formula) => Some(json!(formula)),
+        NodeAttribute::Vec { values, .. } => {
+            Some(Value::Array(values.iter().filter_map(encode).collect()))
+        }
+        NodeAttribute::F64Tuple(first, second) => Some(json!([first, second])),
+        NodeAttribute::ReturnType(return_type) => Some(json!(return_type)),
+        NodeAttribute::DistanceFunction(distance_function) => Some(json!(distance_function)),
+        NodeAttribute::Perlin
+        | NodeAttribute::ScaleBias
+        | NodeAttribute::NoiseFunction(_)
+        | NodeAttribute::VectorFunction(_)
+        | NodeAttribute::NoInput => None,
+    }
+}
+
+/// Reads a value written by `encode`, taking its type from the default value
+/// of the input.
+fn decode(default: &NodeAttribute, value: Value) -> anyhow::Result<NodeAttribute> {
+    Ok(match default {
+        NodeAttribute::F64(_) => NodeAttribute::F64(serde_json::from_value(value)?),
+        NodeAttribute::Usize(_) => NodeAttribute::Usize(serde_json::from_value(value)?),
+        NodeAttribute::Bool(_) => NodeAttribute::Bool(serde_json::from_value(value)?),
+        NodeAttribute::NoiseType(_) => NodeAttribute::NoiseType(serde_json::from_value(value)?),
+        NodeAttribute::Operator(_) => NodeAttribute::Operator(serde_json::from_value(value)?),
+        NodeAttribute::Name(_) => NodeAttribute::Name(serde_json::from_value(value)?),
+        NodeAttribute::Formula(_) => NodeAttribute::Formula(serde_json::from_value(value)?),
+        NodeAttribute::Vec { template, .. } => NodeAttribute::Vec {
+            values: serde_json::from_value::<Vec<Value>>(value)?
+                .into_iter()
+                .map(|value| decode(template, value))
+                .collect::<anyhow::Result<_>>()?,
+            template: template.clone(),
+        },
+        NodeAttribute::F64Tuple(..) => {
+            let (first, second) = serde_json::from_value(value)?;
+            NodeAttribute::F64Tuple(first, second)
+        }
+        NodeAttribute::ReturnType(_) => NodeAttribute::ReturnType(serde_json::from_value(value)?),
+        NodeAttribute::DistanceFunction(_) => {
+            NodeAttribute::DistanceFunction(serde_json::from_value(value)?)
+        }
+        NodeAttribute::Perlin
+        | NodeAttribute::ScaleBias
+        | NodeAttribute::NoiseFunction(_)
+        | NodeAttribute::VectorFunction(_)
+        | NodeAttribute::NoInput => bail!("Input has no constant value"),
+    })
+}
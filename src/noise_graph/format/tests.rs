@@ -0,0 +1,393 @@
+use std::path::Path;
+
+use egui_node_graph::NodeId;
+use noise::NoiseFn;
+use serde_json::{json, Value};
+
+use super::{migrations, FileFormat, CURRENT_VERSION};
+use crate::noise_graph::{
+    connection_type::ConnectionType,
+    dsl,
+    graph_ext::{EvaluationCache, GraphExt},
+    node_attribute::NodeAttribute,
+    NoiseGraph, NoiseGraphResource,
+};
+
+/// The points the nodes of the bundled graphs are sampled at.
+const SAMPLE_POINTS: [[f64; 2]; 4] = [[0.3, 0.7], [12.5, -4.25], [-100.1, 33.3], [517.9, 1024.6]];
+
+/// The template of every node of a graph in order, with its values at
+/// `SAMPLE_POINTS`.
+type Samples = [(&'static str, [f64; 4])];
+
+/// The samples of `assets/noise_graph.json`.
+const NOISE_GRAPH: [(&str, [f64; 4]); 28] = [
+    ("Select", [0.0, 0.379315, 0.0, 0.0]),
+    ("Constant", [0.0, 0.0, 0.0, 0.0]),
+    ("ScaleBias", [1.135088, 0.952185, 0.032279, 0.544253]),
+    ("Scale", [1.430037, 1.629439, 0.0, 0.87988]),
+    ("RidgedMulti", [-0.242617, -0.968305, -0.852368, -0.410637]),
+    ("Scale", [0.046651, 2.087968, -11.860659, 6.928076]),
+    ("Curve", [0.119119, 1.100936, 0.226545, -1.082172]),
+    ("ScaleBias", [0.093303, 4.175936, -23.721319, 13.856153]),
+    ("Cache", [-0.397464, 0.007098, 0.189104, 0.36604]),
+    ("Turbulence", [-0.471185, 0.062633, 0.24225, 0.383854]),
+    ("Fbm", [0.061136, 0.804866, 0.234637, -0.683617]),
+    ("Constant", [0.0, 0.0, 0.0, 0.0]),
+    ("Select", [0.0, 0.125265, 0.0, 0.0]),
+    ("Clamp", [0.061136, 0.804866, 0.234637, -0.683617]),
+    ("ScaleBias", [0.0, 0.125265, 0.484499, 0.767708]),
+    ("Max", [0.332949, 0.287937, 0.0, 0.174788]),
+    ("Add", [0.061136, 1.184181, 0.234637, -0.683617]),
+    ("Constant", [0.0, 0.0, 0.0, 0.0]),
+    ("Max", [0.0, 0.062633, 0.24225, 0.383854]),
+    ("Add", [0.061136, 0.804866, 0.234637, -0.683617]),
+    ("ScaleBias", [0.061136, 0.804866, 0.234637, -0.683617]),
+    ("Turbulence", [0.135088, -0.047815, -0.967721, -0.455747]),
+    ("Worley", [-0.397464, 0.007098, 0.189104, 0.36604]),
+    ("Add", [1.032949, 0.987937, 0.695203, 0.874788]),
+    ("ScaleBias", [0.0, 0.0, 0.0, 0.0]),
+    ("RidgedMulti", [0.160773, -0.859116, -0.87976, -0.062629]),
+    ("ScaleBias", [0.332949, 0.287937, -0.004797, 0.174788]),
+    ("Exponent", [1.032949, 0.987937, 0.695203, 0.874788]),
+];
+
+/// The samples of `assets/complex_planet..json`, numbers are sampled as
+/// constant noise.
+const COMPLEX_PLANET: [(&str, [f64; 4]); 83] = [
+    ("Float", [1.0, 1.0, 1.0, 1.0]),
+    ("Float", [2.208984, 2.208984, 2.208984, 2.208984]),
+    ("Float", [2.142578, 2.142578, 2.142578, 2.142578]),
+    ("Float", [2.162109, 2.162109, 2.162109, 2.162109]),
+    ("Float", [2.314453, 2.314453, 2.314453, 2.314453]),
+    ("Float", [2.212891, 2.212891, 2.212891, 2.212891]),
+    ("Float", [1.0, 1.0, 1.0, 1.0]),
+    ("Float", [1.0, 1.0, 1.0, 1.0]),
+    ("Float", [1.0, 1.0, 1.0, 1.0]),
+    ("Float", [0.0, 0.0, 0.0, 0.0]),
+    ("Float", [-0.375, -0.375, -0.375, -0.375]),
+    ("Float", [0.5, 0.5, 0.5, 0.5]),
+    ("Arithmetic", [1.5, 1.5, 1.5, 1.5]),
+    ("Arithmetic", [0.75, 0.75, 0.75, 0.75]),
+    ("Float", [0.3125, 0.3125, 0.3125, 0.3125]),
+    ("Float", [1.0, 1.0, 1.0, 1.0]),
+    ("Float", [1.0, 1.0, 1.0, 1.0]),
+    ("Arithmetic", [1.0, 1.0, 1.0, 1.0]),
+    ("Arithmetic", [0.25, 0.25, 0.25, 0.25]),
+    ("Float", [0.023438, 0.023438, 0.023438, 0.023438]),
+    ("Fbm", [0.136206, -0.975491, 0.71385, -0.637835]),
+    ("Curve", [0.332008, -1.344953, 0.210135, -1.01783]),
+    ("Fbm", [-0.539479, -0.254034, -0.626132, 1.137424]),
+    ("Arithmetic", [4.3438, 4.3438, 4.3438, 4.3438]),
+    ("ScaleBias", [0.422695, 0.529737, 0.3902, 1.051534]),
+    ("Min", [0.332008, -1.344953, 0.210135, -1.01783]),
+    (
+        "ScaleBias",
+        [-77.773653, -75.890766, -77.648032, -58.083569],
+    ),
+    (
+        "ScalePoint",
+        [52.693769, -77.406816, -78.352067, -76.836552],
+    ),
+    ("Float", [0.01, 0.01, 0.01, 0.01]),
+    ("Clamp", [0.332008, -1.0, 0.210135, -1.0]),
+    ("Cache", [0.332008, -1.0, 0.210135, -1.0]),
+    ("Turbulence", [0.386496, -1.0, 0.21882, -1.0]),
+    ("Arithmetic", [15.25, 15.25, 15.25, 15.25]),
+    ("Arithmetic", [0.008791, 0.008791, 0.008791, 0.008791]),
+    ("Turbulence", [0.384447, -1.0, 0.219629, -1.0]),
+    ("Arithmetic", [47.25, 47.25, 47.25, 47.25]),
+    ("Arithmetic", [0.002305, 0.002305, 0.002305, 0.002305]),
+    ("Turbulence", [0.383295, -1.0, 0.215672, -1.0]),
+    ("Arithmetic", [95.25, 95.25, 95.25, 95.25]),
+    ("Float", [1.0, 1.0, 1.0, 1.0]),
+    ("Arithmetic", [0.000981, 0.000981, 0.000981, 0.000981]),
+    ("Select", [0.383295, -1.0, 0.215672, -1.0]),
+    ("Cache", [0.383295, -1.0, 0.215672, -1.0]),
+    ("Turbulence", [0.135082, -1.0, 0.647744, -1.0]),
+    ("Float", [1.0, 1.0, 1.0, 1.0]),
+    ("Arithmetic", [18.125, 18.125, 18.125, 18.125]),
+    ("Arithmetic", [0.048558, 0.048558, 0.048558, 0.048558]),
+    ("Arithmetic", [0.048558, 0.048558, 0.048558, 0.048558]),
+    ("Terrace", [-0.099871, -1.0, 0.399979, -1.0]),
+    ("Cache", [-0.099871, -1.0, 0.399979, -1.0]),
+    ("RidgedMulti", [-0.429543, -0.996441, -0.444122, 0.188136]),
+    ("ScaleBias", [0.160229, -0.123221, 0.152939, 0.469068]),
+    ("RidgedMulti", [0.923924, -0.342769, 0.843189, -0.462979]),
+    ("ScaleBias", [-2.347849, 0.185538, -2.186378, 0.425958]),
+    ("Constant", [-1.0, -1.0, -1.0, -1.0]),
+    ("Blend", [-3.724041, -0.837324, -3.520761, -0.374238]),
+    ("Turbulence", [-3.576336, -1.116119, -2.584925, -0.592373]),
+    ("Arithmetic", [0.000149, 0.000149, 0.000149, 0.000149]),
+    ("Arithmetic", [0.000149, 0.000149, 0.000149, 0.000149]),
+    ("Turbulence", [-3.517548, -1.106413, -2.573575, -0.628647]),
+    ("Arithmetic", [0.000008, 0.000008, 0.000008, 0.000008]),
+    ("Arithmetic", [0.000008, 0.000008, 0.000008, 0.000008]),
+    ("Cache", [-3.517548, -1.106413, -2.573575, -0.628647]),
+    ("RidgedMulti", [-0.702672, -0.306559, -0.32473, 0.183551]),
+    ("RidgedMulti", [0.368594, 0.407259, -0.413628, -0.80008]),
+    ("Max", [0.368594, 0.407259, -0.32473, 0.183551]),
+    ("Turbulence", [0.253993, 0.375643, 0.399727, 0.642511]),
+    ("Arithmetic", [0.005544, 0.005544, 0.005544, 0.005544]),
+    ("Arithmetic", [0.005544, 0.005544, 0.005544, 0.005544]),
+    ("Cache", [0.253993, 0.375643, 0.399727, 0.642511]),
+    ("RidgedMulti", [0.304204, -0.795402, 0.087441, -0.899895]),
+    ("RidgedMulti", [-0.359829, -0.809268, -0.677172, -0.465331]),
+    ("Multiply", [-0.109461, 0.643694, -0.059213, 0.418749]),
+    ("Cache", [-0.109461, 0.643694, -0.059213, 0.418749]),
+    ("ScaleBias", [-0.972171, -0.948635, -0.9706, -0.955664]),
+    ("ScaleBias", [0.313498, 0.343911, 0.349932, 0.410628]),
+    ("Add", [-3.20405, -0.762502, -2.223643, -0.218019]),
+    ("Select", [-0.972171, -0.948635, -0.9706, -0.726045]),
+    ("ScaleBias", [-0.777737, -0.758908, -0.77648, -0.580836]),
+    ("Exponent", [-0.777737, -0.758908, -0.77648, -0.580836]),
+    ("Cache", [-0.777737, -0.758908, -0.77648, -0.580836]),
+    ("Billow", [-1.010164, -0.943958, 0.148775, -0.330254]),
+    ("ScaleBias", [-0.005082, 0.028021, 0.574388, 0.334873]),
+];
+
+fn find_node(graph: &NoiseGraph, name: &str) -> NodeId {
+    graph
+        .iter_nodes()
+        .find(|node_id| graph.node_name(*node_id) == name)
+        .unwrap_or_else(|| panic!("No node '{name}'"))
+}
+
+fn value(graph: &NoiseGraph, node_name: &str, input: &str) -> NodeAttribute {
+    let input_id = graph[find_node(graph, node_name)].get_input(input).unwrap();
+    graph[input_id].value.clone()
+}
+
+/// Asserts that a node gives the same values as the last statement of a
+/// script.
+fn assert_same_noise(graph: &NoiseGraph, node_name: &str, source: &str) {
+    let expected = dsl::parse(source).unwrap();
+    let expected = expected
+        .state
+        .graph
+        .evaluate_cached(
+            expected.user_state.active_node.unwrap(),
+            &mut EvaluationCache::default(),
+        )
+        .and_then(|value| value.try_to_noise_function())
+        .unwrap();
+    let actual = graph
+        .evaluate_cached(find_node(graph, node_name), &mut EvaluationCache::default())
+        .and_then(|value| value.try_to_noise_function())
+        .unwrap();
+
+    for point in [[0.3, 0.7], [12.5, -4.25], [-100.1, 33.3]] {
+        assert_eq!(actual.get(point), expected.get(point), "at {point:?}");
+    }
+}
+
+#[test]
+fn version_1_readable_graphs_keep_worley() {
+    let resource = NoiseGraphResource::from_json(json!({
+        "version": 1,
+        "nodes": {
+            "cells": {
+                "template": "Worley",
+                "frequency": 0.05,
+                "return type": "Value"
+            }
+        }
+    }))
+    .unwrap();
+    let graph = &resource.state.graph;
+
+    assert!(matches!(
+        value(graph, "cells", "seed"),
+        NodeAttribute::Usize(0)
+    ));
+    assert_same_noise(
+        graph,
+        "cells",
+        "out = worley(seed: 0, frequency: 0.05, amplitude: 1, offset: 0, return_type: Value, distance_function: Euclidean);",
+    );
+}
+
+#[test]
+fn version_1_editor_graphs_keep_worley() {
+    let mut resource = dsl::parse("cells = worley(frequency: 0.05, seed: 3);").unwrap();
+    let graph = &mut resource.state.graph;
+
+    // Worley nodes as they were saved in version 1.
+    let cells = find_node(graph, "cells");
+    for input in ["seed", "amplitude", "offset", "distance function"] {
+        let input_id = graph[cells].get_input(input).unwrap();
+        graph.remove_input_param(input_id);
+    }
+
+    let mut json = Vec::new();
+    resource.write(&mut json, FileFormat::Editor).unwrap();
+    let mut json: Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["version"], CURRENT_VERSION);
+    json["version"] = json!(1);
+
+    migrations::keep_worley(&mut json).unwrap();
+    let graph = &json["state"]["graph"];
+    let cells = graph["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|slot| &slot["value"])
+        .find(|node| node["user_data"]["template"] == "Worley")
+        .unwrap();
+    let inputs: Vec<(&str, &Value)> = cells["inputs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|input| {
+            let key = input[1]["idx"].as_u64().unwrap() as usize;
+            (
+                input[0].as_str().unwrap(),
+                &graph["inputs"][key]["value"]["value"],
+            )
+        })
+        .collect();
+    assert_eq!(
+        inputs[inputs.len() - 4..],
+        [
+            ("seed", &json!({ "Usize": 0 })),
+            ("amplitude", &json!({ "F64": 1.0 })),
+            ("offset", &json!({ "F64": 0.0 })),
+            (
+                "distance function",
+                &json!({ "DistanceFunction": "Euclidean" })
+            ),
+        ]
+    );
+
+    json["version"] = json!(2);
+    let resource = NoiseGraphResource::from_json(json).unwrap();
+    let graph = &resource.state.graph;
+    assert_same_noise(graph, "cells", "out = worley(frequency: 0.05);");
+}
+
+#[test]
+fn version_2_graphs_are_clamped_to_the_ranges() {
+    let resource = NoiseGraphResource::from_json(json!({
+        "version": 2,
+        "nodes": {
+            "hills": {
+                "template": "RidgedMulti",
+                "octaves": 0,
+                "lacunarity": -1.5,
+                "attenuation": 3.5
+            }
+        }
+    }))
+    .unwrap();
+    let graph = &resource.state.graph;
+
+    assert!(matches!(
+        value(graph, "hills", "octaves"),
+        NodeAttribute::Usize(1)
+    ));
+    assert!(matches!(value(graph, "hills", "lacunarity"), NodeAttribute::F64(f) if f == 0.0));
+    assert_same_noise(
+        graph,
+        "hills",
+        "out = ridged_multi(octaves: 1, lacunarity: 0, attenuation: 3.5);",
+    );
+}
+
+#[test]
+fn version_3_readable_connections_are_structured() {
+    let resource = NoiseGraphResource::from_json(json!({
+        "version": 3,
+        "nodes": {
+            "v1.hills": { "template": "Fbm", "frequency": 0.01 },
+            "out": { "template": "Scale", "scale": 2 }
+        },
+        "connections": ["v1.hills.out -> out.source"],
+        "exposed": [{ "name": "Scale", "input": "v1.hills.frequency" }]
+    }))
+    .unwrap();
+    let graph = &resource.state.graph;
+
+    let exposed = resource.user_state.exposed.iter().next().unwrap();
+    assert_eq!(graph.node_name(exposed.node), "v1.hills");
+    assert_eq!(exposed.input, "frequency");
+    assert_same_noise(
+        graph,
+        "out",
+        "hills = fbm(frequency: 0.01);\nout = scale(hills, scale: 2);",
+    );
+}
+
+#[test]
+fn readable_graphs_keep_names_with_arrows_and_dots() {
+    let mut resource = dsl::parse(
+        "hills = fbm(frequency: expose(\"Scale\", 0.01));\nout = scale(hills, scale: 2);",
+    )
+    .unwrap();
+    let graph = &mut resource.state.graph;
+    for (name, new_name) in [("hills", "a->b.out"), ("out", "c.d -> e")] {
+        let input_id = graph[find_node(graph, name)].get_input("name").unwrap();
+        graph[input_id].value = NodeAttribute::Name(new_name.into());
+    }
+
+    let mut json = Vec::new();
+    resource.write(&mut json, FileFormat::Readable).unwrap();
+    let json: Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(
+        json["connections"],
+        json!([{
+            "from": { "node": "a->b.out", "output": "out" },
+            "to": { "node": "c.d -> e", "input": "source" }
+        }])
+    );
+    assert_eq!(
+        json["exposed"],
+        json!([{ "name": "Scale", "node": "a->b.out", "input": "frequency" }])
+    );
+
+    let loaded = NoiseGraphResource::from_json(json).unwrap();
+    let graph = &loaded.state.graph;
+    let exposed = loaded.user_state.exposed.iter().next().unwrap();
+    assert_eq!(graph.node_name(exposed.node), "a->b.out");
+    assert_same_noise(
+        graph,
+        "c.d -> e",
+        "hills = fbm(frequency: 0.01);\nout = scale(hills, scale: 2);",
+    );
+}
+
+#[test]
+fn bundled_graphs_load_and_evaluate() {
+    let bundled: [(&str, &Samples); 2] = [
+        ("assets/noise_graph.json", &NOISE_GRAPH),
+        ("assets/complex_planet..json", &COMPLEX_PLANET),
+    ];
+
+    for (path, expected) in bundled {
+        let resource = NoiseGraphResource::read_file(Path::new(path)).unwrap();
+        let graph = &resource.state.graph;
+        let mut cache = EvaluationCache::default();
+        let node_ids: Vec<NodeId> = graph.iter_nodes().collect();
+        assert_eq!(node_ids.len(), expected.len(), "{path}");
+
+        for (node_id, (template, samples)) in node_ids.into_iter().zip(expected) {
+            let name = graph.node_name(node_id);
+            assert_eq!(
+                graph[node_id].user_data.template.to_string(),
+                *template,
+                "{path}: '{name}'"
+            );
+            let noise = graph
+                .evaluate_cached(node_id, &mut cache)
+                .map(|value| value.convert_to(ConnectionType::Noise))
+                .and_then(|value| value.try_to_noise_function())
+                .unwrap_or_else(|e| panic!("{path}: '{name}' fails with {e}"));
+            for (point, sample) in SAMPLE_POINTS.into_iter().zip(samples) {
+                let value = noise.get(point);
+                assert!(
+                    (value - sample).abs() < 1e-6,
+                    "{path}: '{name}' is {value} instead of {sample} at {point:?}"
+                );
+            }
+        }
+    }
+}
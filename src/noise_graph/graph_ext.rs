@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use egui_node_graph::{Graph, NodeId, OutputId};
+use egui_node_graph::{Graph, InputId, NodeId, OutputId};
 use noise::{
     Abs, Add, BasicMulti, Billow, Blend, Checkerboard, Clamp, Constant, Curve, Cylinders, Displace,
     Exponent, Fbm, HybridMulti, Max, Min, Multiply, Negate, NoiseFn, OpenSimplex, Perlin,
@@ -10,32 +10,212 @@ use noise::{
 
 use super::{
     connection_type::ConnectionType,
-    node_attribute::{NodeAttribute, NoiseType, Operator, WorleyReturnType},
-    node_template::{Arithmetic, Float, NodeImpl, NodeTemplate, Scale, SyncCache, SyncWorley},
-    DynNoiseFn, NodeData,
+    dsl::Formula,
+    node_attribute::{
+        NodeAttribute, NoiseType, Operator, WorleyDistanceFunction, WorleyReturnType,
+    },
+    node_template::{
+        clamp_octaves, Arithmetic, Cos, Curl, DistanceToLine, DistanceToPoint, DomainWarp,
+        ErodedFbm, Expression, Float, Gradient, Lerp, LinearGradient, Modulo, NodeImpl,
+        NodeTemplate, Normalize, Output, Overlay, Quantize, RadialGradient, Remap, Scale, Screen,
+        Sin, Smoothstep, SoftLight, Step, SyncCache, SyncWorley, Vector, X, Y,
+    },
+    DynNoiseFn, DynVectorFn, NodeData, VectorFn,
 };
 
 pub trait GraphExt {
-    fn evaluate(&self, node_id: NodeId) -> anyhow::Result<NodeAttribute>;
+    /// Evaluates a node, reusing the outputs of nodes that haven't changed
+    /// since they were last evaluated with the same cache.
+    fn evaluate_cached(
+        &self,
+        node_id: NodeId,
+        cache: &mut EvaluationCache,
+    ) -> anyhow::Result<NodeAttribute>;
+
+    /// Returns the given node together with every node that (transitively)
+    /// depends on one of its outputs.
+    fn downstream_nodes(&self, node_id: NodeId) -> HashSet<NodeId>;
+
+    /// Whether connecting `output` to `input` would make a node depend on
+    /// itself.
+    fn creates_cycle(&self, output: OutputId, input: InputId) -> bool;
+
+    /// The user given name of a node, falling back to its label.
+    fn node_name(&self, node_id: NodeId) -> String;
+
+    /// The name of an output within its node, like "out".
+    fn output_name(&self, output_id: OutputId) -> &str;
+
+    /// Fails if a number taken by an input is outside the range of its
+    /// `Param`.
+    fn check_input(&self, input_id: InputId, value: &NodeAttribute) -> anyhow::Result<()>;
 }
 
 type OutputsCache = HashMap<OutputId, NodeAttribute>;
 
 type NoiseGraphType = Graph<NodeData, ConnectionType, NodeAttribute>;
 
+/// Keeps node outputs around between evaluations. Whenever a node changes, it
+/// and everything downstream of it becomes dirty and is rebuilt on the next
+/// evaluation, while all other outputs are reused.
+#[derive(Default)]
+pub struct EvaluationCache {
+    outputs: OutputsCache,
+}
+
+impl EvaluationCache {
+    /// Marks a node and all nodes depending on it as dirty. Returns the nodes
+    /// which were affected.
+    pub fn invalidate(&mut self, graph: &NoiseGraphType, node_id: NodeId) -> HashSet<NodeId> {
+        let dirty = graph.downstream_nodes(node_id);
+        self.outputs
+            .retain(|output_id, _| match graph.try_get_output(*output_id) {
+                Some(output) => !dirty.contains(&output.node),
+                None => false,
+            });
+        dirty
+    }
+
+    /// The value of an output, if it was evaluated since it last changed.
+    pub fn get(&self, output_id: OutputId) -> Option<&NodeAttribute> {
+        self.outputs.get(&output_id)
+    }
+
+    /// Drops the outputs of nodes which no longer exist in the graph.
+    pub fn remove_deleted(&mut self, graph: &NoiseGraphType) {
+        self.outputs
+            .retain(|output_id, _| graph.outputs.contains_key(*output_id));
+    }
+}
+
 impl GraphExt for NoiseGraphType {
-    fn evaluate(&self, node_id: NodeId) -> anyhow::Result<NodeAttribute> {
-        evaluate_node(self, node_id, &mut HashMap::new())
+    fn evaluate_cached(
+        &self,
+        node_id: NodeId,
+        cache: &mut EvaluationCache,
+    ) -> anyhow::Result<NodeAttribute> {
+        // A node only counts as cached if all of its outputs are, as outputs
+        // which were added to a node since its last evaluation are missing.
+        let cached = self[node_id]
+            .output_ids()
+            .map(|output_id| cache.outputs.get(&output_id))
+            .collect::<Option<Vec<_>>>()
+            .and_then(|outputs| outputs.first().copied());
+
+        match cached {
+            Some(value) => Ok(value.clone()),
+            None => evaluate_node(self, node_id, &mut cache.outputs, &mut Vec::new()),
+        }
+    }
+
+    fn downstream_nodes(&self, node_id: NodeId) -> HashSet<NodeId> {
+        let mut dependents: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for (input_id, output_id) in self.iter_connections() {
+            dependents
+                .entry(self[output_id].node)
+                .or_default()
+                .push(self[input_id].node);
+        }
+
+        let mut visited = HashSet::from([node_id]);
+        let mut pending = vec![node_id];
+        while let Some(node_id) = pending.pop() {
+            for dependent in dependents.get(&node_id).into_iter().flatten() {
+                if visited.insert(*dependent) {
+                    pending.push(*dependent);
+                }
+            }
+        }
+
+        visited
+    }
+
+    fn creates_cycle(&self, output: OutputId, input: InputId) -> bool {
+        self.downstream_nodes(self[input].node)
+            .contains(&self[output].node)
+    }
+
+    fn node_name(&self, node_id: NodeId) -> String {
+        let node = &self[node_id];
+        match node.get_input("name").map(|input| self[input].value()) {
+            Ok(NodeAttribute::Name(name)) => name.clone(),
+            _ => node.label.clone(),
+        }
+    }
+
+    fn output_name(&self, output_id: OutputId) -> &str {
+        self[self[output_id].node]
+            .outputs
+            .iter()
+            .find(|(_, id)| *id == output_id)
+            .map(|(name, _)| name.as_str())
+            .unwrap_or_default()
+    }
+
+    fn check_input(&self, input_id: InputId, value: &NodeAttribute) -> anyhow::Result<()> {
+        let value = match value {
+            NodeAttribute::F64(value) => *value,
+            NodeAttribute::Usize(value) => *value as f64,
+            _ => return Ok(()),
+        };
+        let node_id = self[input_id].node;
+        let node = &self[node_id];
+        let param = node
+            .inputs
+            .iter()
+            .find(|(_, id)| *id == input_id)
+            .and_then(|(name, _)| Some((name, node.user_data.params.get(name)?)));
+
+        match param {
+            Some((name, param)) if !param.range.contains(&value) => anyhow::bail!(
+                "'{name}' of '{}' must be {}, found {value}",
+                self.node_name(node_id),
+                param.describe_range()
+            ),
+            _ => Ok(()),
+        }
     }
 }
 
+/// Evaluates a node after making sure it isn't already being evaluated further
+/// up the call stack, which would mean the graph contains a cycle.
 fn evaluate_node(
     graph: &NoiseGraphType,
     node_id: NodeId,
     outputs_cache: &mut OutputsCache,
+    path: &mut Vec<NodeId>,
+) -> anyhow::Result<NodeAttribute> {
+    check_cycle(graph, path, node_id)?;
+
+    path.push(node_id);
+    let result = evaluate_template(graph, node_id, outputs_cache, path);
+    path.pop();
+    result
+}
+
+/// Fails if `node_id` is already part of the path of nodes currently being
+/// visited.
+pub fn check_cycle(graph: &NoiseGraphType, path: &[NodeId], node_id: NodeId) -> anyhow::Result<()> {
+    if let Some(start) = path.iter().position(|id| *id == node_id) {
+        let cycle = path[start..]
+            .iter()
+            .chain([&node_id])
+            .map(|id| format!("'{}'", graph.node_name(*id)))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        anyhow::bail!("Cycle detected: {cycle}");
+    }
+    Ok(())
+}
+
+fn evaluate_template(
+    graph: &NoiseGraphType,
+    node_id: NodeId,
+    outputs_cache: &mut OutputsCache,
+    path: &mut Vec<NodeId>,
 ) -> anyhow::Result<NodeAttribute> {
     let node = &graph[node_id];
-    let evaluator = &mut NodeEvaluator::new(graph, outputs_cache, node_id);
+    let evaluator = &mut NodeEvaluator::new(graph, outputs_cache, path, node_id);
     match node.user_data.template {
         NodeTemplate::Abs => Abs::evaluate(evaluator),
         NodeTemplate::Add => Add::evaluate(evaluator),
@@ -47,58 +227,92 @@ fn evaluate_node(
         NodeTemplate::Checkerboard => Checkerboard::evaluate(evaluator),
         NodeTemplate::Clamp => Clamp::evaluate(evaluator),
         NodeTemplate::Constant => Constant::evaluate(evaluator),
+        NodeTemplate::Cos => Cos::evaluate(evaluator),
+        NodeTemplate::Curl => Curl::evaluate(evaluator),
         NodeTemplate::Curve => Curve::evaluate(evaluator),
         NodeTemplate::Cylinders => Cylinders::evaluate(evaluator),
         NodeTemplate::Displace => Displace::evaluate(evaluator),
+        NodeTemplate::DistanceToLine => DistanceToLine::evaluate(evaluator),
+        NodeTemplate::DistanceToPoint => DistanceToPoint::evaluate(evaluator),
+        NodeTemplate::DomainWarp => DomainWarp::evaluate(evaluator),
+        NodeTemplate::ErodedFbm => ErodedFbm::evaluate(evaluator),
         NodeTemplate::Exponent => Exponent::evaluate(evaluator),
+        NodeTemplate::Expression => Expression::evaluate(evaluator),
         NodeTemplate::Fbm => Fbm::evaluate(evaluator),
         NodeTemplate::Float => Float::evaluate(evaluator),
+        NodeTemplate::Gradient => Gradient::evaluate(evaluator),
         NodeTemplate::HybridMulti => HybridMulti::evaluate(evaluator),
+        NodeTemplate::Lerp => Lerp::evaluate(evaluator),
+        NodeTemplate::LinearGradient => LinearGradient::evaluate(evaluator),
         NodeTemplate::Max => Max::evaluate(evaluator),
         NodeTemplate::Min => Min::evaluate(evaluator),
+        NodeTemplate::Modulo => Modulo::evaluate(evaluator),
         NodeTemplate::Multiply => Multiply::evaluate(evaluator),
         NodeTemplate::Negate => Negate::evaluate(evaluator),
+        NodeTemplate::Normalize => Normalize::evaluate(evaluator),
         NodeTemplate::OpenSimplex => OpenSimplex::evaluate(evaluator),
+        NodeTemplate::Output => Output::evaluate(evaluator),
+        NodeTemplate::Overlay => Overlay::evaluate(evaluator),
         NodeTemplate::Perlin => Perlin::evaluate(evaluator),
         NodeTemplate::PerlinSurflet => PerlinSurflet::evaluate(evaluator),
         NodeTemplate::Power => Power::evaluate(evaluator),
+        NodeTemplate::Quantize => Quantize::evaluate(evaluator),
+        NodeTemplate::RadialGradient => RadialGradient::evaluate(evaluator),
+        NodeTemplate::Remap => Remap::evaluate(evaluator),
         NodeTemplate::RidgedMulti => RidgedMulti::evaluate(evaluator),
         NodeTemplate::RotatePoint => RotatePoint::evaluate(evaluator),
         NodeTemplate::Scale => Scale::evaluate(evaluator),
         NodeTemplate::ScaleBias => ScaleBias::evaluate(evaluator),
         NodeTemplate::ScalePoint => ScalePoint::evaluate(evaluator),
+        NodeTemplate::Screen => Screen::evaluate(evaluator),
         NodeTemplate::Select => Select::evaluate(evaluator),
         NodeTemplate::Simplex => Simplex::evaluate(evaluator),
+        NodeTemplate::Sin => Sin::evaluate(evaluator),
+        NodeTemplate::Smoothstep => Smoothstep::evaluate(evaluator),
+        NodeTemplate::SoftLight => SoftLight::evaluate(evaluator),
+        NodeTemplate::Step => Step::evaluate(evaluator),
         NodeTemplate::SuperSimplex => SuperSimplex::evaluate(evaluator),
         NodeTemplate::Terrace => Terrace::evaluate(evaluator),
         NodeTemplate::TranslatePoint => TranslatePoint::evaluate(evaluator),
         NodeTemplate::Turbulence => Turbulence::evaluate(evaluator),
         NodeTemplate::Value => Value::evaluate(evaluator),
+        NodeTemplate::Vector => Vector::evaluate(evaluator),
         NodeTemplate::Worley => SyncWorley::evaluate(evaluator),
+        NodeTemplate::X => X::evaluate(evaluator),
+        NodeTemplate::Y => Y::evaluate(evaluator),
     }
 }
 
 pub struct NodeEvaluator<'a> {
     graph: &'a NoiseGraphType,
     outputs_cache: &'a mut OutputsCache,
+    path: &'a mut Vec<NodeId>,
     node_id: NodeId,
 }
 impl<'a> NodeEvaluator<'a> {
     pub fn new(
         graph: &'a NoiseGraphType,
         outputs_cache: &'a mut OutputsCache,
+        path: &'a mut Vec<NodeId>,
         node_id: NodeId,
     ) -> Self {
         Self {
             graph,
             outputs_cache,
+            path,
             node_id,
         }
     }
     fn evaluate_input(&mut self, name: &str) -> anyhow::Result<NodeAttribute> {
         // Calling `evaluate_input` recursively evaluates other nodes in the
         // graph until the input value for a paramater has been computed.
-        evaluate_input(self.graph, self.node_id, name, self.outputs_cache)
+        evaluate_input(
+            self.graph,
+            self.node_id,
+            name,
+            self.outputs_cache,
+            self.path,
+        )
     }
     fn populate_output(
         &mut self,
@@ -126,8 +340,18 @@ impl<'a> NodeEvaluator<'a> {
     pub fn get_usize(&mut self, name: &str) -> anyhow::Result<usize> {
         self.evaluate_input(name)?.try_to_usize()
     }
+    pub fn get_bool(&mut self, name: &str) -> anyhow::Result<bool> {
+        self.evaluate_input(name)?.try_to_bool()
+    }
     pub fn get_noise_function(&mut self, name: &str) -> anyhow::Result<DynNoiseFn> {
-        self.evaluate_input(name)?.try_to_noise_function()
+        match self.evaluate_input(name)? {
+            // Noise inputs which take a constant while nothing is connected.
+            NodeAttribute::F64(value) => Ok(DynNoiseFn::new(Constant::new(value))),
+            value => value.try_to_noise_function(),
+        }
+    }
+    pub fn get_vector_function(&mut self, name: &str) -> anyhow::Result<DynVectorFn> {
+        self.evaluate_input(name)?.try_to_vector_function()
     }
     pub fn get_noise_type(&mut self) -> anyhow::Result<NoiseType> {
         self.evaluate_input("noise type")?.try_to_noise_type()
@@ -135,21 +359,81 @@ impl<'a> NodeEvaluator<'a> {
     pub fn get_operator(&mut self, name: &str) -> anyhow::Result<Operator> {
         self.evaluate_input(name)?.try_to_operator()
     }
+    pub fn get_formula(&mut self) -> anyhow::Result<Formula> {
+        self.evaluate_input("formula")?.try_to_formula()
+    }
     pub fn get_vec(&mut self, name: &str) -> anyhow::Result<Vec<NodeAttribute>> {
         self.evaluate_input(name)?.try_to_vec()
     }
     pub fn get_return_type(&mut self) -> anyhow::Result<WorleyReturnType> {
         self.evaluate_input("return type")?.try_to_return_type()
     }
+    pub fn get_distance_function(&mut self) -> anyhow::Result<WorleyDistanceFunction> {
+        self.evaluate_input("distance function")?
+            .try_to_distance_function()
+    }
+    pub fn get_seed(&mut self) -> anyhow::Result<u32> {
+        self.evaluate_input("seed")?.try_to_seed()
+    }
+    /// The noise functions for the octaves of a fractal: the connected source
+    /// for every octave, or generators of the node's noise type seeded with
+    /// the octave index otherwise.
+    pub fn get_octave_sources(&mut self) -> anyhow::Result<Vec<DynNoiseFn>> {
+        let octaves = clamp_octaves(self.get_usize("octaves")?);
+        match self.evaluate_input("source")? {
+            NodeAttribute::NoInput => {
+                let noise_type = self.get_noise_type()?;
+                Ok((0..octaves as u32)
+                    .map(|seed| noise_type.generator(seed))
+                    .collect())
+            }
+            source => Ok(vec![source.try_to_noise_function()?; octaves]),
+        }
+    }
+    /// Outputs a generator sampled at the node's frequency, with its values
+    /// multiplied by the amplitude and shifted by the offset.
+    pub fn output_generator(
+        &mut self,
+        noise: impl NoiseFn<f64, 2> + Send + Sync + 'static,
+    ) -> anyhow::Result<NodeAttribute> {
+        self.output_generator_to("out", noise)
+    }
+    /// Like `output_generator`, for one of the other outputs of a node.
+    pub fn output_generator_to(
+        &mut self,
+        name: &str,
+        noise: impl NoiseFn<f64, 2> + Send + Sync + 'static,
+    ) -> anyhow::Result<NodeAttribute> {
+        let frequency = self.get_f64("frequency")?;
+        let amplitude = self.get_f64("amplitude")?;
+        let offset = self.get_f64("offset")?;
+        let noise = ScaleBias::new(ScalePoint::new(noise).set_scale(frequency))
+            .set_scale(amplitude)
+            .set_bias(offset);
+        self.populate_output(name, NodeAttribute::NoiseFunction(DynNoiseFn::new(noise)))
+    }
     pub fn output_noise(
         &mut self,
         noise: impl NoiseFn<f64, 2> + Send + Sync + 'static,
     ) -> anyhow::Result<NodeAttribute> {
         self.populate_output("out", NodeAttribute::NoiseFunction(DynNoiseFn::new(noise)))
     }
+    pub fn output_vector(
+        &mut self,
+        vector: impl VectorFn + Send + Sync + 'static,
+    ) -> anyhow::Result<NodeAttribute> {
+        self.populate_output(
+            "out",
+            NodeAttribute::VectorFunction(DynVectorFn::new(vector)),
+        )
+    }
     pub fn output_number(&mut self, value: f64) -> anyhow::Result<NodeAttribute> {
         self.populate_output("out", NodeAttribute::F64(value))
     }
+    /// Like `output_number`, for one of the other outputs of a node.
+    pub fn output_number_to(&mut self, name: &str, value: f64) -> anyhow::Result<NodeAttribute> {
+        self.populate_output(name, NodeAttribute::F64(value))
+    }
 }
 
 fn populate_output(
@@ -164,15 +448,30 @@ fn populate_output(
     Ok(value)
 }
 
-// Evaluates the input value of
+// Evaluates the input value of a parameter, converting the values of outputs
+// of other types and checking them against the range of the input.
 fn evaluate_input(
     graph: &NoiseGraphType,
     node_id: NodeId,
     param_name: &str,
     outputs_cache: &mut OutputsCache,
+    path: &mut Vec<NodeId>,
 ) -> anyhow::Result<NodeAttribute> {
     let input_id = graph[node_id].get_input(param_name)?;
+    let value = input_value(graph, input_id, outputs_cache, path)?;
+    graph.check_input(input_id, &value)?;
+    match graph.connection(input_id) {
+        Some(_) => Ok(value.convert_to(graph[input_id].typ)),
+        None => Ok(value),
+    }
+}
 
+fn input_value(
+    graph: &NoiseGraphType,
+    input_id: InputId,
+    outputs_cache: &mut OutputsCache,
+    path: &mut Vec<NodeId>,
+) -> anyhow::Result<NodeAttribute> {
     // The output of another node is connected.
     if let Some(other_output_id) = graph.connection(input_id) {
         // The value was already computed due to the evaluation of some other
@@ -184,13 +483,16 @@ fn evaluate_input(
         // recursively evaluate it.
         else {
             // Calling this will populate the cache
-            evaluate_node(graph, graph[other_output_id].node, outputs_cache)?;
+            evaluate_node(graph, graph[other_output_id].node, outputs_cache, path)?;
 
-            // Now that we know the value is cached, return it
-            Ok(outputs_cache
-                .get(&other_output_id)
-                .expect("Cache should be populated")
-                .clone())
+            // Now the value is cached, unless the node left the output out.
+            outputs_cache.get(&other_output_id).cloned().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "'{}' has no value for its output '{}'",
+                    graph.node_name(graph[other_output_id].node),
+                    graph.output_name(other_output_id),
+                )
+            })
         }
     }
     // No existing connection, take the inline value instead.
@@ -198,3 +500,6 @@ fn evaluate_input(
         Ok(graph[input_id].value.clone())
     }
 }
+
+#[cfg(test)]
+mod tests;
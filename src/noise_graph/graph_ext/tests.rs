@@ -0,0 +1,293 @@
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+
+use egui_node_graph::NodeId;
+
+use super::{EvaluationCache, GraphExt};
+use crate::noise_graph::{dsl, node_attribute::NodeAttribute, DynNoiseFn, NoiseGraph};
+
+const POINTS: [[f64; 2]; 4] = [[0.0, 0.0], [0.4, -0.7], [37.7, 29.2], [-186.1, 90.3]];
+
+/// Evaluates the last statement of a script.
+fn noise(source: &str) -> DynNoiseFn {
+    let resource = dsl::parse(source).unwrap();
+    resource
+        .state
+        .graph
+        .evaluate_cached(
+            resource.user_state.active_node.unwrap(),
+            &mut EvaluationCache::default(),
+        )
+        .and_then(|value| value.try_to_noise_function())
+        .unwrap()
+}
+
+fn find_node(graph: &NoiseGraph, name: &str) -> NodeId {
+    graph
+        .iter_nodes()
+        .find(|node_id| graph.node_name(*node_id) == name)
+        .unwrap_or_else(|| panic!("No node '{name}'"))
+}
+
+/// Whether all outputs of a node are cached.
+fn is_cached(cache: &EvaluationCache, graph: &NoiseGraph, node_id: NodeId) -> bool {
+    graph[node_id]
+        .output_ids()
+        .all(|output_id| cache.get(output_id).is_some())
+}
+
+#[test]
+fn fractals_without_a_source_match_the_noise_crate() {
+    let actual = noise("out = fbm(octaves: 4, frequency: 0.02);");
+    let expected = Fbm::<Perlin>::new(0).set_octaves(4).set_frequency(0.02);
+
+    for point in POINTS {
+        assert_eq!(actual.get(point), expected.get(point), "at {point:?}");
+    }
+}
+
+#[test]
+fn fractals_use_a_connected_source_for_every_octave() {
+    let actual = noise(
+        "out = fbm(source: perlin(seed: 5), octaves: 3, frequency: 0.5, lacunarity: 2, persistence: 0.25);",
+    );
+    let source = Perlin::new(5);
+
+    for point in POINTS {
+        let mut expected = 0.0;
+        for octave in 0..3 {
+            let scale = 0.5 * 2.0_f64.powi(octave);
+            let signal = source.get(point.map(|x| x * scale));
+            expected += signal * 0.25_f64.powi(octave);
+        }
+        expected /= 1.0 - 0.25_f64.powi(3);
+
+        assert!(
+            (actual.get(point) - expected).abs() < 1e-12,
+            "{} instead of {expected} at {point:?}",
+            actual.get(point)
+        );
+    }
+}
+
+#[test]
+fn eroded_fbm_damps_octaves_by_their_slope() {
+    let plain = noise("out = eroded_fbm(seed: 2, octaves: 1, gradient_strength: 0);");
+    let eroded = noise("out = eroded_fbm(seed: 2, octaves: 1, gradient_strength: 3);");
+
+    for [x, y] in POINTS {
+        let step = 1e-6;
+        let dx = (plain.get([x + step, y]) - plain.get([x - step, y])) / (2.0 * step);
+        let dy = (plain.get([x, y + step]) - plain.get([x, y - step])) / (2.0 * step);
+        let expected = plain.get([x, y]) / (1.0 + 3.0 * (dx * dx + dy * dy));
+
+        assert!(
+            (eroded.get([x, y]) - expected).abs() < 1e-6,
+            "{} instead of {expected} at {:?}",
+            eroded.get([x, y]),
+            [x, y]
+        );
+    }
+}
+
+#[test]
+fn expressions_evaluate_their_formula_over_their_inputs() {
+    let actual =
+        noise("out = expression(\"max(a, b) * 2 + x - y\", perlin(seed: 1), constant(0.25));");
+    let a = Perlin::new(1);
+
+    for point in POINTS {
+        let expected = a.get(point).max(0.25) * 2.0 + point[0] - point[1];
+        assert_eq!(actual.get(point), expected, "at {point:?}");
+    }
+}
+
+#[test]
+fn shaping_parameters_are_constants_or_noise() {
+    let constant = noise("out = lerp(perlin(seed: 1), perlin(seed: 2), factor: 0.25);");
+    let varying = noise("out = lerp(perlin(seed: 1), perlin(seed: 2), factor: perlin(seed: 3));");
+    let [a, b, factor] = [1, 2, 3].map(Perlin::new);
+
+    for point in POINTS {
+        let (a, b) = (a.get(point), b.get(point));
+        assert_eq!(
+            constant.get(point),
+            0.25_f64.mul_add(b - a, a),
+            "at {point:?}"
+        );
+        assert_eq!(
+            varying.get(point),
+            factor.get(point).mul_add(b - a, a),
+            "at {point:?}"
+        );
+    }
+}
+
+#[test]
+fn shapes_depend_on_the_sample_point_alone() {
+    let coordinates = noise("out = x() - y();");
+    let distance = noise("out = distance_to_point(x: 3, y: -4);");
+    let line = noise("out = distance_to_line(start_x: 0, start_y: 0, end_x: 10, end_y: 0);");
+    let gradient = noise("out = radial_gradient(radius: 100, falloff: 2);");
+
+    for point @ [x, y] in POINTS {
+        assert_eq!(coordinates.get(point), x - y);
+        assert_eq!(distance.get(point), (x - 3.0).hypot(y + 4.0));
+        assert_eq!(line.get(point), (x - x.clamp(0.0, 10.0)).hypot(y));
+        assert_eq!(
+            gradient.get(point),
+            (1.0 - x.hypot(y) / 100.0).max(0.0).powf(2.0)
+        );
+    }
+}
+
+#[test]
+fn domain_warps_offset_the_sample_point_by_the_field() {
+    let constant = noise(
+        "out = domain_warp(perlin(seed: 1), vector(constant(3), constant(-2)), strength: 2);",
+    );
+    let uphill =
+        noise("out = domain_warp(y(), gradient(y(), step: 0.5), strength: 5, iterations: 3);");
+    let along_contours = noise("out = domain_warp(x(), curl(y()), strength: 5);");
+    let source = Perlin::new(1);
+
+    for point @ [x, y] in POINTS {
+        assert_eq!(constant.get(point), source.get([x + 6.0, y - 4.0]));
+        assert_eq!(uphill.get(point), y + 5.0);
+        assert_eq!(along_contours.get(point), x - 5.0);
+    }
+}
+
+#[test]
+fn numbers_convert_to_the_type_of_the_input() {
+    let actual = noise("out = perlin(seed: float(value: 2.6)) + float(value: 0.5);");
+    let expected = Perlin::new(3);
+
+    for point in POINTS {
+        assert_eq!(actual.get(point), expected.get(point) + 0.5, "at {point:?}");
+    }
+}
+
+#[test]
+fn numbers_outside_of_the_range_of_their_input_fail_to_evaluate() {
+    let resource = dsl::parse("hills = fbm(octaves: float(value: 40));").unwrap();
+    let error = resource
+        .state
+        .graph
+        .evaluate_cached(
+            resource.user_state.active_node.unwrap(),
+            &mut EvaluationCache::default(),
+        )
+        .err()
+        .unwrap();
+
+    assert_eq!(
+        error.to_string(),
+        "'octaves' of 'hills' must be between 1 and 32, found 40"
+    );
+}
+
+#[test]
+fn normalize_maps_the_sampled_range_onto_the_target_range() {
+    let plain = noise("out = normalize(x(), -1, 1, extent: 8, samples: 3);");
+    let clamped = noise("out = normalize(x(), -1, 1, clamp: true, extent: 8, samples: 3);");
+    let min = noise("n = normalize(x(), -1, 1, extent: 8, samples: 3);\nout = n.min + x();");
+
+    assert_eq!(plain.get([-8.0, 3.0]), -1.0);
+    assert_eq!(plain.get([4.0, 3.0]), 0.5);
+    assert_eq!(plain.get([16.0, 3.0]), 2.0);
+    assert_eq!(clamped.get([16.0, 3.0]), 1.0);
+    assert_eq!(min.get([1.0, 0.0]), -7.0);
+}
+
+#[test]
+fn invalidating_a_node_drops_the_nodes_depending_on_it() {
+    let resource =
+        dsl::parse("a = perlin();\nb = scale(a, scale: 2);\nc = perlin(seed: 1);\nout = b + c;")
+            .unwrap();
+    let graph = &resource.state.graph;
+    let mut cache = EvaluationCache::default();
+    graph
+        .evaluate_cached(find_node(graph, "out"), &mut cache)
+        .unwrap();
+
+    let dirty = cache.invalidate(graph, find_node(graph, "b"));
+    let mut dirty: Vec<String> = dirty.into_iter().map(|id| graph.node_name(id)).collect();
+    dirty.sort();
+    assert_eq!(dirty, ["b", "out"]);
+    for (name, cached) in [("a", true), ("b", false), ("c", true), ("out", false)] {
+        assert_eq!(
+            is_cached(&cache, graph, find_node(graph, name)),
+            cached,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn cached_outputs_are_kept_until_their_node_is_invalidated() {
+    let mut resource = dsl::parse("a = perlin();\nout = scale(a, scale: 2);").unwrap();
+    let graph = &mut resource.state.graph;
+    let (a, out) = (find_node(graph, "a"), find_node(graph, "out"));
+    let mut cache = EvaluationCache::default();
+    let evaluate = |graph: &NoiseGraph, cache: &mut EvaluationCache| {
+        graph
+            .evaluate_cached(out, cache)
+            .and_then(|value| value.try_to_noise_function())
+            .unwrap()
+            .get([10.3, 4.7])
+    };
+    let before = evaluate(graph, &mut cache);
+
+    let seed = graph[a].get_input("seed").unwrap();
+    graph[seed].value = NodeAttribute::Usize(42);
+    assert_eq!(evaluate(graph, &mut cache), before);
+
+    cache.invalidate(graph, a);
+    assert_ne!(evaluate(graph, &mut cache), before);
+}
+
+#[test]
+fn nodes_missing_a_cached_output_are_evaluated_again() {
+    let resource = dsl::parse("cells = worley();").unwrap();
+    let graph = &resource.state.graph;
+    let cells = find_node(graph, "cells");
+    let mut cache = EvaluationCache::default();
+    graph.evaluate_cached(cells, &mut cache).unwrap();
+
+    // Like an output added to the template since the last evaluation.
+    let cell_value = graph[cells].get_output("cell value").unwrap();
+    cache.outputs.remove(&cell_value);
+
+    graph.evaluate_cached(cells, &mut cache).unwrap();
+    assert!(is_cached(&cache, graph, cells));
+}
+
+#[test]
+fn connections_from_downstream_nodes_create_cycles() {
+    let resource =
+        dsl::parse("a = fbm(source: perlin());\nb = scale(a, scale: 2);\nc = perlin();").unwrap();
+    let graph = &resource.state.graph;
+    let [a, b, c] = ["a", "b", "c"].map(|name| find_node(graph, name));
+    let source = graph[a].get_input("source").unwrap();
+    let out = |node_id: NodeId| graph[node_id].get_output("out").unwrap();
+
+    assert!(graph.creates_cycle(out(b), source));
+    assert!(graph.creates_cycle(out(a), source));
+    assert!(!graph.creates_cycle(out(c), source));
+    assert!(!graph.creates_cycle(out(a), graph[b].get_input("source").unwrap()));
+}
+
+#[test]
+fn evaluating_a_cycle_names_its_nodes() {
+    let mut resource = dsl::parse("a = fbm(source: perlin());\nb = scale(a, scale: 2);").unwrap();
+    let graph = &mut resource.state.graph;
+    let (a, b) = (find_node(graph, "a"), find_node(graph, "b"));
+    let source = graph[a].get_input("source").unwrap();
+    graph.add_connection(graph[b].get_output("out").unwrap(), source);
+
+    let error = graph
+        .evaluate_cached(b, &mut EvaluationCache::default())
+        .err()
+        .unwrap();
+    assert_eq!(error.to_string(), "Cycle detected: 'b' -> 'a' -> 'b'");
+}
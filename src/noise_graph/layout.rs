@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use bevy_egui::egui::pos2;
+use egui_node_graph::NodeId;
+
+use super::{connection_type::ConnectionType, NoiseGraph, NoiseGraphResource};
+
+const COLUMN_WIDTH: f32 = 320.0;
+const ROW_SPACING: f32 = 40.0;
+
+impl NoiseGraphResource {
+    /// Places all nodes in columns by their distance from the nodes without
+    /// connected inputs, so connections run from left to right. Within a
+    /// column, nodes keep their drawing order.
+    pub fn auto_layout(&mut self) {
+        let state = &mut self.state;
+        let mut depths = HashMap::new();
+        let mut column_heights: Vec<f32> = Vec::new();
+
+        for &node_id in &state.node_order {
+            let column = depth(&state.graph, node_id, &mut depths, &mut Vec::new());
+            if column_heights.len() <= column {
+                column_heights.resize(column + 1, 0.0);
+            }
+
+            let y = column_heights[column];
+            column_heights[column] += estimated_height(&state.graph, node_id) + ROW_SPACING;
+            state
+                .node_positions
+                .insert(node_id, pos2(column as f32 * COLUMN_WIDTH, y));
+        }
+    }
+}
+
+/// The length of the longest chain of connections leading into a node. Nodes
+/// on a cycle count as having no inputs, since there is no longest chain.
+fn depth(
+    graph: &NoiseGraph,
+    node_id: NodeId,
+    depths: &mut HashMap<NodeId, usize>,
+    path: &mut Vec<NodeId>,
+) -> usize {
+    if let Some(depth) = depths.get(&node_id) {
+        return *depth;
+    }
+    if path.contains(&node_id) {
+        return 0;
+    }
+
+    path.push(node_id);
+    let depth = graph[node_id]
+        .input_ids()
+        .filter_map(|input_id| graph.connection(input_id))
+        .map(|output_id| depth(graph, graph[output_id].node, depths, path) + 1)
+        .max()
+        .unwrap_or(0);
+    path.pop();
+
+    depths.insert(node_id, depth);
+    depth
+}
+
+/// Roughly how tall a node is drawn, which depends on its inputs and whether
+/// it shows a thumbnail.
+fn estimated_height(graph: &NoiseGraph, node_id: NodeId) -> f32 {
+    let node = &graph[node_id];
+    let thumbnail = if node
+        .outputs(graph)
+        .any(|output| output.typ == ConnectionType::Noise)
+    {
+        170.0
+    } else {
+        0.0
+    };
+
+    60.0 + 30.0 * node.inputs.len() as f32 + thumbnail
+}
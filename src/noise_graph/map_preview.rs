@@ -0,0 +1,482 @@
+use std::collections::HashSet;
+
+use bevy::{
+    prelude::Transform,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use bevy_egui::egui::{
+    self, pos2, vec2, Checkbox, Color32, ColorImage, ComboBox, DragValue, Grid, Pos2, Rect, Sense,
+    Stroke, TextureFilter, TextureHandle, Widget,
+};
+use egui_node_graph::NodeId;
+use futures_lite::future;
+use noise::{utils::ColorGradient, NoiseFn};
+use strum::IntoEnumIterator;
+
+use super::{program, DynNoiseFn, NoiseGraphResource};
+
+/// The part of the world shown by the map preview, which is always square.
+#[derive(Clone, Copy, PartialEq)]
+struct MapView {
+    center: [f64; 2],
+    half_extent: f64,
+}
+
+impl Default for MapView {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0],
+            half_extent: 2048.0,
+        }
+    }
+}
+
+impl MapView {
+    /// The world units covered by a point of the screen when the view is
+    /// shown in `rect`.
+    fn units_per_point(&self, rect: Rect) -> f64 {
+        2.0 * self.half_extent / rect.width() as f64
+    }
+
+    /// The world position shown at a point of the screen. The x axis of the
+    /// world goes to the right and the z axis goes down.
+    fn world(&self, rect: Rect, pos: Pos2) -> [f64; 2] {
+        let offset = pos - rect.center();
+        let scale = self.units_per_point(rect);
+        [
+            self.center[0] + offset.x as f64 * scale,
+            self.center[1] + offset.y as f64 * scale,
+        ]
+    }
+
+    fn screen(&self, rect: Rect, [x, z]: [f64; 2]) -> Pos2 {
+        let scale = self.units_per_point(rect);
+        rect.center()
+            + vec2(
+                ((x - self.center[0]) / scale) as f32,
+                ((z - self.center[1]) / scale) as f32,
+            )
+    }
+
+    /// The area the view covers on the screen when another view is shown in
+    /// `rect`.
+    fn screen_rect(&self, rect: Rect, shown: &MapView) -> Rect {
+        let [x, z] = self.center;
+        let half = self.half_extent;
+        Rect::from_min_max(
+            shown.screen(rect, [x - half, z - half]),
+            shown.screen(rect, [x + half, z + half]),
+        )
+    }
+}
+
+/// How the values of the previewed noise are turned into colors.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, strum::Display, strum::EnumIter)]
+pub enum MapGradient {
+    /// From black at the lowest value to white at the highest one.
+    #[default]
+    Grayscale,
+    /// Water below zero and land above it, scaled so the furthest value from
+    /// zero reaches the end of the gradient.
+    Terrain,
+    /// The lowest to the highest value through the colors of the rainbow.
+    Rainbow,
+}
+
+/// The values sampled for a view of the preview, one per pixel, row by row.
+struct Samples {
+    view: MapView,
+    resolution: usize,
+    values: Vec<f64>,
+}
+
+/// A render of the preview which is still running on the async compute pool.
+/// Every band of rows is sampled by a task of its own.
+struct Rendering {
+    level: usize,
+    samples: Samples,
+    bands: Vec<(usize, Task<Vec<f64>>)>,
+}
+
+/// A 2D map of the active node. It is rendered progressively, at a coarse
+/// resolution first which is then refined, and again whenever the active node
+/// or anything it depends on changes.
+#[derive(Default)]
+pub struct MapPreview {
+    view: MapView,
+    gradient: MapGradient,
+    /// The distance between contour lines in output values, if they are
+    /// shown.
+    contours: Option<f64>,
+    node: Option<NodeId>,
+    noise: Option<DynNoiseFn>,
+    stale: bool,
+    error: Option<String>,
+    rendering: Option<Rendering>,
+    samples: Option<Samples>,
+    texture: Option<TextureHandle>,
+}
+
+impl MapPreview {
+    /// The resolutions the preview is rendered at, one after the other.
+    const LEVELS: [usize; 4] = [32, 64, 128, 256];
+    /// The rows sampled by each task.
+    const BAND_ROWS: usize = 16;
+    const DEFAULT_CONTOUR_INTERVAL: f64 = 0.1;
+
+    /// Marks the preview as outdated if it shows one of the given nodes.
+    pub fn invalidate(&mut self, nodes: &HashSet<NodeId>) {
+        if self.node.is_some_and(|node| nodes.contains(&node)) {
+            self.stale = true;
+        }
+    }
+
+    /// Samples the noise for the view again, dropping a render that is still
+    /// running.
+    fn render(&mut self, level: usize) {
+        self.rendering = self.noise.clone().map(|noise| {
+            let resolution = Self::LEVELS[level];
+            let view = self.view;
+            let bands = (0..resolution)
+                .step_by(Self::BAND_ROWS)
+                .map(|row| {
+                    let noise = noise.clone();
+                    let rows = row..(row + Self::BAND_ROWS).min(resolution);
+                    let task = AsyncComputeTaskPool::get()
+                        .spawn(async move { sample(&noise, view, resolution, rows) });
+                    (row, task)
+                })
+                .collect();
+
+            Rendering {
+                level,
+                samples: Samples {
+                    view,
+                    resolution,
+                    values: vec![0.0; resolution * resolution],
+                },
+                bands,
+            }
+        });
+    }
+
+    /// Collects the bands which are done. Once all of them are, the render
+    /// replaces the image and the next level is started. Returns whether the
+    /// image changed.
+    fn poll(&mut self) -> bool {
+        let Some(rendering) = &mut self.rendering else {
+            return false;
+        };
+
+        let Rendering { samples, bands, .. } = rendering;
+        bands.retain_mut(
+            |(row, task)| match future::block_on(future::poll_once(task)) {
+                Some(band) => {
+                    let start = *row * samples.resolution;
+                    samples.values[start..start + band.len()].copy_from_slice(&band);
+                    false
+                }
+                None => true,
+            },
+        );
+        if !bands.is_empty() {
+            return false;
+        }
+
+        let Some(rendering) = self.rendering.take() else {
+            return false;
+        };
+        self.samples = Some(rendering.samples);
+        if rendering.level + 1 < Self::LEVELS.len() {
+            self.render(rendering.level + 1);
+        }
+        true
+    }
+
+    /// Turns the latest samples into the texture of the preview.
+    fn update_texture(&mut self, ctx: &egui::Context) {
+        let Some(samples) = &self.samples else {
+            return;
+        };
+        let image = colorize(samples, self.gradient, self.contours);
+        match &mut self.texture {
+            Some(texture) => texture.set(image, TextureFilter::Linear),
+            None => {
+                self.texture = Some(ctx.load_texture("map preview", image, TextureFilter::Linear))
+            }
+        }
+    }
+}
+
+/// Samples the rows of a view at the centers of its pixels.
+fn sample(
+    noise: &DynNoiseFn,
+    view: MapView,
+    resolution: usize,
+    rows: std::ops::Range<usize>,
+) -> Vec<f64> {
+    let step = 2.0 * view.half_extent / resolution as f64;
+    let [left, top] = [
+        view.center[0] - view.half_extent,
+        view.center[1] - view.half_extent,
+    ];
+
+    let mut values = Vec::with_capacity(rows.len() * resolution);
+    for y in rows {
+        for x in 0..resolution {
+            values.push(noise.get([
+                left + (x as f64 + 0.5) * step,
+                top + (y as f64 + 0.5) * step,
+            ]));
+        }
+    }
+    values
+}
+
+/// Maps the samples onto a gradient, darkening the pixels where the values
+/// cross a multiple of the contour interval. Values which are not finite are
+/// red, like in the thumbnails.
+fn colorize(samples: &Samples, gradient: MapGradient, contours: Option<f64>) -> ColorImage {
+    let (min, max) = samples
+        .values
+        .iter()
+        .filter(|value| value.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+
+    let colors = match gradient {
+        MapGradient::Grayscale => ColorGradient::new().build_grayscale_gradient(),
+        MapGradient::Terrain => ColorGradient::new().build_terrain_gradient(),
+        MapGradient::Rainbow => ColorGradient::new().build_rainbow_gradient(),
+    };
+    let position = |value: f64| match gradient {
+        MapGradient::Terrain => {
+            let extent = min.abs().max(max.abs());
+            if extent > f64::EPSILON {
+                value / extent
+            } else {
+                0.0
+            }
+        }
+        MapGradient::Grayscale | MapGradient::Rainbow => {
+            if max - min > f64::EPSILON {
+                (value - min) / (max - min) * 2.0 - 1.0
+            } else {
+                0.0
+            }
+        }
+    };
+
+    let resolution = samples.resolution;
+    let contour = |index: usize| -> Option<f64> {
+        samples
+            .values
+            .get(index)
+            .filter(|value| value.is_finite())
+            .zip(contours)
+            .map(|(value, interval)| (value / interval).floor())
+    };
+
+    let pixels = samples
+        .values
+        .iter()
+        .enumerate()
+        .map(|(index, &value)| {
+            if !value.is_finite() {
+                return Color32::RED;
+            }
+            // The gradient has no color for the very end of its domain.
+            let [r, g, b, _] = colors.get_color(position(value).min(1.0 - f64::EPSILON));
+            let on_contour = contour(index).is_some_and(|band| {
+                let right = (index % resolution + 1 < resolution)
+                    .then(|| contour(index + 1))
+                    .flatten();
+                let below = contour(index + resolution);
+                [right, below]
+                    .into_iter()
+                    .flatten()
+                    .any(|other| other != band)
+            });
+            if on_contour {
+                Color32::from_rgb(r / 2, g / 2, b / 2)
+            } else {
+                Color32::from_rgb(r, g, b)
+            }
+        })
+        .collect();
+
+    ColorImage {
+        size: [resolution, resolution],
+        pixels,
+    }
+}
+
+/// A docked map of the active node, which can be panned by dragging and
+/// zoomed by scrolling. It marks the camera and the probed point.
+pub struct MapPreviewPanel<'a>(pub &'a mut NoiseGraphResource, pub Transform);
+
+impl<'a> Widget for MapPreviewPanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let MapPreviewPanel(resource, camera) = self;
+        let graph = &resource.state.graph;
+        let user_state = &mut resource.user_state;
+        let active_node = user_state
+            .active_node
+            .filter(|node_id| graph.nodes.contains_key(*node_id));
+        let preview = &mut user_state.map_preview;
+
+        ui.horizontal(|ui| {
+            ui.heading("Map");
+            if preview.rendering.is_some() {
+                ui.spinner();
+            }
+        });
+        let Some(node_id) = active_node else {
+            return ui.weak("Set a node active to preview it");
+        };
+
+        if preview.node != Some(node_id) {
+            preview.node = Some(node_id);
+            preview.stale = true;
+        }
+        if preview.stale {
+            preview.stale = false;
+            match program::compile(graph, node_id) {
+                Ok(program) => {
+                    preview.error = None;
+                    preview.noise = Some(DynNoiseFn::new(program));
+                    preview.render(0);
+                }
+                Err(e) => {
+                    preview.error = Some(e.to_string());
+                    preview.noise = None;
+                    preview.rendering = None;
+                }
+            }
+        }
+        if preview.poll() {
+            preview.update_texture(ui.ctx());
+        }
+
+        let previous_view = preview.view;
+        let size = ui.available_width().max(128.0);
+        let (rect, response) = ui.allocate_exact_size(vec2(size, size), Sense::drag());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        if let (Some(texture), Some(samples)) = (&preview.texture, &preview.samples) {
+            painter.add(egui::Shape::image(
+                texture.id(),
+                samples.view.screen_rect(rect, &preview.view),
+                Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+                Color32::WHITE,
+            ));
+        }
+
+        let camera_position = [camera.translation.x as f64, camera.translation.z as f64];
+        let camera_pos = preview.view.screen(rect, camera_position);
+        let forward = camera.forward();
+        let heading = vec2(forward.x, forward.z).normalized() * 12.0;
+        let marker = Stroke::new(2.0, Color32::WHITE);
+        painter.circle_stroke(camera_pos, 5.0, marker);
+        if heading.is_finite() {
+            painter.line_segment([camera_pos, camera_pos + heading], marker);
+        }
+        if let Some(point) = user_state.probe.point() {
+            let pos = preview.view.screen(rect, point);
+            let stroke = Stroke::new(2.0, Color32::GOLD);
+            painter.line_segment([pos - vec2(5.0, 5.0), pos + vec2(5.0, 5.0)], stroke);
+            painter.line_segment([pos - vec2(5.0, -5.0), pos + vec2(5.0, -5.0)], stroke);
+        }
+
+        let scale = preview.view.units_per_point(rect);
+        let drag = response.drag_delta();
+        preview.view.center[0] -= drag.x as f64 * scale;
+        preview.view.center[1] -= drag.y as f64 * scale;
+
+        if let Some(pos) = response.hover_pos() {
+            let scroll = ui.input().scroll_delta.y;
+            if scroll != 0.0 {
+                // Zoom around the pointer, keeping the point below it in
+                // place.
+                let anchor = preview.view.world(rect, pos);
+                preview.view.half_extent =
+                    (preview.view.half_extent * (-scroll as f64 * 0.002).exp()).clamp(1.0, 1e7);
+                let scale = preview.view.units_per_point(rect);
+                let offset = pos - rect.center();
+                preview.view.center = [
+                    anchor[0] - offset.x as f64 * scale,
+                    anchor[1] - offset.y as f64 * scale,
+                ];
+            }
+
+            let [x, z] = preview.view.world(rect, pos);
+            let value = preview
+                .noise
+                .as_ref()
+                .map(|noise| format!("{:.4}", noise.get([x, z])))
+                .unwrap_or_default();
+            ui.label(format!("x {x:.0}, z {z:.0}: {value}"));
+        } else {
+            ui.weak("Drag to pan, scroll to zoom");
+        }
+
+        if let Some(error) = &preview.error {
+            ui.colored_label(Color32::LIGHT_RED, error);
+        }
+
+        let previous_colors = (preview.gradient, preview.contours);
+        Grid::new("MapPreviewPanel.grid").show(ui, |ui| {
+            ui.label("gradient");
+            ComboBox::from_id_source("MapPreviewPanel.gradient")
+                .selected_text(preview.gradient.to_string())
+                .show_ui(ui, |ui| {
+                    for gradient in MapGradient::iter() {
+                        ui.selectable_value(&mut preview.gradient, gradient, gradient.to_string());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("contours");
+            ui.horizontal(|ui| {
+                let mut enabled = preview.contours.is_some();
+                if ui.add(Checkbox::new(&mut enabled, "")).changed() {
+                    preview.contours = enabled.then_some(MapPreview::DEFAULT_CONTOUR_INTERVAL);
+                }
+                if let Some(interval) = &mut preview.contours {
+                    ui.add(
+                        DragValue::new(interval)
+                            .speed(0.01)
+                            .clamp_range(1e-6..=f64::MAX),
+                    )
+                    .on_hover_text("The difference in value between neighbouring lines");
+                }
+            });
+            ui.end_row();
+
+            ui.label("view");
+            ui.horizontal(|ui| {
+                if ui.button("Center on camera").clicked() {
+                    preview.view.center = camera_position;
+                }
+                if ui.button("Reset").clicked() {
+                    preview.view = MapView::default();
+                }
+            });
+            ui.end_row();
+        });
+
+        if (preview.gradient, preview.contours) != previous_colors {
+            preview.update_texture(ui.ctx());
+        }
+        if preview.view != previous_view {
+            preview.render(0);
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,68 @@
+use bevy_egui::egui::{pos2, Color32, Rect};
+
+use super::{colorize, sample, MapGradient, MapView, Samples};
+use crate::noise_graph::{
+    dsl,
+    graph_ext::{EvaluationCache, GraphExt},
+};
+
+const VIEW: MapView = MapView {
+    center: [100.0, -50.0],
+    half_extent: 20.0,
+};
+
+#[test]
+fn views_map_the_screen_onto_the_world_and_back() {
+    let rect = Rect::from_min_max(pos2(10.0, 10.0), pos2(50.0, 50.0));
+
+    assert_eq!(VIEW.world(rect, rect.center()), VIEW.center);
+    assert_eq!(VIEW.world(rect, rect.min), [80.0, -70.0]);
+    assert_eq!(VIEW.world(rect, rect.max), [120.0, -30.0]);
+    assert_eq!(VIEW.screen(rect, [110.0, -45.0]), pos2(40.0, 35.0));
+
+    let zoomed_in = MapView {
+        center: VIEW.center,
+        half_extent: 10.0,
+    };
+    assert_eq!(
+        zoomed_in.screen_rect(rect, &VIEW),
+        Rect::from_min_max(pos2(20.0, 20.0), pos2(40.0, 40.0))
+    );
+}
+
+#[test]
+fn samples_are_taken_at_the_centers_of_the_pixels() {
+    let resource = dsl::parse("out = expression(\"x + 1000 * y\");").unwrap();
+    let noise = resource
+        .state
+        .graph
+        .evaluate_cached(
+            resource.user_state.active_node.unwrap(),
+            &mut EvaluationCache::default(),
+        )
+        .and_then(|value| value.try_to_noise_function())
+        .unwrap();
+    let values = sample(&noise, VIEW, 2, 1..2);
+
+    assert_eq!(values, [90.0 - 40_000.0, 110.0 - 40_000.0]);
+}
+
+#[test]
+fn colors_span_the_sampled_values_and_mark_contours() {
+    let samples = Samples {
+        view: VIEW,
+        resolution: 2,
+        values: vec![0.5, -1.0, 1.0, f64::NAN],
+    };
+
+    let image = colorize(&samples, MapGradient::Grayscale, None);
+    assert_eq!(image.size, [2, 2]);
+    assert_eq!(image.pixels[1], Color32::from_rgb(0, 0, 0));
+    assert_eq!(image.pixels[3], Color32::RED);
+
+    // Only the first pixel borders a value of another band, the others
+    // border the NaN or nothing.
+    let contoured = colorize(&samples, MapGradient::Grayscale, Some(1.0));
+    assert_ne!(contoured.pixels[0], image.pixels[0]);
+    assert_eq!(contoured.pixels[2], image.pixels[2]);
+}
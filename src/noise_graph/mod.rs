@@ -1,45 +1,68 @@
-use std::{
-    fmt::Debug,
-    fs::OpenOptions,
-    io::{BufWriter, Write},
-    sync::Arc,
-};
+use std::{collections::HashMap, fmt::Debug};
 
-use anyhow::Context;
 use bevy::{prelude::*, reflect::TypeUuid};
 use bevy_egui::egui;
 use egui_node_graph::{
-    Graph, GraphEditorState, NodeDataTrait, NodeId, NodeResponse, UserResponseTrait,
+    Graph, GraphEditorState, InputId, NodeDataTrait, NodeId, NodeResponse, OutputId,
+    UserResponseTrait,
 };
 use noise::{
     utils::{ImageRenderer, NoiseMapBuilder, PlaneMapBuilder},
-    Checkerboard, NoiseFn,
+    Checkerboard,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::pause::GameState;
+use crate::{chunk::MAX_CHANNELS, pause::GameState};
 
 use self::{
-    connection_type::ConnectionType,
-    graph_ext::GraphExt,
+    connection_type::{accept_conversions, restore_types, ConnectionType},
+    curve_editor::{CurveEditor, SourceHistograms},
+    file::GraphFile,
+    graph_ext::{EvaluationCache, GraphExt},
+    map_preview::MapPreview,
     node_attribute::NodeAttribute,
-    node_template::{AllNodeTemplates, NodeTemplate},
+    node_template::{AllNodeTemplates, NodeTemplate, Param},
+    parameters::ExposedParameters,
+    probe::Probe,
+    statistics::NodeStatistics,
+    thumbnail::{ThumbnailConfig, Thumbnails},
+};
+
+pub use self::{
+    file::GraphFileMenu,
+    map_preview::MapPreviewPanel,
+    parameters::GraphParameters,
+    probe::ProbePanel,
+    runtime::{DynNoiseFn, DynVectorFn, VectorFn},
+    thumbnail::ThumbnailSettings,
 };
 
 mod connection_type;
+mod curve_editor;
+mod dsl;
+mod export;
+mod file;
+mod format;
 mod graph_ext;
+mod layout;
+mod map_preview;
 mod node_attribute;
 mod node_template;
+mod parameters;
+mod probe;
+mod program;
+mod runtime;
+mod statistics;
+mod thumbnail;
 
 pub struct NoiseGraphPlugin; // TODO - use asset handles all over + save extension for AssetServer
 
 impl Plugin for NoiseGraphPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_enter(GameState::Running)
-                .with_system(evaluate_graph)
-                .with_system(save_graph),
-        );
+        app.add_system_set(SystemSet::on_enter(GameState::Running).with_system(evaluate_graph))
+            .add_system_set(
+                SystemSet::on_update(GameState::Running).with_system(probe::probe_terrain),
+            );
     }
 }
 
@@ -47,31 +70,35 @@ fn evaluate_graph(mut graph: ResMut<NoiseGraphResource>) {
     graph.update_current_noise();
 }
 
-fn save_graph(graph: Res<NoiseGraphResource>) {
-    if let Err(e) = graph.save() {
-        error!("Error while saving noise graph: {}", e);
-    }
-}
-
 // ========= First, define your user data types =============
 
 /// The NodeData holds a custom data struct inside each node. It's useful to
 /// store additional information that doesn't live in parameters. For this
 /// example, the node data stores the template (i.e. the "type") of the node.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NodeData {
     template: NodeTemplate,
+    /// How the number inputs can be edited, by input name. Taken from the
+    /// template rather than saved.
+    #[serde(skip)]
+    params: HashMap<String, Param>,
 }
 
 /// The response type is used to encode side-effects produced when drawing a
 /// node in the graph. Most side-effects (creating new nodes, deleting existing
 /// nodes, handling connections...) are already handled by the library, but this
 /// mechanism allows creating additional side effects from user code.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MyResponse {
     SetActiveNode(NodeId),
     ClearActiveNode,
     SaveImage,
+    ExportRust,
+    InputChanged(NodeId),
+    /// An input was exposed as a parameter, renamed or stopped being exposed.
+    ParametersChanged,
+    /// A value of an analysis was copied into a number input.
+    SetNumber(InputId, f64),
 }
 
 /// The graph 'global' state. This state struct is passed around to the node and
@@ -82,10 +109,45 @@ pub struct NoiseGraphState {
     active_node: Option<NodeId>,
     #[serde(skip)]
     current_noise: Option<DynNoiseFn>,
+    /// The compiled channels of the output nodes, see `channel_outputs`.
+    #[serde(skip)]
+    channels: Vec<(String, DynNoiseFn)>,
+    #[serde(default)]
+    thumbnail_config: ThumbnailConfig,
+    /// The inputs shown in the side panel, see `GraphParameters`.
+    #[serde(default)]
+    exposed: ExposedParameters,
+    #[serde(skip)]
+    thumbnails: Thumbnails,
+    #[serde(skip)]
+    source_histograms: SourceHistograms,
+    #[serde(skip)]
+    statistics: NodeStatistics,
+    #[serde(skip)]
+    probe: Probe,
+    #[serde(skip)]
+    map_preview: MapPreview,
+    #[serde(skip)]
+    evaluation_cache: EvaluationCache,
+    #[serde(skip)]
+    editor_error: Option<String>,
+    /// The connections when the connection being dragged was started. The
+    /// editor replaces the connection of the input it's dropped on before we
+    /// get the chance to reject it, so this is where it is restored from.
+    #[serde(skip)]
+    connections_before_drag: HashMap<InputId, OutputId>,
+    /// The graph before `accept_conversions` retagged its ports for the
+    /// connection being dragged, taken once when the drag starts. Nodes are
+    /// drawn from it, so nothing they evaluate or show depends on the drag.
+    #[serde(skip)]
+    graph_before_drag: Option<NoiseGraph>,
 }
 
 pub type NoiseGraph = Graph<NodeData, ConnectionType, NodeAttribute>;
 
+/// The channel which shapes the terrain instead of the active node.
+pub const HEIGHT_CHANNEL: &str = "height";
+
 #[derive(Default, Resource, Serialize, Deserialize, TypeUuid)]
 #[uuid = "b452a8a1-82fe-42a1-be25-c931c310e008"]
 pub struct NoiseGraphResource {
@@ -94,10 +156,10 @@ pub struct NoiseGraphResource {
     state: GraphEditorState<NodeData, ConnectionType, NodeAttribute, NodeTemplate, NoiseGraphState>,
 
     user_state: NoiseGraphState,
-}
 
-#[derive(Clone)]
-pub struct DynNoiseFn(Arc<dyn NoiseFn<f64, 2> + Send + Sync>);
+    #[serde(skip)]
+    file: GraphFile,
+}
 
 // =========== Then, you need to implement some traits ============
 
@@ -124,12 +186,40 @@ impl NodeDataTrait for NodeData {
     where
         MyResponse: UserResponseTrait,
     {
+        let graph_before_drag = user_state.graph_before_drag.take();
+        let graph = graph_before_drag
+            .as_ref()
+            .filter(|graph| graph.nodes.contains_key(node_id))
+            .unwrap_or(graph);
         let mut responses = vec![];
         let is_active = user_state
             .active_node
             .map(|id| id == node_id)
             .unwrap_or(false);
 
+        if CurveEditor::of(graph[node_id].user_data.template).is_some() {
+            let config = user_state.thumbnail_config;
+            user_state.source_histograms.update(
+                node_id,
+                graph,
+                &mut user_state.evaluation_cache,
+                config,
+            );
+        }
+
+        match graph[node_id].user_data.template {
+            NodeTemplate::Output => channel_label(ui, graph, node_id),
+            NodeTemplate::Normalize => {
+                estimated_range_label(ui, graph, node_id, &mut user_state.evaluation_cache)
+            }
+            _ => {}
+        }
+
+        conversion_labels(ui, graph, node_id);
+        user_state
+            .probe
+            .show(ui, node_id, graph, &mut user_state.evaluation_cache);
+
         let outputs_noise = graph[node_id]
             .outputs(graph)
             .any(|output| output.typ == ConnectionType::Noise);
@@ -149,29 +239,39 @@ impl NodeDataTrait for NodeData {
                 if ui.button("Save image").clicked() {
                     responses.push(NodeResponse::User(MyResponse::SaveImage));
                 }
+                if ui.button("Export Rust").clicked() {
+                    responses.push(NodeResponse::User(MyResponse::ExportRust));
+                }
+            }
+
+            if user_state.thumbnail_config.enabled {
+                let config = user_state.thumbnail_config;
+                user_state.thumbnails.show(
+                    ui,
+                    node_id,
+                    graph,
+                    &mut user_state.evaluation_cache,
+                    config,
+                );
             }
+
+            let open = user_state.statistics.is_open(node_id);
+            if ui.selectable_label(open, "📊 Analyse").clicked() {
+                user_state.statistics.toggle(node_id);
+            }
+            let statistics =
+                user_state
+                    .statistics
+                    .show(ui, node_id, graph, &mut user_state.evaluation_cache);
+            responses.extend(statistics.into_iter().map(NodeResponse::User));
         }
 
+        user_state.graph_before_drag = graph_before_drag;
         responses
     }
 }
 
 impl NoiseGraphResource {
-    const FILE_PATH: &'static str = "assets/noise_graph.json";
-
-    fn save(&self) -> anyhow::Result<()> {
-        let mut writer = BufWriter::new(
-            OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(Self::FILE_PATH)
-                .context("Unable to open file")?,
-        );
-        serde_json::to_writer_pretty(&mut writer, &self).context("Unable to save to json")?;
-        writer.flush().context("Unable to save file")
-    }
-
     fn save_image(&self) -> anyhow::Result<()> {
         let node = self
             .user_state
@@ -199,11 +299,44 @@ impl NoiseGraphResource {
         Ok(())
     }
 
+    fn export_rust(&self) -> anyhow::Result<()> {
+        let node_id = self
+            .user_state
+            .active_node
+            .ok_or(anyhow::anyhow!("No active node"))?;
+        let name = self.state.graph.node_name(node_id);
+        let code = export::export_rust(&self.state.graph, node_id)?;
+        std::fs::write(format!("{name}.rs"), code)?;
+
+        Ok(())
+    }
+
+    /// The noise the height of the terrain comes from, the height channel if
+    /// there is one and the active node otherwise.
+    pub fn terrain_height(&self) -> DynNoiseFn {
+        self.user_state
+            .channels
+            .iter()
+            .find(|(name, _)| name == HEIGHT_CHANNEL)
+            .map(|(_, noise)| noise.clone())
+            .unwrap_or_else(|| self.get_noise_fn())
+    }
+
+    /// The channels besides the height in the order of their names, which is
+    /// the order of the components of `ATTRIBUTE_CHANNELS`.
+    pub fn terrain_channels(&self) -> Vec<DynNoiseFn> {
+        self.user_state
+            .channels
+            .iter()
+            .filter(|(name, _)| name != HEIGHT_CHANNEL)
+            .map(|(_, noise)| noise.clone())
+            .collect()
+    }
+
     pub fn get_noise_fn(&self) -> DynNoiseFn {
         self.user_state
             .current_noise
-            .as_ref()
-            .map(|noise| noise.clone())
+            .clone()
             .unwrap_or_else(|| DynNoiseFn::new(Checkerboard::default()))
     }
 
@@ -220,28 +353,173 @@ impl NoiseGraphResource {
     fn update_current_noise(&mut self) {
         if let Some(node) = self.user_state.active_node {
             if self.state.graph.nodes.contains_key(node) {
-                if let Ok(value) = self.state.graph.evaluate(node) {
-                    self.user_state.current_noise = value.try_to_noise_function().ok();
+                match program::compile(&self.state.graph, node) {
+                    Ok(program) => self.user_state.current_noise = Some(DynNoiseFn::new(program)),
+                    Err(e) => error!("Error while compiling noise graph: {e}"),
                 }
             } else {
                 self.user_state.active_node = None;
             }
         }
+
+        let graph = &self.state.graph;
+        self.user_state.channels = channel_outputs(graph)
+            .into_iter()
+            .filter_map(|(name, node_id)| match program::compile(graph, node_id) {
+                Ok(program) => Some((name, DynNoiseFn::new(program))),
+                Err(e) => {
+                    error!("Error while compiling the '{name}' channel: {e}");
+                    None
+                }
+            })
+            .collect();
+    }
+
+    /// Takes back a connection the editor made if it creates a cycle, in which
+    /// case the input gets the connection it had before the drag again, and
+    /// marks the graph as changed otherwise.
+    fn end_connection(&mut self, output: OutputId, input: InputId) {
+        let graph = &mut self.state.graph;
+        let before = std::mem::take(&mut self.user_state.connections_before_drag);
+
+        if graph.creates_cycle(output, input) {
+            graph.remove_connection(input);
+            if let Some(previous) = before.get(&input) {
+                graph.add_connection(*previous, input);
+            }
+            self.user_state.editor_error = Some(format!(
+                "Can't connect '{}' to '{}', it would create a cycle",
+                graph.node_name(graph[output].node),
+                graph.node_name(graph[input].node),
+            ));
+        } else {
+            self.user_state.invalidate(graph, graph[input].node);
+            self.file.mark_changed();
+        }
+    }
+}
+
+/// The output nodes with the channels they define, sorted by name. If several
+/// output nodes have the same name, only the first one counts.
+fn channel_outputs(graph: &NoiseGraph) -> Vec<(String, NodeId)> {
+    let mut outputs: Vec<(String, NodeId)> = graph
+        .iter_nodes()
+        .filter(|node_id| matches!(graph[*node_id].user_data.template, NodeTemplate::Output))
+        .map(|node_id| (graph.node_name(node_id), node_id))
+        .collect();
+    outputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    outputs.dedup_by(|(a, _), (b, _)| a == b);
+    outputs
+}
+
+/// Shows the range a normalize node estimated for its source, which it
+/// outputs as "min" and "max".
+fn estimated_range_label(
+    ui: &mut egui::Ui,
+    graph: &NoiseGraph,
+    node_id: NodeId,
+    evaluation_cache: &mut EvaluationCache,
+) {
+    if graph.evaluate_cached(node_id, evaluation_cache).is_err() {
+        return;
+    }
+
+    let output = |name: &str| {
+        let output_id = graph[node_id].get_output(name).ok()?;
+        evaluation_cache.get(output_id)?.clone().try_to_f64().ok()
+    };
+    if let (Some(min), Some(max)) = (output("min"), output("max")) {
+        ui.label(format!("Estimated range {min:.3} to {max:.3}"));
+    }
+}
+
+/// Tells what the channel of an output node is used for.
+fn channel_label(ui: &mut egui::Ui, graph: &NoiseGraph, node_id: NodeId) {
+    let outputs = channel_outputs(graph);
+    let name = graph.node_name(node_id);
+    let mut channels = outputs
+        .iter()
+        .filter(|(name, _)| name != HEIGHT_CHANNEL)
+        .map(|(_, node_id)| *node_id);
+
+    if !outputs.iter().any(|(_, id)| *id == node_id) {
+        ui.colored_label(
+            egui::Color32::RED,
+            format!("Another output defines '{name}'"),
+        );
+    } else if name == HEIGHT_CHANNEL {
+        ui.label("Shapes the terrain");
+    } else {
+        match channels.position(|id| id == node_id) {
+            Some(index) if index < MAX_CHANNELS => {
+                ui.label(format!("Channel {index} of the chunk meshes"));
+            }
+            _ => {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("Only {MAX_CHANNELS} channels fit into the chunk meshes"),
+                );
+            }
+        }
+    }
+}
+
+/// Names the conversion of every input connected to an output of another type.
+/// The editor draws the connections itself without telling where their ports
+/// are, so the conversions are listed in the node instead of on the wires.
+fn conversion_labels(ui: &mut egui::Ui, graph: &NoiseGraph, node_id: NodeId) {
+    for (name, input_id) in &graph[node_id].inputs {
+        let conversion = graph
+            .connection(*input_id)
+            .and_then(|output_id| graph[output_id].typ.conversion(graph[*input_id].typ));
+        if let Some(conversion) = conversion {
+            ui.label(egui::RichText::new(format!("{name}: {conversion}")).weak());
+        }
+    }
+}
+
+impl NoiseGraphState {
+    /// Marks a node and everything downstream of it as changed.
+    fn invalidate(&mut self, graph: &NoiseGraph, node_id: NodeId) {
+        let dirty = self.evaluation_cache.invalidate(graph, node_id);
+        self.thumbnails.invalidate(&dirty);
+        self.source_histograms.invalidate(&dirty);
+        self.statistics.invalidate(&dirty);
+        self.map_preview.invalidate(&dirty);
     }
 }
 
 impl egui::Widget for &mut NoiseGraphResource {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let retagged = match self.state.connection_in_progress {
+            Some((_, dragged)) => {
+                // Nothing but the drag itself changes the graph while the
+                // mouse is held, so the copy stays valid until it ends.
+                if self.user_state.graph_before_drag.is_none() {
+                    self.user_state.graph_before_drag = Some(self.state.graph.clone());
+                }
+                accept_conversions(&mut self.state.graph, dragged)
+            }
+            None => {
+                self.user_state.graph_before_drag = None;
+                Vec::new()
+            }
+        };
         let graph_response =
             self.state
                 .draw_graph_editor(ui, AllNodeTemplates, &mut self.user_state);
+        restore_types(&mut self.state.graph, retagged);
 
         for node_response in graph_response.node_responses {
             // Here, we ignore all other graph events. But you may find
             // some use for them. For example, by playing a sound when a new
             // connection is created
-            if let NodeResponse::User(user_event) = node_response {
-                match user_event {
+            if changes_graph(&node_response) {
+                self.file.mark_changed();
+            }
+
+            match node_response {
+                NodeResponse::User(user_event) => match user_event {
                     MyResponse::SetActiveNode(node) => self.user_state.active_node = Some(node),
                     MyResponse::ClearActiveNode => self.user_state.active_node = None,
                     MyResponse::SaveImage => {
@@ -252,10 +530,64 @@ impl egui::Widget for &mut NoiseGraphResource {
                             NoiseGraphResource::debug_text(ui.ctx(), e)
                         }
                     }
+                    MyResponse::ExportRust => {
+                        if let Err(e) = self.export_rust() {
+                            error!("{e}");
+                            NoiseGraphResource::debug_text(ui.ctx(), e)
+                        }
+                    }
+                    MyResponse::InputChanged(node) => {
+                        let graph = &mut self.state.graph;
+                        graph[node].user_data.template.sync_inputs(graph, node);
+                        self.user_state.invalidate(graph, node)
+                    }
+                    MyResponse::ParametersChanged => {}
+                    MyResponse::SetNumber(input_id, value) => {
+                        let graph = &mut self.state.graph;
+                        let node = graph[input_id].node;
+                        graph.inputs[input_id].value = NodeAttribute::F64(value);
+                        graph[node].user_data.template.sync_inputs(graph, node);
+                        self.user_state.invalidate(graph, node)
+                    }
+                },
+                NodeResponse::ConnectEventStarted(..) => {
+                    self.user_state.editor_error = None;
+                    self.user_state.connections_before_drag =
+                        self.state.graph.iter_connections().collect();
+                }
+                NodeResponse::ConnectEventEnded { input, output } => {
+                    self.end_connection(output, input)
+                }
+                NodeResponse::DisconnectEvent { input, .. } => {
+                    // Dragging a connection away from an input starts a new
+                    // drag from its output.
+                    if self.state.connection_in_progress.is_some() {
+                        self.user_state.connections_before_drag =
+                            self.state.graph.iter_connections().collect();
+                    }
+                    // The input is gone already if the disconnect was caused
+                    // by deleting its node.
+                    if let Some(input) = self.state.graph.try_get_input(input) {
+                        self.user_state.invalidate(&self.state.graph, input.node)
+                    }
                 }
+                NodeResponse::DeleteNodeFull { node_id, .. } => {
+                    self.user_state.thumbnails.remove(node_id);
+                    self.user_state.source_histograms.remove(node_id);
+                    self.user_state.exposed.remove_node(node_id);
+                    self.user_state.statistics.remove(node_id);
+                    self.user_state
+                        .evaluation_cache
+                        .remove_deleted(&self.state.graph);
+                }
+                _ => {}
             }
         }
 
+        if let Some(error) = &self.user_state.editor_error {
+            NoiseGraphResource::debug_text(ui.ctx(), error);
+        }
+
         ui.allocate_rect(
             ui.min_rect(),
             egui::Sense::click().union(egui::Sense::drag()),
@@ -263,14 +595,25 @@ impl egui::Widget for &mut NoiseGraphResource {
     }
 }
 
-impl DynNoiseFn {
-    fn new<T: NoiseFn<f64, 2> + Send + Sync + 'static>(noise: T) -> Self {
-        Self(Arc::new(noise))
-    }
+/// Whether a response changes anything that is saved with the graph, which
+/// includes the positions of the nodes and the active node. Connections only
+/// change it once they are accepted, see `end_connection`.
+fn changes_graph(response: &NodeResponse<MyResponse, NodeData>) -> bool {
+    matches!(
+        response,
+        NodeResponse::DisconnectEvent { .. }
+            | NodeResponse::CreatedNode(_)
+            | NodeResponse::DeleteNodeFull { .. }
+            | NodeResponse::MoveNode { .. }
+            | NodeResponse::User(
+                MyResponse::SetActiveNode(_)
+                    | MyResponse::ClearActiveNode
+                    | MyResponse::InputChanged(_)
+                    | MyResponse::ParametersChanged
+                    | MyResponse::SetNumber(..)
+            )
+    )
 }
 
-impl NoiseFn<f64, 2> for DynNoiseFn {
-    fn get(&self, point: [f64; 2]) -> f64 {
-        self.0.get(point)
-    }
-}
+#[cfg(test)]
+mod tests;
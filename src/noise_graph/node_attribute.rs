@@ -1,11 +1,21 @@
 use bevy_egui::egui::{self, Color32, ComboBox, DragValue, TextEdit};
 use egui_node_graph::{NodeId, WidgetValueTrait};
-use noise::core::worley::ReturnType;
+use noise::{
+    core::worley::{distance_functions, ReturnType},
+    Constant, OpenSimplex, Perlin, PerlinSurflet, Simplex, SuperSimplex, Value,
+};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use strum::IntoEnumIterator;
 
-use super::{DynNoiseFn, MyResponse, NodeData, NoiseGraphState};
+use super::{
+    connection_type::ConnectionType,
+    curve_editor::CurveEditor,
+    dsl::Formula,
+    node_template::{DistanceFunction, SyncWorley, MAX_DECIMALS},
+    parameters::input_label,
+    DynNoiseFn, DynVectorFn, MyResponse, NodeData, NoiseGraphState,
+};
 
 /// In the graph, input parameters can optionally have a constant value. This
 /// value can be directly edited in a widget inside the node itself.
@@ -19,20 +29,26 @@ use super::{DynNoiseFn, MyResponse, NodeData, NoiseGraphState};
 pub enum NodeAttribute {
     F64(f64),
     Usize(usize),
+    Bool(bool),
     Perlin,
     ScaleBias,
     #[serde(skip)]
     NoiseFunction(DynNoiseFn),
+    #[serde(skip)]
+    VectorFunction(DynVectorFn),
     NoInput,
     NoiseType(NoiseType),
     Operator(Operator),
     Name(String),
+    /// The formula of an expression node, see `Formula`.
+    Formula(String),
     Vec {
         values: Vec<NodeAttribute>,
         template: Box<NodeAttribute>,
     },
     F64Tuple(f64, f64),
     ReturnType(WorleyReturnType),
+    DistanceFunction(WorleyDistanceFunction),
 }
 
 #[derive(
@@ -41,6 +57,27 @@ pub enum NodeAttribute {
 pub enum NoiseType {
     Perlin,
     Simplex,
+    OpenSimplex,
+    SuperSimplex,
+    Value,
+    Worley,
+    PerlinSurflet,
+}
+
+impl NoiseType {
+    /// The generator with its default settings, as used for the octaves of
+    /// fractals.
+    pub fn generator(self, seed: u32) -> DynNoiseFn {
+        match self {
+            NoiseType::Perlin => DynNoiseFn::new(Perlin::new(seed)),
+            NoiseType::Simplex => DynNoiseFn::new(Simplex::new(seed)),
+            NoiseType::OpenSimplex => DynNoiseFn::new(OpenSimplex::new(seed)),
+            NoiseType::SuperSimplex => DynNoiseFn::new(SuperSimplex::new(seed)),
+            NoiseType::Value => DynNoiseFn::new(Value::new(seed)),
+            NoiseType::Worley => DynNoiseFn::new(SyncWorley::new(seed)),
+            NoiseType::PerlinSurflet => DynNoiseFn::new(PerlinSurflet::new(seed)),
+        }
+    }
 }
 
 #[derive(
@@ -83,6 +120,27 @@ impl From<WorleyReturnType> for ReturnType {
     }
 }
 
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, strum::Display, strum::EnumIter, Deserialize, Serialize,
+)]
+pub enum WorleyDistanceFunction {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+    Quadratic,
+}
+
+impl WorleyDistanceFunction {
+    pub fn function(self) -> DistanceFunction {
+        match self {
+            WorleyDistanceFunction::Euclidean => distance_functions::euclidean,
+            WorleyDistanceFunction::Manhattan => distance_functions::manhattan,
+            WorleyDistanceFunction::Chebyshev => distance_functions::chebyshev,
+            WorleyDistanceFunction::Quadratic => distance_functions::quadratic,
+        }
+    }
+}
+
 impl WidgetValueTrait for NodeAttribute {
     type UserState = NoiseGraphState;
     type NodeData = NodeData;
@@ -95,105 +153,245 @@ impl WidgetValueTrait for NodeAttribute {
         user_state: &mut NoiseGraphState,
         node_state: &NodeData,
     ) -> Vec<MyResponse> {
-        const MAX_DECIMALS: usize = 5;
+        self.edit(
+            param_name,
+            ValueLabel::Input,
+            node_id,
+            ui,
+            user_state,
+            node_state,
+        )
+    }
+}
+
+/// How the widget of a value is labelled.
+#[derive(Clone, Copy)]
+pub enum ValueLabel<'a> {
+    /// The name of a node input, which can be right clicked to expose the
+    /// input as a graph parameter.
+    Input,
+    Plain(&'a str),
+    /// No label, for when the caller shows one.
+    Hidden,
+}
 
+impl ValueLabel<'_> {
+    /// Shows the label, returns whether the exposed parameters changed.
+    fn show(
+        self,
+        ui: &mut egui::Ui,
+        param_name: &str,
+        node_id: NodeId,
+        user_state: &mut NoiseGraphState,
+    ) -> bool {
+        match self {
+            ValueLabel::Input => input_label(ui, &mut user_state.exposed, node_id, param_name),
+            _ => {
+                self.show_plain(ui, param_name);
+                false
+            }
+        }
+    }
+
+    /// Shows the label without offering to expose the input.
+    fn show_plain(self, ui: &mut egui::Ui, param_name: &str) {
+        match self {
+            ValueLabel::Input => {
+                ui.label(param_name);
+            }
+            ValueLabel::Plain(text) => {
+                ui.label(text);
+            }
+            ValueLabel::Hidden => {}
+        }
+    }
+}
+
+impl NodeAttribute {
+    /// The widget editing the value, used inside of nodes and by the panel
+    /// of exposed parameters.
+    #[allow(clippy::only_used_in_recursion)]
+    pub fn edit(
+        &mut self,
+        param_name: &str,
+        label: ValueLabel,
+        node_id: NodeId,
+        ui: &mut egui::Ui,
+        user_state: &mut NoiseGraphState,
+        node_state: &NodeData,
+    ) -> Vec<MyResponse> {
+        let mut changed = false;
+        let mut exposed_changed = false;
         // This trait is used to tell the library which UI to display for the
         // inline parameter widgets.
         match self {
             NodeAttribute::F64(value) => {
+                let param = node_state.params.get(param_name).cloned();
                 ui.horizontal(|ui| {
-                    ui.label(param_name);
-                    ui.add(DragValue::new(value).max_decimals(MAX_DECIMALS));
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
+                    changed |= param.unwrap_or_default().show(ui, value).changed();
                 });
             }
             NodeAttribute::Usize(value) => {
+                let param = node_state.params.get(param_name).cloned();
+                ui.horizontal(|ui| {
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
+                    changed |= param.unwrap_or_default().show(ui, value).changed();
+                });
+            }
+            NodeAttribute::Bool(value) => {
                 ui.horizontal(|ui| {
-                    ui.label(param_name);
-                    ui.add(DragValue::new(value));
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
+                    let tooltip = node_state
+                        .params
+                        .get(param_name)
+                        .and_then(|param| param.tooltip);
+                    let response = ui.checkbox(value, "");
+                    changed |= match tooltip {
+                        Some(tooltip) => response.on_hover_text(tooltip),
+                        None => response,
+                    }
+                    .changed();
                 });
             }
             NodeAttribute::NoiseType(noise_type) => {
                 ui.horizontal(|ui| {
-                    ui.label(param_name);
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
                     ComboBox::from_id_source(param_name)
                         .selected_text(noise_type.to_string())
                         .show_ui(ui, |ui| {
                             for available in NoiseType::iter() {
-                                ui.selectable_value(noise_type, available, available.to_string());
+                                changed |= ui
+                                    .selectable_value(noise_type, available, available.to_string())
+                                    .changed();
                             }
                         });
                 });
             }
             NodeAttribute::Operator(operator) => {
                 ui.horizontal(|ui| {
-                    ui.label(param_name);
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
                     ComboBox::from_id_source(param_name)
                         .selected_text(operator.to_string())
                         .show_ui(ui, |ui| {
                             for available in Operator::iter() {
-                                ui.selectable_value(operator, available, available.to_string());
+                                changed |= ui
+                                    .selectable_value(operator, available, available.to_string())
+                                    .changed();
                             }
                         });
                 });
             }
             NodeAttribute::Name(name) => {
-                ui.add(TextEdit::singleline(name).text_color(Color32::LIGHT_GREEN));
+                changed |= ui
+                    .add(TextEdit::singleline(name).text_color(Color32::LIGHT_GREEN))
+                    .changed();
+            }
+            NodeAttribute::Formula(formula) => {
+                ui.horizontal(|ui| {
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
+                    changed |= ui
+                        .add(TextEdit::singleline(formula).code_editor())
+                        .changed();
+                });
+                if let Err(e) = Formula::parse(formula) {
+                    ui.colored_label(Color32::LIGHT_RED, e.to_string());
+                }
             }
             NodeAttribute::Vec { values, template } => {
-                ui.label(param_name);
+                label.show_plain(ui, param_name);
+                if let Some(editor) = CurveEditor::of(node_state.template) {
+                    let histogram = user_state.source_histograms.get(node_id);
+                    changed |= editor.show(ui, node_id, values, histogram);
+                }
                 ui.indent("values", |ui| {
                     ui.vertical(|ui| {
                         let mut indices_to_remove = Vec::with_capacity(values.len());
 
-                        for i in 0..values.len() {
+                        for (i, value) in values.iter_mut().enumerate() {
+                            let index = i.to_string();
                             ui.horizontal(|ui| {
-                                values[i].value_widget(
-                                    &i.to_string(),
-                                    node_id,
-                                    ui,
-                                    user_state,
-                                    node_state,
-                                );
+                                changed |= !value
+                                    .edit(
+                                        &index,
+                                        ValueLabel::Plain(&index),
+                                        node_id,
+                                        ui,
+                                        user_state,
+                                        node_state,
+                                    )
+                                    .is_empty();
                                 if ui.button("x").clicked() {
                                     indices_to_remove.push(i);
                                 }
                             });
                         }
 
+                        changed |= !indices_to_remove.is_empty();
                         for index in indices_to_remove {
                             values.remove(index);
                         }
                     });
                     if ui.button("+").clicked() {
                         values.push(*template.clone());
+                        changed = true;
                     }
                 });
             }
             NodeAttribute::F64Tuple(first, second) => {
                 ui.horizontal(|ui| {
-                    ui.label(param_name);
-                    ui.add(DragValue::new(first).max_decimals(MAX_DECIMALS));
-                    ui.add(DragValue::new(second).max_decimals(MAX_DECIMALS));
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
+                    changed |= ui
+                        .add(DragValue::new(first).max_decimals(MAX_DECIMALS))
+                        .changed();
+                    changed |= ui
+                        .add(DragValue::new(second).max_decimals(MAX_DECIMALS))
+                        .changed();
                 });
             }
             NodeAttribute::ReturnType(return_type) => {
                 ui.horizontal(|ui| {
-                    ui.label(param_name);
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
                     ComboBox::from_id_source(param_name)
                         .selected_text(return_type.to_string())
                         .show_ui(ui, |ui| {
                             for available in WorleyReturnType::iter() {
-                                ui.selectable_value(return_type, available, available.to_string());
+                                changed |= ui
+                                    .selectable_value(return_type, available, available.to_string())
+                                    .changed();
                             }
                         });
                 });
             }
-            _ => {
-                ui.label(param_name);
+            NodeAttribute::DistanceFunction(distance_function) => {
+                ui.horizontal(|ui| {
+                    exposed_changed |= label.show(ui, param_name, node_id, user_state);
+                    ComboBox::from_id_source(param_name)
+                        .selected_text(distance_function.to_string())
+                        .show_ui(ui, |ui| {
+                            for available in WorleyDistanceFunction::iter() {
+                                changed |= ui
+                                    .selectable_value(
+                                        distance_function,
+                                        available,
+                                        available.to_string(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                });
             }
+            _ => label.show_plain(ui, param_name),
         }
         // This allows you to return your responses from the inline widgets.
-        Vec::new()
+        let mut responses = Vec::new();
+        if changed {
+            responses.push(MyResponse::InputChanged(node_id));
+        }
+        if exposed_changed {
+            responses.push(MyResponse::ParametersChanged);
+        }
+        responses
     }
 }
 
@@ -222,6 +420,14 @@ impl NodeAttribute {
         }
     }
 
+    pub fn try_to_bool(self) -> anyhow::Result<bool> {
+        if let NodeAttribute::Bool(value) = self {
+            Ok(value)
+        } else {
+            self.invalid_cast("Bool")
+        }
+    }
+
     pub fn try_to_noise_function(self) -> anyhow::Result<DynNoiseFn> {
         if let NodeAttribute::NoiseFunction(noise_function) = self {
             Ok(noise_function)
@@ -230,6 +436,14 @@ impl NodeAttribute {
         }
     }
 
+    pub fn try_to_vector_function(self) -> anyhow::Result<DynVectorFn> {
+        if let NodeAttribute::VectorFunction(vector_function) = self {
+            Ok(vector_function)
+        } else {
+            self.invalid_cast("VectorFunction")
+        }
+    }
+
     pub fn try_to_noise_type(self) -> anyhow::Result<NoiseType> {
         if let NodeAttribute::NoiseType(ty) = self {
             Ok(ty)
@@ -246,6 +460,14 @@ impl NodeAttribute {
         }
     }
 
+    pub fn try_to_formula(self) -> anyhow::Result<Formula> {
+        if let NodeAttribute::Formula(formula) = &self {
+            Formula::parse(formula).map_err(|e| anyhow::anyhow!("Invalid formula, {e}"))
+        } else {
+            self.invalid_cast("Formula")
+        }
+    }
+
     pub fn try_to_vec(self) -> anyhow::Result<Vec<NodeAttribute>> {
         if let NodeAttribute::Vec { values, .. } = self {
             Ok(values)
@@ -270,6 +492,36 @@ impl NodeAttribute {
         }
     }
 
+    pub fn try_to_distance_function(self) -> anyhow::Result<WorleyDistanceFunction> {
+        if let NodeAttribute::DistanceFunction(distance_function) = self {
+            Ok(distance_function)
+        } else {
+            self.invalid_cast("DistanceFunction")
+        }
+    }
+
+    /// Seeds are stored as usize, but generators only take 32 bit seeds.
+    pub fn try_to_seed(self) -> anyhow::Result<u32> {
+        let seed = self.try_to_usize()?;
+        u32::try_from(seed).map_err(|_| anyhow::anyhow!("Seed {seed} is larger than {}", u32::MAX))
+    }
+
+    /// Converts the value of an output for an input of another type, see
+    /// `ConnectionType::conversion`. Other values stay as they are.
+    pub fn convert_to(self, typ: ConnectionType) -> Self {
+        match (self, typ) {
+            (NodeAttribute::F64(value), ConnectionType::Noise) => {
+                NodeAttribute::NoiseFunction(DynNoiseFn::new(Constant::new(value)))
+            }
+            // Negative numbers and NaN become 0.
+            (NodeAttribute::F64(value), ConnectionType::Usize) => {
+                NodeAttribute::Usize(value.round() as usize)
+            }
+            (NodeAttribute::Usize(value), ConnectionType::F64) => NodeAttribute::F64(value as f64),
+            (value, _) => value,
+        }
+    }
+
     fn invalid_cast<T>(self, ty: &str) -> anyhow::Result<T> {
         anyhow::bail!("Invalid cast from {} to {}", self, ty)
     }
@@ -2,10 +2,18 @@ use egui_node_graph::{Graph, InputParamKind, NodeId};
 
 use crate::noise_graph::{
     connection_type::ConnectionType,
-    node_attribute::{NodeAttribute, NoiseType, Operator, WorleyReturnType},
+    node_attribute::{
+        NodeAttribute, NoiseType, Operator, WorleyDistanceFunction, WorleyReturnType,
+    },
+    node_template::MAX_OCTAVES,
     NodeData,
 };
 
+use super::Param;
+
+/// The name of newly created nodes.
+pub const DEFAULT_NODE_NAME: &str = "New Node";
+
 pub struct NodeBuilder<'a> {
     graph: &'a mut Graph<NodeData, ConnectionType, NodeAttribute>,
     node_id: NodeId,
@@ -24,7 +32,7 @@ impl<'a> NodeBuilder<'a> {
             self.node_id,
             "name".into(),
             ConnectionType::NoConnection,
-            NodeAttribute::Name("New Node".into()),
+            NodeAttribute::Name(DEFAULT_NODE_NAME.into()),
             InputParamKind::ConstantOnly,
             true,
         );
@@ -55,6 +63,18 @@ impl<'a> NodeBuilder<'a> {
         self
     }
 
+    pub fn input_bool(&mut self, name: &str, initial: bool) -> &mut Self {
+        self.graph.add_input_param(
+            self.node_id,
+            name.into(),
+            ConnectionType::NoConnection,
+            NodeAttribute::Bool(initial),
+            InputParamKind::ConstantOnly,
+            true,
+        );
+        self
+    }
+
     pub fn input_noise(&mut self, name: &str) -> &mut Self {
         self.graph.add_input_param(
             self.node_id,
@@ -67,6 +87,31 @@ impl<'a> NodeBuilder<'a> {
         self
     }
 
+    pub fn input_vector(&mut self, name: &str) -> &mut Self {
+        self.graph.add_input_param(
+            self.node_id,
+            name.into(),
+            ConnectionType::Vec2,
+            NodeAttribute::NoInput,
+            InputParamKind::ConnectionOnly,
+            true,
+        );
+        self
+    }
+
+    /// A noise input which takes a constant while nothing is connected.
+    pub fn input_noise_or_f64(&mut self, name: &str, initial: f64) -> &mut Self {
+        self.graph.add_input_param(
+            self.node_id,
+            name.into(),
+            ConnectionType::Noise,
+            NodeAttribute::F64(initial),
+            InputParamKind::ConnectionOrConstant,
+            true,
+        );
+        self
+    }
+
     pub fn input_noise_type(&mut self, initial: NoiseType) -> &mut Self {
         self.graph.add_input_param(
             self.node_id,
@@ -91,6 +136,18 @@ impl<'a> NodeBuilder<'a> {
         self
     }
 
+    pub fn input_formula(&mut self, initial: &str) -> &mut Self {
+        self.graph.add_input_param(
+            self.node_id,
+            "formula".into(),
+            ConnectionType::NoConnection,
+            NodeAttribute::Formula(initial.into()),
+            InputParamKind::ConstantOnly,
+            true,
+        );
+        self
+    }
+
     pub fn input_vec(&mut self, name: &str, template: NodeAttribute) -> &mut Self {
         self.graph.add_input_param(
             self.node_id,
@@ -118,12 +175,119 @@ impl<'a> NodeBuilder<'a> {
         self
     }
 
+    pub fn input_distance_function(&mut self) -> &mut Self {
+        self.graph.add_input_param(
+            self.node_id,
+            "distance function".into(),
+            ConnectionType::NoConnection,
+            NodeAttribute::DistanceFunction(WorleyDistanceFunction::Euclidean),
+            InputParamKind::ConstantOnly,
+            true,
+        );
+        self
+    }
+
+    /// The inputs every generator has, see `NodeEvaluator::output_generator`.
+    pub fn input_generator(&mut self) -> &mut Self {
+        self.input_usize("seed", 0)
+            .tooltip("Different seeds give different noise")
+            .input_frequency(1.0)
+            .input_f64("amplitude", 1.0)
+            .tooltip("Multiplies the values")
+            .input_f64("offset", 0.0)
+            .tooltip("Added to the values")
+    }
+
+    /// The frequency of a generator or fractal.
+    pub fn input_frequency(&mut self, initial: f64) -> &mut Self {
+        self.input_f64("frequency", initial)
+            .range(0.0, f64::INFINITY)
+            .soft_range(0.0001, 10.0)
+            .logarithmic()
+            .tooltip("How many features fit into one unit, the inverse of their size")
+    }
+
+    pub fn input_octaves(&mut self, initial: usize) -> &mut Self {
+        self.input_usize("octaves", initial)
+            .range(1.0, MAX_OCTAVES as f64)
+            .tooltip("How many layers of noise are added up, each adding finer detail")
+    }
+
+    pub fn input_lacunarity(&mut self, initial: f64) -> &mut Self {
+        self.input_f64("lacunarity", initial)
+            .range(0.0, f64::INFINITY)
+            .soft_range(1.0, 4.0)
+            .tooltip("How much the frequency grows from one octave to the next")
+    }
+
+    pub fn input_persistence(&mut self, initial: f64) -> &mut Self {
+        self.input_f64("persistence", initial)
+            .range(0.0, f64::INFINITY)
+            .soft_range(0.0, 1.0)
+            .tooltip("How much the amplitude shrinks from one octave to the next")
+    }
+
+    /// Limits the values of the last input to `[min, max]`, see `Param`.
+    pub fn range(&mut self, min: f64, max: f64) -> &mut Self {
+        self.param().range = min..=max;
+        self
+    }
+
+    /// Makes the widget of the last input a slider over `[min, max]`.
+    pub fn soft_range(&mut self, min: f64, max: f64) -> &mut Self {
+        self.param().soft_range = Some(min..=max);
+        self
+    }
+
+    pub fn speed(&mut self, speed: f64) -> &mut Self {
+        self.param().speed = Some(speed);
+        self
+    }
+
+    /// Moves the slider of the last input on a logarithmic scale.
+    pub fn logarithmic(&mut self) -> &mut Self {
+        self.param().logarithmic = true;
+        self
+    }
+
+    pub fn tooltip(&mut self, tooltip: &'static str) -> &mut Self {
+        self.param().tooltip = Some(tooltip);
+        self
+    }
+
+    /// The metadata of the input added last.
+    fn param(&mut self) -> &mut Param {
+        let node = &mut self.graph[self.node_id];
+        let (name, _) = node.inputs.last().expect("Expected an input");
+        node.user_data.params.entry(name.clone()).or_default()
+    }
+
     pub fn output_noise(&mut self) -> &mut Self {
         self.graph
             .add_output_param(self.node_id, "out".into(), ConnectionType::Noise);
         self
     }
 
+    /// A noise output besides "out", for nodes with more than one.
+    pub fn output_noise_named(&mut self, name: &str) -> &mut Self {
+        self.graph
+            .add_output_param(self.node_id, name.into(), ConnectionType::Noise);
+        self
+    }
+
+    /// A number output besides "out", or next to a noise output.
+    pub fn output_number_named(&mut self, name: &str) -> &mut Self {
+        self.graph
+            .add_output_param(self.node_id, name.into(), ConnectionType::F64);
+        self
+    }
+
+    pub fn output_vector(&mut self) -> &mut Self {
+        self.graph
+            .add_output_param(self.node_id, "out".into(), ConnectionType::Vec2);
+        self
+    }
+
     pub fn output_number(&mut self) -> &mut Self {
         self.graph
             .add_output_param(self.node_id, "out".into(), ConnectionType::F64);
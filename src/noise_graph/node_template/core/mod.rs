@@ -1,3 +1,5 @@
 mod builder;
+mod param;
 
-pub use builder::NodeBuilder;
+pub use builder::{NodeBuilder, DEFAULT_NODE_NAME};
+pub use param::{Param, MAX_DECIMALS};
@@ -0,0 +1,88 @@
+use std::ops::RangeInclusive;
+
+use bevy_egui::egui::{self, emath::Numeric, DragValue, Slider};
+
+/// The most decimals the widgets of numbers show.
+pub const MAX_DECIMALS: usize = 5;
+
+/// How the value of a number input can be edited, set up by the methods of
+/// `NodeBuilder` following the input.
+#[derive(Clone, Debug)]
+pub struct Param {
+    /// The values the input takes. The widget keeps the value inside, and
+    /// evaluating the node fails for values outside of it.
+    pub range: RangeInclusive<f64>,
+    /// The values which are sensible in most cases. The widget becomes a
+    /// slider over them, other values of the range can still be typed in.
+    pub soft_range: Option<RangeInclusive<f64>>,
+    /// How much dragging the widget by a point changes the value.
+    pub speed: Option<f64>,
+    /// Whether the slider moves over the soft range on a logarithmic scale,
+    /// for values which span several orders of magnitude like frequencies.
+    pub logarithmic: bool,
+    pub tooltip: Option<&'static str>,
+}
+
+impl Default for Param {
+    fn default() -> Self {
+        Self {
+            range: f64::NEG_INFINITY..=f64::INFINITY,
+            soft_range: None,
+            speed: None,
+            logarithmic: false,
+            tooltip: None,
+        }
+    }
+}
+
+impl Param {
+    /// The values of the range in words, like "at least 0".
+    pub fn describe_range(&self) -> String {
+        let (min, max) = (*self.range.start(), *self.range.end());
+        if max == f64::INFINITY {
+            format!("at least {min}")
+        } else if min == f64::NEG_INFINITY {
+            format!("at most {max}")
+        } else {
+            format!("between {min} and {max}")
+        }
+    }
+
+    /// Edits a value, a slider if there is a soft range and a drag value
+    /// otherwise.
+    pub fn show<N: Numeric>(&self, ui: &mut egui::Ui, value: &mut N) -> egui::Response {
+        let response = match &self.soft_range {
+            Some(soft_range) => {
+                let range = N::from_f64(*soft_range.start())..=N::from_f64(*soft_range.end());
+                let mut slider = Slider::new(value, range)
+                    .logarithmic(self.logarithmic)
+                    .clamp_to_range(false);
+                if !N::INTEGRAL {
+                    slider = slider.max_decimals(MAX_DECIMALS);
+                }
+                ui.add(slider)
+            }
+            None => {
+                let mut drag_value = DragValue::new(value).clamp_range(self.range.clone());
+                if let Some(speed) = self.speed {
+                    drag_value = drag_value.speed(speed);
+                }
+                if !N::INTEGRAL {
+                    drag_value = drag_value.max_decimals(MAX_DECIMALS);
+                }
+                ui.add(drag_value)
+            }
+        };
+
+        // The slider allows typing in values outside of its range.
+        let clamped = value.to_f64().clamp(*self.range.start(), *self.range.end());
+        if clamped != value.to_f64() {
+            *value = N::from_f64(clamped);
+        }
+
+        match self.tooltip {
+            Some(tooltip) => response.on_hover_text(tooltip),
+            None => response,
+        }
+    }
+}
@@ -5,7 +5,11 @@ use noise::Checkerboard;
 
 impl NodeImpl for Checkerboard {
     fn build(builder: &mut NodeBuilder) {
-        builder.input_usize("size", 1).output_noise();
+        builder
+            .input_usize("size", 1)
+            .range(0.0, 30.0)
+            .tooltip("The cells are 2 to the power of the size wide")
+            .output_noise();
     }
 
     fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
@@ -0,0 +1,23 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shaped, Shaping};
+
+pub struct Cos;
+
+impl NodeImpl for Cos {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_noise_or_f64("frequency", 1.0)
+            .input_noise_or_f64("phase", 0.0)
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let frequency = evaluator.get_noise_function("frequency")?;
+        let phase = evaluator.get_noise_function("phase")?;
+        let noise = Shaped::new(Shaping::Cos, vec![source, frequency, phase]);
+        evaluator.output_noise(noise)
+    }
+}
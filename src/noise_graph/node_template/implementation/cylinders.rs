@@ -6,7 +6,7 @@ use noise::Cylinders;
 impl NodeImpl for Cylinders {
     fn build(builder: &mut NodeBuilder) {
         builder
-            .input_f64("frequency", Self::DEFAULT_FREQUENCY)
+            .input_frequency(Self::DEFAULT_FREQUENCY)
             .output_noise();
     }
 
@@ -0,0 +1,22 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shape};
+
+pub struct DistanceToLine;
+
+impl NodeImpl for DistanceToLine {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_f64("start x", 0.0)
+            .input_f64("start y", 0.0)
+            .input_f64("end x", 1000.0)
+            .input_f64("end y", 0.0)
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let start = [evaluator.get_f64("start x")?, evaluator.get_f64("start y")?];
+        let end = [evaluator.get_f64("end x")?, evaluator.get_f64("end y")?];
+        evaluator.output_noise(Shape::DistanceToLine { start, end })
+    }
+}
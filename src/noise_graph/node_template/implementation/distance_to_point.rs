@@ -0,0 +1,20 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shape};
+
+pub struct DistanceToPoint;
+
+impl NodeImpl for DistanceToPoint {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_f64("x", 0.0)
+            .input_f64("y", 0.0)
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let x = evaluator.get_f64("x")?;
+        let y = evaluator.get_f64("y")?;
+        evaluator.output_noise(Shape::DistanceToPoint { point: [x, y] })
+    }
+}
@@ -0,0 +1,26 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+use crate::noise_graph::runtime::{DomainWarp, DynNoiseFn, DynVectorFn, MAX_WARP_ITERATIONS};
+
+impl NodeImpl for DomainWarp<DynNoiseFn, DynVectorFn> {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_vector("warp")
+            .input_f64("strength", 100.0)
+            .tooltip("How far the warp moves the points")
+            .input_usize("iterations", 1)
+            .range(0.0, MAX_WARP_ITERATIONS as f64)
+            .tooltip("How often the warp is applied to the points it moved")
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let warp = evaluator.get_vector_function("warp")?;
+        let strength = evaluator.get_f64("strength")?;
+        let iterations = evaluator.get_usize("iterations")?;
+        evaluator.output_noise(DomainWarp::new(source, warp, strength, iterations))
+    }
+}
@@ -0,0 +1,35 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+use crate::noise_graph::runtime::ErodedFbm;
+
+impl NodeImpl for ErodedFbm {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_generator()
+            .input_octaves(Self::DEFAULT_OCTAVES)
+            .input_f64("gain", Self::DEFAULT_GAIN)
+            .range(0.0, f64::INFINITY)
+            .soft_range(0.0, 1.0)
+            .tooltip("How much the amplitude shrinks from one octave to the next")
+            .input_lacunarity(Self::DEFAULT_LACUNARITY)
+            .input_f64("gradient strength", Self::DEFAULT_GRADIENT_STRENGTH)
+            .range(0.0, f64::INFINITY)
+            .tooltip("How strongly steep slopes damp the octaves on top of them")
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let seed = evaluator.get_seed()?;
+        let octaves = evaluator.get_usize("octaves")?;
+        let gain = evaluator.get_f64("gain")?;
+        let lacunarity = evaluator.get_f64("lacunarity")?;
+        let gradient_strength = evaluator.get_f64("gradient strength")?;
+        let noise = ErodedFbm::new(seed)
+            .set_octaves(octaves)
+            .set_gain(gain)
+            .set_lacunarity(lacunarity)
+            .set_gradient_strength(gradient_strength);
+        evaluator.output_generator(noise)
+    }
+}
@@ -0,0 +1,103 @@
+use egui_node_graph::{InputId, NodeId};
+use noise::NoiseFn;
+
+use crate::noise_graph::dsl::{Formula, MAX_VARIABLES};
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+use crate::noise_graph::{DynNoiseFn, NoiseGraph};
+
+/// Combines noise functions with a [`Formula`] over their values and the
+/// coordinates of the sample point. The node has a noise input for every
+/// variable of its formula.
+#[derive(Clone)]
+pub struct Expression {
+    formula: Formula,
+    sources: Vec<DynNoiseFn>,
+}
+
+impl NodeImpl for Expression {
+    fn build(builder: &mut NodeBuilder) {
+        builder.input_formula("a").input_noise("a").output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let formula = evaluator.get_formula()?;
+        let sources = formula
+            .variables()
+            .iter()
+            .map(|variable| evaluator.get_noise_function(variable))
+            .collect::<anyhow::Result<_>>()?;
+        evaluator.output_noise(Expression::new(formula, sources))
+    }
+}
+
+impl Expression {
+    /// Creates an expression with one source per variable of the formula, in
+    /// the order of `Formula::variables`.
+    pub fn new(formula: Formula, sources: Vec<DynNoiseFn>) -> Self {
+        assert_eq!(
+            formula.variables().len(),
+            sources.len(),
+            "Expected one source per variable"
+        );
+        Self { formula, sources }
+    }
+
+    /// Gives a node a noise input for every variable of its formula, in the
+    /// order of their first use, and removes the inputs of variables which
+    /// are no longer used. Nothing changes while the formula doesn't parse.
+    pub fn sync_inputs(graph: &mut NoiseGraph, node_id: NodeId) {
+        let formula = match graph[node_id]
+            .get_input("formula")
+            .map(|input_id| graph[input_id].value().clone())
+        {
+            Ok(formula) => match formula.try_to_formula() {
+                Ok(formula) => formula,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+        let variables = formula.variables();
+        let is_variable = |name: &str| variables.iter().any(|variable| variable == name);
+
+        let unused: Vec<InputId> = graph[node_id]
+            .inputs
+            .iter()
+            .filter(|(name, _)| name != "name" && name != "formula" && !is_variable(name))
+            .map(|(_, input_id)| *input_id)
+            .collect();
+        for input_id in unused {
+            graph.remove_input_param(input_id);
+        }
+
+        for variable in variables {
+            if graph[node_id].get_input(variable).is_err() {
+                NodeBuilder::new(graph, node_id).input_noise(variable);
+            }
+        }
+
+        let order = |name: &str| match name {
+            "name" => 0,
+            "formula" => 1,
+            name => {
+                2 + variables
+                    .iter()
+                    .position(|variable| variable == name)
+                    .unwrap_or(0)
+            }
+        };
+        graph[node_id].inputs.sort_by_key(|(name, _)| order(name));
+    }
+}
+
+impl NoiseFn<f64, 2> for Expression {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        let mut values = [0.0; MAX_VARIABLES];
+        for (value, source) in values.iter_mut().zip(&self.sources) {
+            *value = source.get(point);
+        }
+
+        self.formula.evaluate(&values[..self.sources.len()], point)
+    }
+}
@@ -0,0 +1,31 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+use crate::noise_graph::runtime::{DynNoiseFn, Gradient};
+
+impl NodeImpl for Gradient<DynNoiseFn> {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_f64("step", 1.0)
+            .speed(0.1)
+            .tooltip("How far apart the samples of the source are")
+            .output_vector();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let step = evaluator.get_f64("step")?;
+        check_step(step)?;
+        evaluator.output_vector(Gradient::new(source, step))
+    }
+}
+
+/// Fails for steps which don't estimate a gradient.
+pub fn check_step(step: f64) -> anyhow::Result<()> {
+    if step > 0.0 {
+        Ok(())
+    } else {
+        anyhow::bail!("The step of a gradient must be positive")
+    }
+}
@@ -0,0 +1,24 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shaped, Shaping};
+
+pub struct Lerp;
+
+impl NodeImpl for Lerp {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source 1")
+            .input_noise("source 2")
+            .input_noise_or_f64("factor", 0.5)
+            .soft_range(0.0, 1.0)
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source_1 = evaluator.get_noise_function("source 1")?;
+        let source_2 = evaluator.get_noise_function("source 2")?;
+        let factor = evaluator.get_noise_function("factor")?;
+        let noise = Shaped::new(Shaping::Lerp, vec![source_1, source_2, factor]);
+        evaluator.output_noise(noise)
+    }
+}
@@ -0,0 +1,32 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shape};
+
+pub struct LinearGradient;
+
+impl NodeImpl for LinearGradient {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_f64("start x", 0.0)
+            .input_f64("start y", 0.0)
+            .input_f64("end x", 1000.0)
+            .input_f64("end y", 0.0)
+            .input_f64("falloff", 1.0)
+            .range(0.0, f64::INFINITY)
+            .soft_range(0.1, 10.0)
+            .logarithmic()
+            .tooltip("The power the gradient is raised to")
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let start = [evaluator.get_f64("start x")?, evaluator.get_f64("start y")?];
+        let end = [evaluator.get_f64("end x")?, evaluator.get_f64("end y")?];
+        let falloff = evaluator.get_f64("falloff")?;
+        evaluator.output_noise(Shape::LinearGradient {
+            start,
+            end,
+            falloff,
+        })
+    }
+}
@@ -8,35 +8,71 @@ mod cache;
 mod checkerboard;
 mod clamp;
 mod constant;
+mod cos;
+mod curl;
 mod curve;
 mod cylinders;
 mod displace;
+mod distance_to_line;
+mod distance_to_point;
+mod domain_warp;
+mod eroded_fbm;
 mod exponent;
+mod expression;
 mod fbm;
 mod float;
+mod gradient;
 mod hybrid_multi;
+mod lerp;
+mod linear_gradient;
 mod max;
 mod min;
+mod modulo;
 mod multiply;
 mod negate;
+mod normalize;
 mod open_simplex;
+mod output;
+mod overlay;
 mod perlin;
 mod perlin_surflet;
 mod power;
+mod quantize;
+mod radial_gradient;
+mod remap;
 mod ridged_multi;
 mod rotate_point;
 mod scale;
 mod scale_bias;
 mod scale_point;
+mod screen;
 mod select;
 mod simplex;
+mod sin;
+mod smoothstep;
+mod soft_light;
+mod step;
 mod super_simplex;
 mod terrace;
 mod translate_point;
 mod turbulence;
 mod value;
+mod vector;
 mod worley;
+mod x;
+mod y;
 
 pub use self::{
-    arithmetic::Arithmetic, cache::SyncCache, float::Float, scale::Scale, worley::SyncWorley,
+    arithmetic::Arithmetic, cos::Cos, distance_to_line::DistanceToLine,
+    distance_to_point::DistanceToPoint, expression::Expression, float::Float, gradient::check_step,
+    lerp::Lerp, linear_gradient::LinearGradient, modulo::Modulo, normalize::Normalize,
+    output::Output, overlay::Overlay, quantize::Quantize, radial_gradient::RadialGradient,
+    remap::Remap, screen::Screen, sin::Sin, smoothstep::Smoothstep, soft_light::SoftLight,
+    step::Step, x::X, y::Y,
+};
+
+pub use crate::noise_graph::runtime::{
+    clamp_octaves, Curl, DistanceFunction, DomainWarp, ErodedFbm, Fractal, FractalKind, Gradient,
+    Scale, Shape, Shaped, Shaping, SyncCache, SyncWorley, Vector, MAX_OCTAVES, MAX_SOURCES,
+    MAX_WARP_ITERATIONS,
 };
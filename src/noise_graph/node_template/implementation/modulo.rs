@@ -0,0 +1,22 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shaped, Shaping};
+
+pub struct Modulo;
+
+impl NodeImpl for Modulo {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_noise_or_f64("divisor", 1.0)
+            .tooltip("0 keeps the values as they are")
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let divisor = evaluator.get_noise_function("divisor")?;
+        let noise = Shaped::new(Shaping::Modulo, vec![source, divisor]);
+        evaluator.output_noise(noise)
+    }
+}
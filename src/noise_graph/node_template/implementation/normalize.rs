@@ -0,0 +1,104 @@
+use noise::{Clamp, NoiseFn, ScaleBias};
+
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+
+/// Maps the range of its source onto a target range. The range of the source
+/// is estimated by sampling it on a grid around the origin whenever the graph
+/// is evaluated, so generators can be swapped without retuning a
+/// `ScaleBias`.
+pub struct Normalize;
+
+/// The most samples along each side of the grid.
+pub const MAX_NORMALIZE_SAMPLES: usize = 512;
+
+impl NodeImpl for Normalize {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_f64("to min", -1.0)
+            .input_f64("to max", 1.0)
+            .input_bool("clamp", false)
+            .tooltip("Keeps values outside of the sampled area within the target range")
+            .input_f64("extent", 2048.0)
+            .range(f64::EPSILON, f64::INFINITY)
+            .soft_range(1.0, 100_000.0)
+            .logarithmic()
+            .tooltip("How far the sampled area reaches from the origin in every direction")
+            .input_usize("samples", 64)
+            .range(2.0, MAX_NORMALIZE_SAMPLES as f64)
+            .tooltip("The number of samples along each side of the sampled area")
+            .output_noise()
+            .output_number_named("min")
+            .output_number_named("max");
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let to_min = evaluator.get_f64("to min")?;
+        let to_max = evaluator.get_f64("to max")?;
+        let clamp = evaluator.get_bool("clamp")?;
+        let extent = evaluator.get_f64("extent")?;
+        let samples = evaluator.get_usize("samples")?;
+
+        let (min, max) = Normalize::estimate_range(&source, extent, samples)?;
+        let (scale, bias) = Normalize::scale_bias((min, max), to_min, to_max);
+        evaluator.output_number_to("min", min)?;
+        evaluator.output_number_to("max", max)?;
+
+        let noise = ScaleBias::new(source).set_scale(scale).set_bias(bias);
+        if clamp {
+            let (lower, upper) = Normalize::bounds(to_min, to_max);
+            evaluator.output_noise(Clamp::new(noise).set_bounds(lower, upper))
+        } else {
+            evaluator.output_noise(noise)
+        }
+    }
+}
+
+impl Normalize {
+    /// Estimates the range of a noise function from its finite values on a
+    /// grid of `samples` by `samples` points, reaching `extent` from the
+    /// origin in every direction.
+    pub fn estimate_range(
+        noise: &impl NoiseFn<f64, 2>,
+        extent: f64,
+        samples: usize,
+    ) -> anyhow::Result<(f64, f64)> {
+        let samples = samples.max(2);
+        let step = 2.0 * extent / (samples - 1) as f64;
+
+        let mut range: Option<(f64, f64)> = None;
+        for y in 0..samples {
+            for x in 0..samples {
+                let value = noise.get([-extent + x as f64 * step, -extent + y as f64 * step]);
+                if value.is_finite() {
+                    range = Some(match range {
+                        Some((min, max)) => (min.min(value), max.max(value)),
+                        None => (value, value),
+                    });
+                }
+            }
+        }
+
+        range.ok_or_else(|| anyhow::anyhow!("The source has no finite values to normalize"))
+    }
+
+    /// The scale and bias which map the estimated range onto the target
+    /// range. A source without any variation ends up in the middle of the
+    /// target range.
+    pub fn scale_bias((min, max): (f64, f64), to_min: f64, to_max: f64) -> (f64, f64) {
+        if max > min {
+            let scale = (to_max - to_min) / (max - min);
+            (scale, to_min - min * scale)
+        } else {
+            (0.0, (to_min + to_max) / 2.0)
+        }
+    }
+
+    /// The target range as clamp bounds, which have to be in order.
+    pub fn bounds(to_min: f64, to_max: f64) -> (f64, f64) {
+        (to_min.min(to_max), to_min.max(to_max))
+    }
+}
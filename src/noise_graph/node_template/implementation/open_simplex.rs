@@ -5,10 +5,11 @@ use noise::OpenSimplex;
 
 impl NodeImpl for OpenSimplex {
     fn build(builder: &mut NodeBuilder) {
-        builder.output_noise();
+        builder.input_generator().output_noise();
     }
 
     fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
-        evaluator.output_noise(OpenSimplex::default())
+        let seed = evaluator.get_seed()?;
+        evaluator.output_generator(OpenSimplex::new(seed))
     }
 }
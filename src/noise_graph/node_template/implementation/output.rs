@@ -0,0 +1,21 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+
+/// Passes noise on to the terrain as the channel named like the node. The
+/// "height" channel shapes the terrain in place of the active node, the other
+/// channels end up in the chunk meshes for the material to read, see
+/// `NoiseGraphResource::terrain_channels`.
+pub struct Output;
+
+impl NodeImpl for Output {
+    fn build(builder: &mut NodeBuilder) {
+        builder.input_noise("source");
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        // There is no output to populate, nothing can be connected to it.
+        let source = evaluator.get_noise_function("source")?;
+        Ok(NodeAttribute::NoiseFunction(source))
+    }
+}
@@ -0,0 +1,21 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shaped, Shaping};
+
+pub struct Overlay;
+
+impl NodeImpl for Overlay {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("base")
+            .input_noise_or_f64("blend", 0.0)
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let base = evaluator.get_noise_function("base")?;
+        let blend = evaluator.get_noise_function("blend")?;
+        let noise = Shaped::new(Shaping::Overlay, vec![base, blend]);
+        evaluator.output_noise(noise)
+    }
+}
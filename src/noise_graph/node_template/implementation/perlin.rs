@@ -6,10 +6,11 @@ use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
 
 impl NodeImpl for Perlin {
     fn build(builder: &mut NodeBuilder) {
-        builder.output_noise();
+        builder.input_generator().output_noise();
     }
 
     fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
-        evaluator.output_noise(noise::Perlin::default())
+        let seed = evaluator.get_seed()?;
+        evaluator.output_generator(Perlin::new(seed))
     }
 }
@@ -5,11 +5,11 @@ use noise::PerlinSurflet;
 
 impl NodeImpl for PerlinSurflet {
     fn build(builder: &mut NodeBuilder) {
-        builder.output_noise();
+        builder.input_generator().output_noise();
     }
 
     fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
-        let noise = PerlinSurflet::default();
-        evaluator.output_noise(noise)
+        let seed = evaluator.get_seed()?;
+        evaluator.output_generator(PerlinSurflet::new(seed))
     }
 }
@@ -0,0 +1,22 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shaped, Shaping};
+
+pub struct Quantize;
+
+impl NodeImpl for Quantize {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_noise_or_f64("step size", 0.25)
+            .tooltip("0 keeps the values as they are")
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let step_size = evaluator.get_noise_function("step size")?;
+        let noise = Shaped::new(Shaping::Quantize, vec![source, step_size]);
+        evaluator.output_noise(noise)
+    }
+}
@@ -0,0 +1,35 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shape};
+
+pub struct RadialGradient;
+
+impl NodeImpl for RadialGradient {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_f64("center x", 0.0)
+            .input_f64("center y", 0.0)
+            .input_f64("radius", 1000.0)
+            .range(0.0, f64::INFINITY)
+            .input_f64("falloff", 1.0)
+            .range(0.0, f64::INFINITY)
+            .soft_range(0.1, 10.0)
+            .logarithmic()
+            .tooltip("The power the gradient is raised to")
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let center = [
+            evaluator.get_f64("center x")?,
+            evaluator.get_f64("center y")?,
+        ];
+        let radius = evaluator.get_f64("radius")?;
+        let falloff = evaluator.get_f64("falloff")?;
+        evaluator.output_noise(Shape::RadialGradient {
+            center,
+            radius,
+            falloff,
+        })
+    }
+}
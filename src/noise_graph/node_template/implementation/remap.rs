@@ -0,0 +1,30 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shaped, Shaping};
+
+pub struct Remap;
+
+impl NodeImpl for Remap {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_noise_or_f64("from min", -1.0)
+            .input_noise_or_f64("from max", 1.0)
+            .input_noise_or_f64("to min", 0.0)
+            .input_noise_or_f64("to max", 1.0)
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let from_min = evaluator.get_noise_function("from min")?;
+        let from_max = evaluator.get_noise_function("from max")?;
+        let to_min = evaluator.get_noise_function("to min")?;
+        let to_max = evaluator.get_noise_function("to max")?;
+        let noise = Shaped::new(
+            Shaping::Remap,
+            vec![source, from_min, from_max, to_min, to_max],
+        );
+        evaluator.output_noise(noise)
+    }
+}
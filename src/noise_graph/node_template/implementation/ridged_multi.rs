@@ -1,59 +1,38 @@
 use crate::noise_graph::graph_ext::NodeEvaluator;
-use noise::{MultiFractal, Perlin, RidgedMulti, Simplex};
+use noise::{Perlin, RidgedMulti};
 
 use crate::noise_graph::node_attribute::{NodeAttribute, NoiseType};
-use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+use crate::noise_graph::node_template::{Fractal, FractalKind, NodeBuilder, NodeImpl};
 
 impl NodeImpl for RidgedMulti<Perlin> {
     fn build(builder: &mut NodeBuilder) {
         builder
             .input_noise_type(NoiseType::Perlin)
-            .input_usize(
-                "octaves",
-                noise::RidgedMulti::<Perlin>::DEFAULT_OCTAVE_COUNT,
-            )
-            .input_f64("frequency", noise::RidgedMulti::<Perlin>::DEFAULT_FREQUENCY)
-            .input_f64(
-                "lacunarity",
-                noise::RidgedMulti::<Perlin>::DEFAULT_LACUNARITY,
-            )
-            .input_f64(
-                "persistence",
-                noise::RidgedMulti::<Perlin>::DEFAULT_PERSISTENCE,
-            )
+            .input_octaves(noise::RidgedMulti::<Perlin>::DEFAULT_OCTAVE_COUNT)
+            .input_frequency(noise::RidgedMulti::<Perlin>::DEFAULT_FREQUENCY)
+            .input_lacunarity(noise::RidgedMulti::<Perlin>::DEFAULT_LACUNARITY)
+            .input_persistence(noise::RidgedMulti::<Perlin>::DEFAULT_PERSISTENCE)
             .input_f64(
                 "attenuation",
                 noise::RidgedMulti::<Perlin>::DEFAULT_ATTENUATION,
             )
+            .range(0.0, f64::INFINITY)
+            .tooltip("How strongly the ridges of an octave damp the octaves on top of it")
+            .input_noise("source")
             .output_noise();
     }
 
     fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
-        let octaves = evaluator.get_usize("octaves")?;
+        let sources = evaluator.get_octave_sources()?;
         let frequency = evaluator.get_f64("frequency")?;
         let lacunarity = evaluator.get_f64("lacunarity")?;
         let persistence = evaluator.get_f64("persistence")?;
         let attenuation = evaluator.get_f64("attenuation")?;
 
-        match evaluator.get_noise_type()? {
-            NoiseType::Perlin => {
-                let noise = noise::RidgedMulti::<Perlin>::default()
-                    .set_octaves(octaves)
-                    .set_frequency(frequency)
-                    .set_lacunarity(lacunarity)
-                    .set_persistence(persistence)
-                    .set_attenuation(attenuation);
-                evaluator.output_noise(noise)
-            }
-            NoiseType::Simplex => {
-                let noise = noise::RidgedMulti::<Simplex>::default()
-                    .set_octaves(octaves)
-                    .set_frequency(frequency)
-                    .set_lacunarity(lacunarity)
-                    .set_persistence(persistence)
-                    .set_attenuation(attenuation);
-                evaluator.output_noise(noise)
-            }
-        }
+        let noise = Fractal::new(FractalKind::RidgedMulti { attenuation }, sources)
+            .set_frequency(frequency)
+            .set_lacunarity(lacunarity)
+            .set_persistence(persistence);
+        evaluator.output_noise(noise)
     }
 }
@@ -10,9 +10,13 @@ impl NodeImpl for RotatePoint<DynNoiseFn> {
         builder
             .input_noise("source")
             .input_f64("x angle", 0.0)
+            .tooltip("In degrees")
             .input_f64("y angle", 0.0)
+            .tooltip("In degrees")
             .input_f64("z angle", 0.0)
+            .tooltip("In degrees")
             .input_f64("u angle", 0.0)
+            .tooltip("In degrees")
             .output_noise();
     }
 
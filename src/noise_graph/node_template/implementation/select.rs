@@ -13,6 +13,8 @@ impl NodeImpl for Select<f64, DynNoiseFn, DynNoiseFn, DynNoiseFn, 2> {
             .input_f64("bounds lower", 0.0)
             .input_f64("bounds upper", 1.0)
             .input_f64("falloff", 0.0)
+            .range(0.0, f64::INFINITY)
+            .tooltip("How smoothly the sources blend at the bounds")
             .output_noise();
     }
 
@@ -5,11 +5,11 @@ use noise::Simplex;
 
 impl NodeImpl for Simplex {
     fn build(builder: &mut NodeBuilder) {
-        builder.output_noise();
+        builder.input_generator().output_noise();
     }
 
     fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
-        let noise = Simplex::default();
-        evaluator.output_noise(noise)
+        let seed = evaluator.get_seed()?;
+        evaluator.output_generator(Simplex::new(seed))
     }
 }
@@ -0,0 +1,23 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shaped, Shaping};
+
+pub struct Smoothstep;
+
+impl NodeImpl for Smoothstep {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_noise_or_f64("lower edge", -1.0)
+            .input_noise_or_f64("upper edge", 1.0)
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let lower_edge = evaluator.get_noise_function("lower edge")?;
+        let upper_edge = evaluator.get_noise_function("upper edge")?;
+        let noise = Shaped::new(Shaping::Smoothstep, vec![source, lower_edge, upper_edge]);
+        evaluator.output_noise(noise)
+    }
+}
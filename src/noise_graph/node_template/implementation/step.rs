@@ -0,0 +1,21 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shaped, Shaping};
+
+pub struct Step;
+
+impl NodeImpl for Step {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise("source")
+            .input_noise_or_f64("threshold", 0.0)
+            .output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let source = evaluator.get_noise_function("source")?;
+        let threshold = evaluator.get_noise_function("threshold")?;
+        let noise = Shaped::new(Shaping::Step, vec![source, threshold]);
+        evaluator.output_noise(noise)
+    }
+}
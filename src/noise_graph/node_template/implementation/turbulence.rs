@@ -1,7 +1,9 @@
-use noise::{Perlin, Simplex, Turbulence};
+use noise::{
+    NoiseFn, OpenSimplex, Perlin, PerlinSurflet, Seedable, Simplex, SuperSimplex, Turbulence, Value,
+};
 
 use crate::noise_graph::graph_ext::NodeEvaluator;
-use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, SyncWorley, MAX_OCTAVES};
 use crate::noise_graph::{
     node_attribute::{NodeAttribute, NoiseType},
     DynNoiseFn,
@@ -12,15 +14,15 @@ impl NodeImpl for Turbulence<DynNoiseFn, Perlin> {
         builder
             .input_noise("source")
             .input_noise_type(NoiseType::Perlin)
-            .input_f64(
-                "frequency",
-                noise::Turbulence::<Perlin, Perlin>::DEFAULT_FREQUENCY,
-            )
+            .input_frequency(noise::Turbulence::<Perlin, Perlin>::DEFAULT_FREQUENCY)
             .input_f64("power", noise::Turbulence::<Perlin, Perlin>::DEFAULT_POWER)
+            .tooltip("How far the points are moved")
             .input_usize(
                 "roughness",
                 noise::Turbulence::<Perlin, Perlin>::DEFAULT_ROUGHNESS,
             )
+            .range(1.0, MAX_OCTAVES as f64)
+            .tooltip("How many octaves the distortion has")
             .output_noise();
     }
 
@@ -32,19 +34,42 @@ impl NodeImpl for Turbulence<DynNoiseFn, Perlin> {
 
         match evaluator.get_noise_type()? {
             NoiseType::Perlin => {
-                let noise = noise::Turbulence::<_, Perlin>::new(source)
-                    .set_frequency(frequency)
-                    .set_power(power)
-                    .set_roughness(roughness);
-                evaluator.output_noise(noise)
+                turbulence::<Perlin>(evaluator, source, frequency, power, roughness)
             }
             NoiseType::Simplex => {
-                let noise = noise::Turbulence::<_, Simplex>::new(source)
-                    .set_frequency(frequency)
-                    .set_power(power)
-                    .set_roughness(roughness);
-                evaluator.output_noise(noise)
+                turbulence::<Simplex>(evaluator, source, frequency, power, roughness)
+            }
+            NoiseType::OpenSimplex => {
+                turbulence::<OpenSimplex>(evaluator, source, frequency, power, roughness)
+            }
+            NoiseType::SuperSimplex => {
+                turbulence::<SuperSimplex>(evaluator, source, frequency, power, roughness)
+            }
+            NoiseType::Value => turbulence::<Value>(evaluator, source, frequency, power, roughness),
+            NoiseType::Worley => {
+                turbulence::<SyncWorley>(evaluator, source, frequency, power, roughness)
+            }
+            NoiseType::PerlinSurflet => {
+                turbulence::<PerlinSurflet>(evaluator, source, frequency, power, roughness)
             }
         }
     }
 }
+
+/// Outputs the source distorted by fbm functions over `Distortion`.
+fn turbulence<Distortion>(
+    evaluator: &mut NodeEvaluator,
+    source: DynNoiseFn,
+    frequency: f64,
+    power: f64,
+    roughness: usize,
+) -> anyhow::Result<NodeAttribute>
+where
+    Distortion: Default + Seedable + NoiseFn<f64, 2> + Send + Sync + 'static,
+{
+    let noise = noise::Turbulence::<_, Distortion>::new(source)
+        .set_frequency(frequency)
+        .set_power(power)
+        .set_roughness(roughness);
+    evaluator.output_noise(noise)
+}
@@ -5,11 +5,11 @@ use noise::Value;
 
 impl NodeImpl for Value {
     fn build(builder: &mut NodeBuilder) {
-        builder.output_noise();
+        builder.input_generator().output_noise();
     }
 
     fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
-        let noise = Value::default();
-        evaluator.output_noise(noise)
+        let seed = evaluator.get_seed()?;
+        evaluator.output_generator(Value::new(seed))
     }
 }
@@ -0,0 +1,19 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
+use crate::noise_graph::runtime::{DynNoiseFn, Vector};
+
+impl NodeImpl for Vector<DynNoiseFn, DynNoiseFn> {
+    fn build(builder: &mut NodeBuilder) {
+        builder
+            .input_noise_or_f64("x", 0.0)
+            .input_noise_or_f64("y", 0.0)
+            .output_vector();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        let x = evaluator.get_noise_function("x")?;
+        let y = evaluator.get_noise_function("y")?;
+        evaluator.output_vector(Vector::new(x, y))
+    }
+}
@@ -1,104 +1,32 @@
+use noise::core::worley::ReturnType;
+
 use crate::noise_graph::graph_ext::NodeEvaluator;
 use crate::noise_graph::node_attribute::NodeAttribute;
 use crate::noise_graph::node_template::{NodeBuilder, NodeImpl};
-use noise::{
-    core::worley::{distance_functions, worley_2d, ReturnType},
-    permutationtable::PermutationTable,
-    NoiseFn, Seedable,
-};
-
-#[derive(Clone)]
-pub struct SyncWorley {
-    distance_function: DistanceFunction,
-    return_type: ReturnType,
-    frequency: f64,
-    seed: u32,
-    perm_table: PermutationTable,
-}
-
-pub type DistanceFunction = fn(&[f64], &[f64]) -> f64;
+use crate::noise_graph::runtime::SyncWorley;
 
+/// Besides the output following the return type, the node has a "cell value"
+/// output with the value of the cell the point is in, so the distances and the
+/// cells can be used together.
 impl NodeImpl for SyncWorley {
     fn build(builder: &mut NodeBuilder) {
         builder
-            .input_f64("frequency", Self::DEFAULT_FREQUENCY)
+            .input_generator()
             .input_return_type()
-            .output_noise();
+            .input_distance_function()
+            .output_noise()
+            .output_noise_named("cell value");
     }
 
     fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
-        let frequency = evaluator.get_f64("frequency")?;
+        let seed = evaluator.get_seed()?;
         let return_type = evaluator.get_return_type()?;
-        let noise = SyncWorley::default()
-            .set_return_type(return_type.into())
-            .set_frequency(frequency);
-        evaluator.output_noise(noise)
-    }
-}
-
-impl SyncWorley {
-    pub const _DEFAULT_SEED: u32 = 0;
-    pub const DEFAULT_FREQUENCY: f64 = 1.0;
-
-    pub fn new(seed: u32) -> Self {
-        Self {
-            perm_table: PermutationTable::new(seed),
-            seed,
-            distance_function: distance_functions::euclidean,
-            return_type: ReturnType::Value,
-            frequency: Self::DEFAULT_FREQUENCY,
-        }
-    }
-
-    /// Enables or disables applying the distance from the nearest seed point
-    /// to the output value.
-    pub fn set_return_type(self, return_type: ReturnType) -> Self {
-        Self {
-            return_type,
-            ..self
-        }
-    }
-
-    /// Sets the frequency of the seed points.
-    pub fn set_frequency(self, frequency: f64) -> Self {
-        Self { frequency, ..self }
-    }
-}
-
-impl Default for SyncWorley {
-    fn default() -> Self {
-        Self::new(0)
-    }
-}
-
-impl Seedable for SyncWorley {
-    /// Sets the seed value used by the Worley cells.
-    fn set_seed(self, seed: u32) -> Self {
-        // If the new seed is the same as the current seed, just return self.
-        if self.seed == seed {
-            return self;
-        }
-
-        // Otherwise, regenerate the permutation table based on the new seed.
-        Self {
-            perm_table: PermutationTable::new(seed),
-            seed,
-            ..self
-        }
-    }
-
-    fn seed(&self) -> u32 {
-        self.seed
-    }
-}
-
-impl NoiseFn<f64, 2> for SyncWorley {
-    fn get(&self, [x, y]: [f64; 2]) -> f64 {
-        worley_2d(
-            &self.perm_table,
-            self.distance_function,
-            self.return_type,
-            [x * self.frequency, y * self.frequency],
-        )
+        let distance_function = evaluator.get_distance_function()?;
+        let noise = SyncWorley::new(seed).set_distance_function(distance_function.function());
+        evaluator.output_generator_to(
+            "cell value",
+            noise.clone().set_return_type(ReturnType::Value),
+        )?;
+        evaluator.output_generator(noise.set_return_type(return_type.into()))
     }
 }
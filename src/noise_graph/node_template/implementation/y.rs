@@ -0,0 +1,15 @@
+use crate::noise_graph::graph_ext::NodeEvaluator;
+use crate::noise_graph::node_attribute::NodeAttribute;
+use crate::noise_graph::node_template::{NodeBuilder, NodeImpl, Shape};
+
+pub struct Y;
+
+impl NodeImpl for Y {
+    fn build(builder: &mut NodeBuilder) {
+        builder.output_noise();
+    }
+
+    fn evaluate(evaluator: &mut NodeEvaluator) -> anyhow::Result<NodeAttribute> {
+        evaluator.output_noise(Shape::Y)
+    }
+}
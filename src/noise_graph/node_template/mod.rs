@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashMap};
 
 use egui_node_graph::{Graph, NodeId, NodeTemplateTrait};
 use noise::{
@@ -13,12 +13,12 @@ use strum::IntoEnumIterator;
 mod core;
 mod implementation;
 
-pub use self::core::NodeBuilder;
+pub use self::core::{NodeBuilder, Param, DEFAULT_NODE_NAME, MAX_DECIMALS};
 pub use implementation::*;
 
 use super::{
     connection_type::ConnectionType, graph_ext::NodeEvaluator, node_attribute::NodeAttribute,
-    NodeData, NoiseGraphState,
+    NodeData, NoiseGraph, NoiseGraphState,
 };
 
 pub trait NodeImpl {
@@ -30,7 +30,7 @@ pub trait NodeImpl {
 /// NodeTemplate is a mechanism to define node templates. It's what the graph
 /// will display in the "new node" popup. The user code needs to tell the
 /// library how to convert a NodeTemplate into a Node.
-#[derive(Clone, Copy, Debug, strum::EnumIter, strum::Display, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, strum::EnumIter, strum::Display, Serialize, Deserialize)]
 pub enum NodeTemplate {
     Abs,
     Add,
@@ -42,34 +42,70 @@ pub enum NodeTemplate {
     Checkerboard,
     Clamp,
     Constant,
+    Cos,
+    Curl,
     Curve,
     Cylinders,
     Displace,
+    DistanceToLine,
+    DistanceToPoint,
+    DomainWarp,
+    ErodedFbm,
     Exponent,
+    Expression,
     Fbm,
     Float,
+    Gradient,
     HybridMulti,
+    Lerp,
+    LinearGradient,
     Max,
     Min,
+    Modulo,
     Multiply,
     Negate,
+    Normalize,
     OpenSimplex,
+    Output,
+    Overlay,
+    #[default]
     Perlin,
     PerlinSurflet,
     Power,
+    Quantize,
+    RadialGradient,
+    Remap,
     RidgedMulti,
     RotatePoint,
     Scale,
     ScaleBias,
     ScalePoint,
+    Screen,
     Select,
     Simplex,
+    Sin,
+    Smoothstep,
+    SoftLight,
+    Step,
     SuperSimplex,
     Terrace,
     TranslatePoint,
     Turbulence,
     Value,
+    Vector,
     Worley,
+    X,
+    Y,
+}
+
+impl NodeTemplate {
+    /// Brings inputs which depend on the values of other inputs in line with
+    /// them, like the inputs for the variables of an expression.
+    pub fn sync_inputs(self, graph: &mut NoiseGraph, node_id: NodeId) {
+        if let NodeTemplate::Expression = self {
+            Expression::sync_inputs(graph, node_id);
+        }
+    }
 }
 
 pub struct AllNodeTemplates;
@@ -82,12 +118,6 @@ impl egui_node_graph::NodeTemplateIter for AllNodeTemplates {
     }
 }
 
-impl Default for NodeTemplate {
-    fn default() -> Self {
-        Self::Perlin
-    }
-}
-
 // A trait for the node kinds, which tells the library how to build new nodes
 // from the templates in the node finder
 impl NodeTemplateTrait for NodeTemplate {
@@ -96,7 +126,7 @@ impl NodeTemplateTrait for NodeTemplate {
     type ValueType = NodeAttribute;
     type UserState = NoiseGraphState;
 
-    fn node_finder_label(&self, _user_state: &mut NoiseGraphState) -> Cow<str> {
+    fn node_finder_label(&self, _user_state: &mut NoiseGraphState) -> Cow<'_, str> {
         Cow::Owned(format!("{:?}", self))
     }
 
@@ -107,7 +137,10 @@ impl NodeTemplateTrait for NodeTemplate {
     }
 
     fn user_data(&self, _user_state: &mut NoiseGraphState) -> Self::NodeData {
-        NodeData { template: *self }
+        NodeData {
+            template: *self,
+            params: HashMap::new(),
+        }
     }
 
     fn build_node(
@@ -135,34 +168,59 @@ impl NodeTemplateTrait for NodeTemplate {
             NodeTemplate::Checkerboard => Checkerboard::build(builder),
             NodeTemplate::Clamp => Clamp::build(builder),
             NodeTemplate::Constant => Constant::build(builder),
+            NodeTemplate::Cos => Cos::build(builder),
+            NodeTemplate::Curl => Curl::build(builder),
             NodeTemplate::Curve => Curve::build(builder),
             NodeTemplate::Cylinders => Cylinders::build(builder),
             NodeTemplate::Displace => Displace::build(builder),
+            NodeTemplate::DistanceToLine => DistanceToLine::build(builder),
+            NodeTemplate::DistanceToPoint => DistanceToPoint::build(builder),
+            NodeTemplate::DomainWarp => DomainWarp::build(builder),
+            NodeTemplate::ErodedFbm => ErodedFbm::build(builder),
             NodeTemplate::Exponent => Exponent::build(builder),
+            NodeTemplate::Expression => Expression::build(builder),
             NodeTemplate::Fbm => Fbm::build(builder),
             NodeTemplate::Float => Float::build(builder),
+            NodeTemplate::Gradient => Gradient::build(builder),
             NodeTemplate::HybridMulti => HybridMulti::build(builder),
+            NodeTemplate::Lerp => Lerp::build(builder),
+            NodeTemplate::LinearGradient => LinearGradient::build(builder),
             NodeTemplate::Max => Max::build(builder),
             NodeTemplate::Min => Min::build(builder),
+            NodeTemplate::Modulo => Modulo::build(builder),
             NodeTemplate::Multiply => Multiply::build(builder),
             NodeTemplate::Negate => Negate::build(builder),
+            NodeTemplate::Normalize => Normalize::build(builder),
             NodeTemplate::OpenSimplex => OpenSimplex::build(builder),
+            NodeTemplate::Output => Output::build(builder),
+            NodeTemplate::Overlay => Overlay::build(builder),
             NodeTemplate::Perlin => Perlin::build(builder),
             NodeTemplate::PerlinSurflet => PerlinSurflet::build(builder),
             NodeTemplate::Power => Power::build(builder),
+            NodeTemplate::Quantize => Quantize::build(builder),
+            NodeTemplate::RadialGradient => RadialGradient::build(builder),
+            NodeTemplate::Remap => Remap::build(builder),
             NodeTemplate::RidgedMulti => RidgedMulti::build(builder),
             NodeTemplate::RotatePoint => RotatePoint::build(builder),
             NodeTemplate::Scale => Scale::build(builder),
             NodeTemplate::ScaleBias => ScaleBias::build(builder),
             NodeTemplate::ScalePoint => ScalePoint::build(builder),
+            NodeTemplate::Screen => Screen::build(builder),
             NodeTemplate::Select => Select::build(builder),
             NodeTemplate::Simplex => Simplex::build(builder),
+            NodeTemplate::Sin => Sin::build(builder),
+            NodeTemplate::Smoothstep => Smoothstep::build(builder),
+            NodeTemplate::SoftLight => SoftLight::build(builder),
+            NodeTemplate::Step => Step::build(builder),
             NodeTemplate::SuperSimplex => SuperSimplex::build(builder),
             NodeTemplate::Terrace => Terrace::build(builder),
             NodeTemplate::TranslatePoint => TranslatePoint::build(builder),
             NodeTemplate::Turbulence => Turbulence::build(builder),
             NodeTemplate::Value => Value::build(builder),
+            NodeTemplate::Vector => Vector::build(builder),
             NodeTemplate::Worley => SyncWorley::build(builder),
+            NodeTemplate::X => X::build(builder),
+            NodeTemplate::Y => Y::build(builder),
         }
     }
 }
@@ -0,0 +1,179 @@
+use bevy_egui::egui::{self, Color32, Grid, Label, RichText, Sense, Widget};
+use egui_node_graph::NodeId;
+use serde::{Deserialize, Serialize};
+
+use super::{node_attribute::ValueLabel, NoiseGraphResource};
+
+/// A constant input of a node which is shown in the side panel under a name
+/// of its own, so the values that matter most for a graph, like the sea level
+/// or the scale of the world, can be tweaked without searching the graph.
+///
+/// To drive several inputs by one value, expose the `value` of a `float` node
+/// which is connected to all of them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExposedParameter {
+    pub name: String,
+    pub node: NodeId,
+    pub input: String,
+}
+
+/// The exposed parameters of a graph, in the order they were exposed.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ExposedParameters(Vec<ExposedParameter>);
+
+impl ExposedParameters {
+    pub fn get(&self, node: NodeId, input: &str) -> Option<&ExposedParameter> {
+        self.0
+            .iter()
+            .find(|parameter| parameter.node == node && parameter.input == input)
+    }
+
+    fn get_mut(&mut self, node: NodeId, input: &str) -> Option<&mut ExposedParameter> {
+        self.0
+            .iter_mut()
+            .find(|parameter| parameter.node == node && parameter.input == input)
+    }
+
+    /// Exposes an input under the given name, or renames it if it is exposed
+    /// already.
+    pub fn expose(&mut self, node: NodeId, input: &str, name: String) {
+        match self.get_mut(node, input) {
+            Some(parameter) => parameter.name = name,
+            None => self.0.push(ExposedParameter {
+                name,
+                node,
+                input: input.into(),
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, node: NodeId, input: &str) {
+        self.0
+            .retain(|parameter| parameter.node != node || parameter.input != input);
+    }
+
+    /// Forgets the parameters of a deleted node.
+    pub fn remove_node(&mut self, node: NodeId) {
+        self.0.retain(|parameter| parameter.node != node);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ExposedParameter> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The label of a node input, which can be right clicked to expose the input
+/// as a parameter or to rename and stop exposing it. Returns whether the
+/// exposed parameters changed.
+pub fn input_label(
+    ui: &mut egui::Ui,
+    exposed: &mut ExposedParameters,
+    node_id: NodeId,
+    input: &str,
+) -> bool {
+    let mut text = RichText::new(input);
+    if exposed.get(node_id, input).is_some() {
+        text = text.color(Color32::GOLD);
+    }
+    let mut response = ui.add(Label::new(text).sense(Sense::click()));
+    if let Some(parameter) = exposed.get(node_id, input) {
+        response = response.on_hover_text(format!("Exposed as '{}'", parameter.name));
+    }
+
+    let mut changed = false;
+    response.context_menu(|ui| match exposed.get_mut(node_id, input) {
+        Some(parameter) => {
+            ui.horizontal(|ui| {
+                ui.label("name");
+                changed |= ui.text_edit_singleline(&mut parameter.name).changed();
+            });
+            if ui.button("Stop exposing").clicked() {
+                exposed.remove(node_id, input);
+                changed = true;
+                ui.close_menu();
+            }
+        }
+        None => {
+            if ui.button("Expose as parameter").clicked() {
+                exposed.expose(node_id, input, input.into());
+                changed = true;
+                ui.close_menu();
+            }
+        }
+    });
+
+    changed
+}
+
+/// The exposed parameters of the graph as a sheet of controls.
+pub struct GraphParameters<'a>(pub &'a mut NoiseGraphResource);
+
+impl<'a> Widget for GraphParameters<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let resource = self.0;
+
+        ui.heading("Parameters");
+        if resource.user_state.exposed.is_empty() {
+            return ui.weak("Right click an input in the graph to expose it");
+        }
+
+        let parameters: Vec<ExposedParameter> =
+            resource.user_state.exposed.iter().cloned().collect();
+        let mut changed_nodes = Vec::new();
+        let response = Grid::new("GraphParameters.grid")
+            .show(ui, |ui| {
+                for parameter in parameters {
+                    let graph = &mut resource.state.graph;
+                    // Parameters of inputs which a template no longer has
+                    // are kept, in case the input comes back.
+                    let input_id = match graph.nodes.get(parameter.node) {
+                        Some(node) => match node.get_input(&parameter.input) {
+                            Ok(input_id) => input_id,
+                            Err(_) => continue,
+                        },
+                        None => continue,
+                    };
+
+                    ui.label(&parameter.name);
+                    if graph.connection(input_id).is_some() {
+                        ui.weak("connected");
+                    } else {
+                        let node_data = &graph.nodes[parameter.node].user_data;
+                        let responses = graph.inputs[input_id].value.edit(
+                            &parameter.input,
+                            ValueLabel::Hidden,
+                            parameter.node,
+                            ui,
+                            &mut resource.user_state,
+                            node_data,
+                        );
+                        if !responses.is_empty() {
+                            changed_nodes.push(parameter.node);
+                        }
+                    }
+                    ui.end_row();
+                }
+            })
+            .response;
+
+        for node_id in changed_nodes {
+            let graph = &mut resource.state.graph;
+            graph[node_id]
+                .user_data
+                .template
+                .sync_inputs(graph, node_id);
+            resource.user_state.invalidate(graph, node_id);
+            resource.file.mark_changed();
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,71 @@
+use serde_json::Value;
+
+use super::ExposedParameter;
+use crate::noise_graph::{dsl, format::FileFormat, graph_ext::GraphExt, NoiseGraphResource};
+
+const SCRIPT: &str = "sea = float(value: expose(\"Sea level\", 0.3));\n\
+                      hills = fbm(frequency: expose(\"Scale\", 0.01));";
+
+/// The exposed parameters as (name, node name, input) in their order.
+fn exposed(resource: &NoiseGraphResource) -> Vec<(String, String, String)> {
+    resource
+        .user_state
+        .exposed
+        .iter()
+        .map(|ExposedParameter { name, node, input }| {
+            (
+                name.clone(),
+                resource.state.graph.node_name(*node).to_string(),
+                input.clone(),
+            )
+        })
+        .collect()
+}
+
+fn expected() -> Vec<(String, String, String)> {
+    [
+        ("Sea level", "sea", "value"),
+        ("Scale", "hills", "frequency"),
+    ]
+    .map(|(name, node, input)| (name.into(), node.into(), input.into()))
+    .to_vec()
+}
+
+#[test]
+fn scripts_expose_inputs_in_their_order() {
+    let resource = dsl::parse(SCRIPT).unwrap();
+    assert_eq!(exposed(&resource), expected());
+
+    let printed = dsl::print(&resource).unwrap();
+    assert!(printed.contains("expose(\"Sea level\", 0.3)"), "{printed}");
+    assert_eq!(exposed(&dsl::parse(&printed).unwrap()), expected());
+}
+
+#[test]
+fn exposed_parameters_are_saved_in_both_formats() {
+    let resource = dsl::parse(SCRIPT).unwrap();
+
+    for format in [FileFormat::Editor, FileFormat::Readable] {
+        let mut json = Vec::new();
+        resource.write(&mut json, format).unwrap();
+        let json: Value = serde_json::from_slice(&json).unwrap();
+        let loaded = NoiseGraphResource::from_json(json).unwrap();
+
+        assert_eq!(exposed(&loaded), expected(), "in the {format} format");
+    }
+}
+
+#[test]
+fn exposing_an_exposed_input_renames_it() {
+    let mut resource = dsl::parse(SCRIPT).unwrap();
+    let exposed_parameters = &mut resource.user_state.exposed;
+    let sea = exposed_parameters.iter().next().unwrap().node;
+
+    exposed_parameters.expose(sea, "value", "Water".into());
+    assert_eq!(exposed_parameters.get(sea, "value").unwrap().name, "Water");
+    assert_eq!(exposed_parameters.iter().count(), 2);
+
+    exposed_parameters.remove_node(sea);
+    assert!(exposed_parameters.get(sea, "value").is_none());
+    assert_eq!(exposed_parameters.iter().count(), 1);
+}
@@ -0,0 +1,234 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Color32, DragValue, Grid, Widget};
+use egui_node_graph::NodeId;
+use noise::NoiseFn;
+
+use crate::camera_controller::CameraController;
+
+use super::{
+    graph_ext::{EvaluationCache, GraphExt},
+    node_attribute::NodeAttribute,
+    DynNoiseFn, NoiseGraph, NoiseGraphResource, VectorFn,
+};
+
+/// A point of the world at which every node shows the value of its outputs,
+/// in order to find the node responsible for a feature of the terrain.
+#[derive(Default)]
+pub struct Probe {
+    point: Option<[f64; 2]>,
+}
+
+impl Probe {
+    /// How far along the view of the camera the terrain is searched for.
+    const MAX_DISTANCE: f32 = 10_000.0;
+    /// The distance between the samples of the search, which may step over
+    /// thinner peaks.
+    const STEP: f32 = 4.0;
+    /// The halvings of the last step which narrow down the hit.
+    const REFINEMENTS: usize = 16;
+
+    pub fn point(&self) -> Option<[f64; 2]> {
+        self.point
+    }
+
+    pub fn set(&mut self, point: [f64; 2]) {
+        self.point = Some(point);
+    }
+
+    /// Shows the value of every output of a node at the probed point, if
+    /// there is one.
+    pub fn show(
+        &self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        graph: &NoiseGraph,
+        evaluation_cache: &mut EvaluationCache,
+    ) {
+        let Some(point) = self.point else {
+            return;
+        };
+        // Evaluation errors are shown by the thumbnails already.
+        if let Ok(values) = values(graph, node_id, evaluation_cache, point) {
+            for (output, value) in values {
+                ui.label(
+                    egui::RichText::new(format!("📍 {output} = {value}")).color(Color32::GOLD),
+                );
+            }
+        }
+    }
+
+    /// The point where a ray hits the terrain, which is the first sample
+    /// below the height, refined by bisecting the step leading to it.
+    fn hit(height: &DynNoiseFn, origin: Vec3, direction: Vec3) -> Option<[f64; 2]> {
+        let below = |distance: f32| {
+            let point = origin + direction * distance;
+            (point.y as f64) <= height.get([point.x as f64, point.z as f64])
+        };
+
+        let mut far = Self::STEP;
+        while !below(far) {
+            far += Self::STEP;
+            if far > Self::MAX_DISTANCE {
+                return None;
+            }
+        }
+
+        let mut near = far - Self::STEP;
+        for _ in 0..Self::REFINEMENTS {
+            let middle = (near + far) / 2.0;
+            if below(middle) {
+                far = middle;
+            } else {
+                near = middle;
+            }
+        }
+
+        let point = origin + direction * far;
+        Some([point.x as f64, point.z as f64])
+    }
+}
+
+/// Probes the terrain in the middle of the screen when it's clicked.
+pub fn probe_terrain(
+    mouse: Res<Input<MouseButton>>,
+    camera: Query<&Transform, With<CameraController>>,
+    mut graph: ResMut<NoiseGraphResource>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Ok(transform) = camera.get_single() else {
+        return;
+    };
+
+    let height = graph.terrain_height();
+    match Probe::hit(&height, transform.translation, transform.forward()) {
+        Some(point) => {
+            info!("Probing the terrain at ({:.1}, {:.1})", point[0], point[1]);
+            graph.user_state.probe.set(point);
+        }
+        None => info!("No terrain to probe in sight"),
+    }
+}
+
+/// The value of every output of a node at a point, formatted for display.
+fn values(
+    graph: &NoiseGraph,
+    node_id: NodeId,
+    evaluation_cache: &mut EvaluationCache,
+    point: [f64; 2],
+) -> anyhow::Result<Vec<(String, String)>> {
+    graph.evaluate_cached(node_id, evaluation_cache)?;
+
+    Ok(graph[node_id]
+        .outputs
+        .iter()
+        .filter_map(|(name, output_id)| {
+            let value = match evaluation_cache.get(*output_id)? {
+                NodeAttribute::NoiseFunction(noise) => format!("{:.4}", noise.get(point)),
+                NodeAttribute::VectorFunction(vector) => {
+                    let [x, y] = vector.get(point);
+                    format!("({x:.4}, {y:.4})")
+                }
+                NodeAttribute::F64(value) => format!("{value:.4}"),
+                NodeAttribute::Usize(value) => value.to_string(),
+                NodeAttribute::Bool(value) => value.to_string(),
+                _ => return None,
+            };
+            Some((name.clone(), value))
+        })
+        .collect())
+}
+
+/// A node together with every node it depends on, with the dependencies
+/// before the nodes depending on them.
+fn chain(graph: &NoiseGraph, node_id: NodeId) -> Vec<NodeId> {
+    fn visit(
+        graph: &NoiseGraph,
+        node_id: NodeId,
+        visited: &mut HashSet<NodeId>,
+        chain: &mut Vec<NodeId>,
+    ) {
+        if !visited.insert(node_id) {
+            return;
+        }
+        for (_, input_id) in &graph[node_id].inputs {
+            if let Some(output_id) = graph.connection(*input_id) {
+                visit(graph, graph[output_id].node, visited, chain);
+            }
+        }
+        chain.push(node_id);
+    }
+
+    let mut chain = Vec::new();
+    visit(graph, node_id, &mut HashSet::new(), &mut chain);
+    chain
+}
+
+/// The probed point, which can be moved to the camera, and the values of the
+/// nodes leading up to the active node at it.
+pub struct ProbePanel<'a>(pub &'a mut NoiseGraphResource, pub Vec3);
+
+impl<'a> Widget for ProbePanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let ProbePanel(resource, camera) = self;
+        let user_state = &mut resource.user_state;
+
+        ui.heading("Probe");
+        let response = ui.horizontal(|ui| {
+            if let Some([x, z]) = &mut user_state.probe.point {
+                ui.label("x");
+                ui.add(DragValue::new(x));
+                ui.label("z");
+                ui.add(DragValue::new(z));
+            }
+            if ui
+                .button("Camera position")
+                .on_hover_text("Probe the terrain below the camera")
+                .clicked()
+            {
+                user_state.probe.set([camera.x as f64, camera.z as f64]);
+            }
+            if user_state.probe.point.is_some() && ui.button("Clear").clicked() {
+                user_state.probe = Probe::default();
+            }
+        });
+
+        let Some(point) = user_state.probe.point else {
+            return ui.weak("Click the terrain while flying to probe it");
+        };
+        let graph = &resource.state.graph;
+        let Some(active_node) = user_state
+            .active_node
+            .filter(|id| graph.nodes.contains_key(*id))
+        else {
+            return ui.weak("Set a node active to see the values leading up to it");
+        };
+
+        Grid::new("ProbePanel.grid").show(ui, |ui| {
+            for node_id in chain(graph, active_node) {
+                ui.label(graph.node_name(node_id));
+                match values(graph, node_id, &mut user_state.evaluation_cache, point) {
+                    Ok(values) => {
+                        ui.vertical(|ui| {
+                            for (output, value) in values {
+                                ui.label(format!("{output} = {value}"));
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        ui.colored_label(Color32::LIGHT_RED, e.to_string());
+                    }
+                }
+                ui.end_row();
+            }
+        });
+
+        response.response
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,82 @@
+use bevy::prelude::Vec3;
+use egui_node_graph::NodeId;
+
+use super::{chain, values, Probe};
+use crate::noise_graph::{
+    dsl,
+    graph_ext::{EvaluationCache, GraphExt},
+    DynNoiseFn, NoiseGraph,
+};
+
+fn find_node(graph: &NoiseGraph, name: &str) -> NodeId {
+    graph
+        .iter_nodes()
+        .find(|node_id| graph.node_name(*node_id) == name)
+        .unwrap_or_else(|| panic!("No node '{name}'"))
+}
+
+/// Evaluates the last statement of a script.
+fn noise(source: &str) -> DynNoiseFn {
+    let resource = dsl::parse(source).unwrap();
+    resource
+        .state
+        .graph
+        .evaluate_cached(
+            resource.user_state.active_node.unwrap(),
+            &mut EvaluationCache::default(),
+        )
+        .and_then(|value| value.try_to_noise_function())
+        .unwrap()
+}
+
+#[test]
+fn rays_hit_the_terrain_where_they_first_go_below_it() {
+    let height = noise("height = expression(\"10 + x * 0\");");
+
+    let hit = Probe::hit(
+        &height,
+        Vec3::new(0.0, 100.0, 5.0),
+        Vec3::new(1.0, -1.0, 0.0).normalize(),
+    )
+    .unwrap();
+    assert!((hit[0] - 90.0).abs() < 1e-3, "{hit:?}");
+    assert!((hit[1] - 5.0).abs() < 1e-3, "{hit:?}");
+
+    let up = Probe::hit(&height, Vec3::new(0.0, 100.0, 0.0), Vec3::Y);
+    assert!(up.is_none());
+}
+
+#[test]
+fn every_output_of_a_node_shows_its_value() {
+    let resource = dsl::parse("n = normalize(x(), -1, 1, extent: 8, samples: 3);").unwrap();
+    let graph = &resource.state.graph;
+    let values = values(
+        graph,
+        find_node(graph, "n"),
+        &mut EvaluationCache::default(),
+        [4.0, 0.0],
+    )
+    .unwrap();
+
+    assert_eq!(
+        values,
+        [("out", "0.5000"), ("min", "-8.0000"), ("max", "8.0000")]
+            .map(|(output, value)| (output.to_string(), value.to_string()))
+    );
+}
+
+#[test]
+fn chains_list_the_dependencies_before_their_nodes() {
+    let resource =
+        dsl::parse("a = perlin();\nb = scale(a, scale: 2);\nc = lerp(b, a, x());").unwrap();
+    let graph = &resource.state.graph;
+    let names: Vec<String> = chain(graph, find_node(graph, "c"))
+        .into_iter()
+        .map(|node_id| graph.node_name(node_id))
+        .collect();
+
+    assert_eq!(names.len(), 4);
+    assert_eq!(names.last().unwrap(), "c");
+    let position = |name| names.iter().position(|n| *n == name).unwrap();
+    assert!(position("a") < position("b"));
+}
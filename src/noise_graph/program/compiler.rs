@@ -0,0 +1,1305 @@
+use std::collections::HashMap;
+
+use egui_node_graph::{InputId, NodeId, OutputId};
+use noise::{
+    Checkerboard, Cylinders, Fbm, OpenSimplex, Perlin, PerlinSurflet, Simplex, SuperSimplex, Value,
+};
+
+use crate::noise_graph::{
+    connection_type::ConnectionType,
+    dsl::Formula,
+    graph_ext::{check_cycle, GraphExt},
+    node_attribute::{NodeAttribute, NoiseType, WorleyReturnType},
+    node_template::{
+        check_step, clamp_octaves, ErodedFbm, FractalKind, NodeTemplate, Normalize, Shape, Shaping,
+        SyncWorley, MAX_WARP_ITERATIONS,
+    },
+    NoiseGraph,
+};
+
+use super::{
+    blend, selection, sort_curve_points, sort_terrace_points, BinaryOp, Branch, Generator,
+    Instruction, Operand, PointOp, PointRegister, Program, Register, Selection, UnaryOp,
+};
+
+/// Compiles the noise function produced by a node into a [`Program`].
+pub fn compile(graph: &NoiseGraph, node_id: NodeId) -> anyhow::Result<Program> {
+    let mut compiler = Compiler::new(graph);
+    compiler.program.output = compiler.node(node_id, 0)?;
+    Ok(compiler.program)
+}
+
+/// Compiles the noise function connected to an input of a node on its own,
+/// like the source of a `Normalize` node, whose range is estimated from it.
+/// The path of the nodes being compiled is passed on to detect cycles.
+pub fn compile_input(
+    graph: &NoiseGraph,
+    node_id: NodeId,
+    name: &str,
+    path: &[NodeId],
+) -> anyhow::Result<Program> {
+    let mut compiler = Compiler::new(graph);
+    compiler.path = path.to_vec();
+    compiler.program.output = compiler.noise(node_id, name, 0)?;
+    Ok(compiler.program)
+}
+
+/// Identifies an instruction by its operation, parameters and operands, so
+/// instructions computing the same thing are only emitted once.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key(&'static str, Vec<u64>);
+
+/// Everything remembered while compiling a branch, which has to be forgotten
+/// again once the branch is done.
+#[derive(Default)]
+struct Scope {
+    values: Vec<Key>,
+    points: Vec<Key>,
+    nodes: Vec<(NodeId, PointRegister)>,
+}
+
+struct Compiler<'a> {
+    graph: &'a NoiseGraph,
+    program: Program,
+    /// Registers holding constants, keyed by their bits.
+    constants: HashMap<u64, Register>,
+    values: HashMap<Key, Register>,
+    points: HashMap<Key, PointRegister>,
+    /// Already compiled nodes, per point they were sampled at.
+    nodes: HashMap<(NodeId, PointRegister), Operand>,
+    /// Already folded number nodes.
+    numbers: HashMap<NodeId, f64>,
+    /// The estimated source ranges of `Normalize` nodes.
+    ranges: HashMap<NodeId, (f64, f64)>,
+    path: Vec<NodeId>,
+    scopes: Vec<Scope>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(graph: &'a NoiseGraph) -> Self {
+        Self {
+            graph,
+            program: Program {
+                instructions: Vec::new(),
+                constants: Vec::new(),
+                value_registers: 0,
+                point_registers: 1,
+                output: Operand::Constant(0.0),
+            },
+            constants: HashMap::new(),
+            values: HashMap::new(),
+            points: HashMap::new(),
+            nodes: HashMap::new(),
+            numbers: HashMap::new(),
+            ranges: HashMap::new(),
+            path: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    fn node(&mut self, node_id: NodeId, point: PointRegister) -> anyhow::Result<Operand> {
+        if let Some(operand) = self.nodes.get(&(node_id, point)) {
+            return Ok(*operand);
+        }
+
+        check_cycle(self.graph, &self.path, node_id)?;
+        self.path.push(node_id);
+        let operand = self.lower(node_id, point);
+        self.path.pop();
+
+        let operand = operand?;
+        self.nodes.insert((node_id, point), operand);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.nodes.push((node_id, point));
+        }
+        Ok(operand)
+    }
+
+    /// Compiles one of the outputs of a node. Outputs besides "out" aren't
+    /// remembered like nodes are, the keys of their instructions still make
+    /// sure they're only computed once.
+    fn output(&mut self, output_id: OutputId, point: PointRegister) -> anyhow::Result<Operand> {
+        let node_id = self.graph[output_id].node;
+        let name = self.graph.output_name(output_id);
+        if name == "out" {
+            return self.node(node_id, point);
+        }
+
+        check_cycle(self.graph, &self.path, node_id)?;
+        self.path.push(node_id);
+        let operand = match self.graph[node_id].user_data.template {
+            NodeTemplate::Worley if name == "cell value" => {
+                self.worley(node_id, WorleyReturnType::Value, point)
+            }
+            _ => Err(anyhow::anyhow!(
+                "'{}' has no output '{name}'",
+                self.graph.node_name(node_id)
+            )),
+        };
+        self.path.pop();
+        operand
+    }
+
+    fn lower(&mut self, node_id: NodeId, point: PointRegister) -> anyhow::Result<Operand> {
+        let template = self.graph[node_id].user_data.template;
+        let operand = match template {
+            NodeTemplate::Abs => self.unary(node_id, point, UnaryOp::Abs)?,
+            NodeTemplate::Add => self.binary(node_id, point, BinaryOp::Add)?,
+            NodeTemplate::Arithmetic | NodeTemplate::Float => anyhow::bail!(
+                "'{}' outputs a number, not a noise function",
+                self.graph.node_name(node_id)
+            ),
+            NodeTemplate::BasicMulti => self.fractal(node_id, FractalKind::BasicMulti, point)?,
+            NodeTemplate::Billow => self.fractal(node_id, FractalKind::Billow, point)?,
+            NodeTemplate::Blend => {
+                let source_1 = self.noise(node_id, "source 1", point)?;
+                let source_2 = self.noise(node_id, "source 2", point)?;
+                let control = self.noise(node_id, "control", point)?;
+                self.apply_blend(source_1, source_2, control)
+            }
+            // Caching is implied by compiling every node only once per point.
+            NodeTemplate::Cache => self.noise(node_id, "source", point)?,
+            NodeTemplate::Checkerboard => {
+                let size = self.attribute(node_id, "size")?.try_to_usize()?;
+                self.sample(
+                    Key("checkerboard", vec![size as u64]),
+                    Generator::Checkerboard(Checkerboard::new(size)),
+                    point,
+                )
+            }
+            NodeTemplate::Clamp => {
+                let lower = self.number(node_id, "bounds lower")?;
+                let upper = self.number(node_id, "bounds upper")?;
+                self.unary(node_id, point, UnaryOp::Clamp(lower, upper))?
+            }
+            NodeTemplate::Constant => Operand::Constant(self.number(node_id, "value")?),
+            NodeTemplate::Cos => self.shaping(
+                node_id,
+                Shaping::Cos,
+                ["source", "frequency", "phase"],
+                point,
+            )?,
+            NodeTemplate::Curl | NodeTemplate::Gradient | NodeTemplate::Vector => anyhow::bail!(
+                "'{}' outputs a vector, not a noise function",
+                self.graph.node_name(node_id)
+            ),
+            NodeTemplate::Curve => {
+                let control_points = self.attribute(node_id, "control points")?.try_to_vec()?;
+                if control_points.len() < 4 {
+                    anyhow::bail!("Curve needs at least 4 control points");
+                }
+                let control_points = control_points
+                    .into_iter()
+                    .map(NodeAttribute::try_to_f64_tuple)
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let sorted = sort_curve_points(control_points);
+                if sorted.len() < 4 {
+                    anyhow::bail!("Curve needs at least 4 distinct control points");
+                }
+                self.unary(node_id, point, UnaryOp::Curve(sorted))?
+            }
+            NodeTemplate::Cylinders => {
+                let frequency = self.number(node_id, "frequency")?;
+                self.sample(
+                    Key("cylinders", vec![frequency.to_bits()]),
+                    Generator::Cylinders(Cylinders::new().set_frequency(frequency)),
+                    point,
+                )
+            }
+            NodeTemplate::Displace => {
+                // Only x and y displace a 2D point, but the other inputs are
+                // still required to be connected.
+                self.connected(node_id, "z")?;
+                self.connected(node_id, "u")?;
+                let x = self.noise(node_id, "x", point)?;
+                let y = self.noise(node_id, "y", point)?;
+                let displaced = self.displace(point, x, y);
+                self.noise(node_id, "source", displaced)?
+            }
+            NodeTemplate::DistanceToLine => {
+                let start = [
+                    self.number(node_id, "start x")?,
+                    self.number(node_id, "start y")?,
+                ];
+                let end = [
+                    self.number(node_id, "end x")?,
+                    self.number(node_id, "end y")?,
+                ];
+                self.shape(Shape::DistanceToLine { start, end }, point)
+            }
+            NodeTemplate::DistanceToPoint => {
+                let x = self.number(node_id, "x")?;
+                let y = self.number(node_id, "y")?;
+                self.shape(Shape::DistanceToPoint { point: [x, y] }, point)
+            }
+            NodeTemplate::DomainWarp => {
+                // Fails for an unconnected warp even without iterations, just
+                // like the evaluation.
+                self.connected(node_id, "source")?;
+                self.vector_source(node_id, "warp")?;
+                let strength = Operand::Constant(self.number(node_id, "strength")?);
+                let iterations = self.attribute(node_id, "iterations")?.try_to_usize()?;
+
+                let mut warped = point;
+                for _ in 0..iterations.min(MAX_WARP_ITERATIONS) {
+                    let (x, y) = self.vector(node_id, "warp", warped)?;
+                    let x = self.apply_binary(BinaryOp::Multiply, x, strength);
+                    let y = self.apply_binary(BinaryOp::Multiply, y, strength);
+                    warped = self.displace(point, x, y);
+                }
+                self.noise(node_id, "source", warped)?
+            }
+            NodeTemplate::ErodedFbm => {
+                let seed = self.seed(node_id)?;
+                let octaves = self.attribute(node_id, "octaves")?.try_to_usize()?;
+                let gain = self.number(node_id, "gain")?;
+                let lacunarity = self.number(node_id, "lacunarity")?;
+                let gradient_strength = self.number(node_id, "gradient strength")?;
+                self.generator(
+                    node_id,
+                    Key(
+                        "eroded fbm",
+                        vec![
+                            seed as u64,
+                            octaves as u64,
+                            gain.to_bits(),
+                            lacunarity.to_bits(),
+                            gradient_strength.to_bits(),
+                        ],
+                    ),
+                    Generator::ErodedFbm(
+                        ErodedFbm::new(seed)
+                            .set_octaves(octaves)
+                            .set_gain(gain)
+                            .set_lacunarity(lacunarity)
+                            .set_gradient_strength(gradient_strength),
+                    ),
+                    point,
+                )?
+            }
+            NodeTemplate::Exponent => {
+                let exponent = self.number(node_id, "exponent")?;
+                self.unary(node_id, point, UnaryOp::Exponent(exponent))?
+            }
+            NodeTemplate::Expression => {
+                let formula = self.attribute(node_id, "formula")?.try_to_formula()?;
+                let inputs = formula
+                    .variables()
+                    .iter()
+                    .map(|variable| self.noise(node_id, variable, point))
+                    .collect::<anyhow::Result<_>>()?;
+                self.apply_expression(formula, inputs, point)
+            }
+            NodeTemplate::Fbm => self.fractal(node_id, FractalKind::Fbm, point)?,
+            NodeTemplate::HybridMulti => self.fractal(node_id, FractalKind::HybridMulti, point)?,
+            NodeTemplate::Lerp => self.shaping(
+                node_id,
+                Shaping::Lerp,
+                ["source 1", "source 2", "factor"],
+                point,
+            )?,
+            NodeTemplate::LinearGradient => {
+                let start = [
+                    self.number(node_id, "start x")?,
+                    self.number(node_id, "start y")?,
+                ];
+                let end = [
+                    self.number(node_id, "end x")?,
+                    self.number(node_id, "end y")?,
+                ];
+                let falloff = self.number(node_id, "falloff")?;
+                self.shape(
+                    Shape::LinearGradient {
+                        start,
+                        end,
+                        falloff,
+                    },
+                    point,
+                )
+            }
+            NodeTemplate::Max => self.binary(node_id, point, BinaryOp::Max)?,
+            NodeTemplate::Min => self.binary(node_id, point, BinaryOp::Min)?,
+            NodeTemplate::Modulo => {
+                self.shaping(node_id, Shaping::Modulo, ["source", "divisor"], point)?
+            }
+            NodeTemplate::Multiply => self.binary(node_id, point, BinaryOp::Multiply)?,
+            NodeTemplate::Negate => self.unary(node_id, point, UnaryOp::Negate)?,
+            NodeTemplate::Normalize => self.normalize(node_id, point)?,
+            NodeTemplate::OpenSimplex => {
+                let seed = self.seed(node_id)?;
+                self.generator(
+                    node_id,
+                    Key("open simplex", vec![seed as u64]),
+                    Generator::OpenSimplex(OpenSimplex::new(seed)),
+                    point,
+                )?
+            }
+            NodeTemplate::Output => self.noise(node_id, "source", point)?,
+            NodeTemplate::Overlay => {
+                self.shaping(node_id, Shaping::Overlay, ["base", "blend"], point)?
+            }
+            NodeTemplate::Perlin => {
+                let seed = self.seed(node_id)?;
+                self.generator(
+                    node_id,
+                    Key("perlin", vec![seed as u64]),
+                    Generator::Perlin(Perlin::new(seed)),
+                    point,
+                )?
+            }
+            NodeTemplate::PerlinSurflet => {
+                let seed = self.seed(node_id)?;
+                self.generator(
+                    node_id,
+                    Key("perlin surflet", vec![seed as u64]),
+                    Generator::PerlinSurflet(PerlinSurflet::new(seed)),
+                    point,
+                )?
+            }
+            NodeTemplate::Power => self.binary(node_id, point, BinaryOp::Power)?,
+            NodeTemplate::Quantize => {
+                self.shaping(node_id, Shaping::Quantize, ["source", "step size"], point)?
+            }
+            NodeTemplate::RadialGradient => {
+                let center = [
+                    self.number(node_id, "center x")?,
+                    self.number(node_id, "center y")?,
+                ];
+                let radius = self.number(node_id, "radius")?;
+                let falloff = self.number(node_id, "falloff")?;
+                self.shape(
+                    Shape::RadialGradient {
+                        center,
+                        radius,
+                        falloff,
+                    },
+                    point,
+                )
+            }
+            NodeTemplate::Remap => self.shaping(
+                node_id,
+                Shaping::Remap,
+                ["source", "from min", "from max", "to min", "to max"],
+                point,
+            )?,
+            NodeTemplate::RidgedMulti => {
+                let attenuation = self.number(node_id, "attenuation")?;
+                self.fractal(node_id, FractalKind::RidgedMulti { attenuation }, point)?
+            }
+            NodeTemplate::RotatePoint => {
+                // A 2D point is rotated around the z axis only.
+                let theta = self.number(node_id, "z angle")?.to_radians();
+                let op = PointOp::Rotate {
+                    cos: theta.cos(),
+                    sin: theta.sin(),
+                };
+                let rotated = self.transform(point, op);
+                self.noise(node_id, "source", rotated)?
+            }
+            NodeTemplate::Scale => {
+                let scale = self.number(node_id, "scale")?;
+                let divided = self.transform(point, PointOp::Divide(scale));
+                let source = self.noise(node_id, "source", divided)?;
+                self.apply_unary(UnaryOp::Scale(scale), source)
+            }
+            NodeTemplate::ScaleBias => {
+                let scale = self.number(node_id, "scale")?;
+                let bias = self.number(node_id, "bias")?;
+                self.unary(node_id, point, UnaryOp::ScaleBias(scale, bias))?
+            }
+            NodeTemplate::ScalePoint => {
+                let x = self.number(node_id, "x")?;
+                let y = self.number(node_id, "y")?;
+                let scaled = self.transform(point, PointOp::Scale([x, y]));
+                self.noise(node_id, "source", scaled)?
+            }
+            NodeTemplate::Screen => {
+                self.shaping(node_id, Shaping::Screen, ["base", "blend"], point)?
+            }
+            NodeTemplate::Select => {
+                let bounds = (
+                    self.number(node_id, "bounds lower")?,
+                    self.number(node_id, "bounds upper")?,
+                );
+                let falloff = self.number(node_id, "falloff")?;
+                match self.noise(node_id, "control", point)? {
+                    // The selected sources are known while compiling.
+                    Operand::Constant(control) => match selection(control, bounds, falloff) {
+                        Selection::Source1 => self.noise(node_id, "source 1", point)?,
+                        Selection::Source2 => self.noise(node_id, "source 2", point)?,
+                        Selection::Blend { from, alpha } => {
+                            let sources = ["source 1", "source 2"];
+                            let from_value = self.noise(node_id, sources[from], point)?;
+                            let to_value = self.noise(node_id, sources[1 - from], point)?;
+                            self.apply_blend(from_value, to_value, Operand::Constant(alpha))
+                        }
+                    },
+                    Operand::Register(control) => {
+                        let source_1 = self.branch(node_id, "source 1", point)?;
+                        let source_2 = self.branch(node_id, "source 2", point)?;
+                        Operand::Register(self.push(|out| Instruction::Select {
+                            source_1,
+                            source_2,
+                            control,
+                            bounds,
+                            falloff,
+                            out,
+                        }))
+                    }
+                }
+            }
+            NodeTemplate::Simplex => {
+                let seed = self.seed(node_id)?;
+                self.generator(
+                    node_id,
+                    Key("simplex", vec![seed as u64]),
+                    Generator::Simplex(Simplex::new(seed)),
+                    point,
+                )?
+            }
+            NodeTemplate::Sin => self.shaping(
+                node_id,
+                Shaping::Sin,
+                ["source", "frequency", "phase"],
+                point,
+            )?,
+            NodeTemplate::Smoothstep => self.shaping(
+                node_id,
+                Shaping::Smoothstep,
+                ["source", "lower edge", "upper edge"],
+                point,
+            )?,
+            NodeTemplate::SoftLight => {
+                self.shaping(node_id, Shaping::SoftLight, ["base", "blend"], point)?
+            }
+            NodeTemplate::Step => {
+                self.shaping(node_id, Shaping::Step, ["source", "threshold"], point)?
+            }
+            NodeTemplate::SuperSimplex => {
+                let seed = self.seed(node_id)?;
+                self.generator(
+                    node_id,
+                    Key("super simplex", vec![seed as u64]),
+                    Generator::SuperSimplex(SuperSimplex::new(seed)),
+                    point,
+                )?
+            }
+            NodeTemplate::Terrace => {
+                let control_points = self.attribute(node_id, "control points")?.try_to_vec()?;
+                if control_points.len() < 2 {
+                    anyhow::bail!("Terrace requires at least 2 control points");
+                }
+                let control_points = control_points
+                    .into_iter()
+                    .map(NodeAttribute::try_to_f64)
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let sorted = sort_terrace_points(control_points);
+                if sorted.len() < 2 {
+                    anyhow::bail!("Terrace requires at least 2 distinct control points");
+                }
+                self.unary(node_id, point, UnaryOp::Terrace(sorted))?
+            }
+            NodeTemplate::TranslatePoint => {
+                let x = self.number(node_id, "x")?;
+                let y = self.number(node_id, "y")?;
+                let translated = self.transform(point, PointOp::Translate([x, y]));
+                self.noise(node_id, "source", translated)?
+            }
+            NodeTemplate::Turbulence => {
+                let frequency = self.number(node_id, "frequency")?;
+                let power = self.number(node_id, "power")?;
+                let roughness = self.attribute(node_id, "roughness")?.try_to_usize()?;
+                let noise_type = self.noise_type(node_id)?;
+
+                // Same offsets and distortion functions as `noise::Turbulence`.
+                let x_point = self.transform(
+                    point,
+                    PointOp::Translate([12414.0 / 65536.0, 65124.0 / 65536.0]),
+                );
+                let y_point = self.transform(
+                    point,
+                    PointOp::Translate([26519.0 / 65536.0, 18128.0 / 65536.0]),
+                );
+                let x_distort = self.distortion(noise_type, 0, frequency, roughness, x_point)?;
+                let y_distort = self.distortion(noise_type, 1, frequency, roughness, y_point)?;
+                let x_distort =
+                    self.apply_binary(BinaryOp::Multiply, x_distort, Operand::Constant(power));
+                let y_distort =
+                    self.apply_binary(BinaryOp::Multiply, y_distort, Operand::Constant(power));
+
+                let distorted = self.displace(point, x_distort, y_distort);
+                self.noise(node_id, "source", distorted)?
+            }
+            NodeTemplate::Value => {
+                let seed = self.seed(node_id)?;
+                self.generator(
+                    node_id,
+                    Key("value", vec![seed as u64]),
+                    Generator::Value(Value::new(seed)),
+                    point,
+                )?
+            }
+            NodeTemplate::Worley => {
+                let return_type = self
+                    .attribute(node_id, "return type")?
+                    .try_to_return_type()?;
+                self.worley(node_id, return_type, point)?
+            }
+            NodeTemplate::X => self.shape(Shape::X, point),
+            NodeTemplate::Y => self.shape(Shape::Y, point),
+        };
+
+        Ok(operand)
+    }
+
+    /// The value of an input, taken from the connected number node if there
+    /// is one.
+    fn attribute(&mut self, node_id: NodeId, name: &str) -> anyhow::Result<NodeAttribute> {
+        let input_id = self.graph[node_id].get_input(name)?;
+        let value = self.unconverted(input_id)?;
+        match self.graph.connection(input_id) {
+            Some(_) => Ok(value.convert_to(self.graph[input_id].typ)),
+            None => Ok(value),
+        }
+    }
+
+    /// The value of an input before it is converted to the type of the input,
+    /// checked against the range of the input.
+    fn unconverted(&mut self, input_id: InputId) -> anyhow::Result<NodeAttribute> {
+        let value = match self.graph.connection(input_id) {
+            Some(output_id) => NodeAttribute::F64(self.fold_output(output_id)?),
+            None => self.graph[input_id].value.clone(),
+        };
+        self.graph.check_input(input_id, &value)?;
+        Ok(value)
+    }
+
+    fn number(&mut self, node_id: NodeId, name: &str) -> anyhow::Result<f64> {
+        self.attribute(node_id, name)?.try_to_f64()
+    }
+
+    fn noise_type(&mut self, node_id: NodeId) -> anyhow::Result<NoiseType> {
+        self.attribute(node_id, "noise type")?.try_to_noise_type()
+    }
+
+    fn seed(&mut self, node_id: NodeId) -> anyhow::Result<u32> {
+        self.attribute(node_id, "seed")?.try_to_seed()
+    }
+
+    /// Computes a number output while compiling.
+    fn fold_output(&mut self, output_id: OutputId) -> anyhow::Result<f64> {
+        let node_id = self.graph[output_id].node;
+        let name = self.graph.output_name(output_id);
+        if let NodeTemplate::Normalize = self.graph[node_id].user_data.template {
+            check_cycle(self.graph, &self.path, node_id)?;
+            self.path.push(node_id);
+            let range = self.normalize_range(node_id);
+            self.path.pop();
+            let (min, max) = range?;
+            match name {
+                "min" => return Ok(min),
+                "max" => return Ok(max),
+                _ => {}
+            }
+        }
+
+        self.fold(node_id)
+    }
+
+    /// Computes the output of a number node while compiling.
+    fn fold(&mut self, node_id: NodeId) -> anyhow::Result<f64> {
+        if let Some(value) = self.numbers.get(&node_id) {
+            return Ok(*value);
+        }
+
+        check_cycle(self.graph, &self.path, node_id)?;
+        self.path.push(node_id);
+        let value = match self.graph[node_id].user_data.template {
+            NodeTemplate::Float => self.number(node_id, "value"),
+            NodeTemplate::Arithmetic => self.arithmetic(node_id),
+            _ => Err(anyhow::anyhow!(
+                "'{}' doesn't output a number",
+                self.graph.node_name(node_id)
+            )),
+        };
+        self.path.pop();
+
+        let value = value?;
+        self.numbers.insert(node_id, value);
+        Ok(value)
+    }
+
+    /// Maps the estimated range of the source onto the target range, see
+    /// `Normalize`.
+    fn normalize(&mut self, node_id: NodeId, point: PointRegister) -> anyhow::Result<Operand> {
+        let source = self.noise(node_id, "source", point)?;
+        let to_min = self.number(node_id, "to min")?;
+        let to_max = self.number(node_id, "to max")?;
+        let clamp = self.attribute(node_id, "clamp")?.try_to_bool()?;
+        let range = self.normalize_range(node_id)?;
+
+        let (scale, bias) = Normalize::scale_bias(range, to_min, to_max);
+        let scaled = self.apply_unary(UnaryOp::ScaleBias(scale, bias), source);
+        if clamp {
+            let (lower, upper) = Normalize::bounds(to_min, to_max);
+            Ok(self.apply_unary(UnaryOp::Clamp(lower, upper), scaled))
+        } else {
+            Ok(scaled)
+        }
+    }
+
+    /// Estimates the range of the source of a `Normalize` node by sampling
+    /// the source compiled on its own. Expects the node to be on the path.
+    fn normalize_range(&mut self, node_id: NodeId) -> anyhow::Result<(f64, f64)> {
+        if let Some(range) = self.ranges.get(&node_id) {
+            return Ok(*range);
+        }
+
+        let extent = self.number(node_id, "extent")?;
+        let samples = self.attribute(node_id, "samples")?.try_to_usize()?;
+        let source = compile_input(self.graph, node_id, "source", &self.path)?;
+        let range = Normalize::estimate_range(&source, extent, samples)?;
+        self.ranges.insert(node_id, range);
+        Ok(range)
+    }
+
+    fn arithmetic(&mut self, node_id: NodeId) -> anyhow::Result<f64> {
+        let operator = self.attribute(node_id, "operator")?.try_to_operator()?;
+        let a = self.number(node_id, "a")?;
+        let b = self.number(node_id, "b")?;
+        Ok(operator.apply(a, b))
+    }
+
+    /// Compiles the node connected to a noise input, sampled at `point`.
+    fn noise(
+        &mut self,
+        node_id: NodeId,
+        name: &str,
+        point: PointRegister,
+    ) -> anyhow::Result<Operand> {
+        let input_id = self.graph[node_id].get_input(name)?;
+        match self.graph.connection(input_id) {
+            // Numbers become constants.
+            Some(output_id) if self.graph[output_id].typ == ConnectionType::F64 => {
+                Ok(Operand::Constant(self.unconverted(input_id)?.try_to_f64()?))
+            }
+            Some(output_id) => self.output(output_id, point),
+            None => match &self.graph[input_id].value {
+                // Noise inputs which take a constant while nothing is
+                // connected.
+                NodeAttribute::F64(_) => {
+                    Ok(Operand::Constant(self.unconverted(input_id)?.try_to_f64()?))
+                }
+                value => {
+                    // Other noise inputs never hold a value of their own, so
+                    // this fails the same way the regular evaluation does.
+                    value.clone().try_to_noise_function()?;
+                    anyhow::bail!("Input '{name}' isn't connected")
+                }
+            },
+        }
+    }
+
+    /// The node connected to a vector input.
+    fn vector_source(&self, node_id: NodeId, name: &str) -> anyhow::Result<NodeId> {
+        let input_id = self.graph[node_id].get_input(name)?;
+        match self.graph.connection(input_id) {
+            Some(output_id) => Ok(self.graph[output_id].node),
+            None => {
+                self.graph[input_id]
+                    .value
+                    .clone()
+                    .try_to_vector_function()?;
+                anyhow::bail!("Input '{name}' isn't connected")
+            }
+        }
+    }
+
+    /// Compiles the vector field connected to an input, sampled at `point`.
+    /// Vector nodes aren't remembered like noise nodes, their components are
+    /// still only computed once thanks to the keys of their instructions.
+    fn vector(
+        &mut self,
+        node_id: NodeId,
+        name: &str,
+        point: PointRegister,
+    ) -> anyhow::Result<(Operand, Operand)> {
+        let source = self.vector_source(node_id, name)?;
+        check_cycle(self.graph, &self.path, source)?;
+        self.path.push(source);
+        let vector = self.lower_vector(source, point);
+        self.path.pop();
+        vector
+    }
+
+    fn lower_vector(
+        &mut self,
+        node_id: NodeId,
+        point: PointRegister,
+    ) -> anyhow::Result<(Operand, Operand)> {
+        match self.graph[node_id].user_data.template {
+            NodeTemplate::Curl => {
+                let (x, y) = self.gradient(node_id, point)?;
+                Ok((self.apply_unary(UnaryOp::Negate, y), x))
+            }
+            NodeTemplate::Gradient => self.gradient(node_id, point),
+            NodeTemplate::Vector => Ok((
+                self.noise(node_id, "x", point)?,
+                self.noise(node_id, "y", point)?,
+            )),
+            _ => anyhow::bail!(
+                "'{}' doesn't output a vector",
+                self.graph.node_name(node_id)
+            ),
+        }
+    }
+
+    /// The central differences of the source of a gradient node, like
+    /// [`Gradient`](crate::noise_graph::node_template::Gradient) computes
+    /// them.
+    fn gradient(
+        &mut self,
+        node_id: NodeId,
+        point: PointRegister,
+    ) -> anyhow::Result<(Operand, Operand)> {
+        let step = self.number(node_id, "step")?;
+        check_step(step)?;
+        let scale = Operand::Constant(0.5 / step);
+
+        let mut components = [Operand::Constant(0.0); 2];
+        for (axis, component) in components.iter_mut().enumerate() {
+            let mut offset = [0.0; 2];
+            offset[axis] = step;
+            let ahead = self.transform(point, PointOp::Translate(offset));
+            offset[axis] = -step;
+            let behind = self.transform(point, PointOp::Translate(offset));
+
+            let ahead = self.noise(node_id, "source", ahead)?;
+            let behind = self.noise(node_id, "source", behind)?;
+            let behind = self.apply_unary(UnaryOp::Negate, behind);
+            let difference = self.apply_binary(BinaryOp::Add, ahead, behind);
+            *component = self.apply_binary(BinaryOp::Multiply, difference, scale);
+        }
+        Ok((components[0], components[1]))
+    }
+
+    fn connected(&self, node_id: NodeId, name: &str) -> anyhow::Result<()> {
+        let input_id = self.graph[node_id].get_input(name)?;
+        if self.graph.connection(input_id).is_none() {
+            self.graph[input_id].value.clone().try_to_noise_function()?;
+        }
+        Ok(())
+    }
+
+    fn unary(
+        &mut self,
+        node_id: NodeId,
+        point: PointRegister,
+        op: UnaryOp,
+    ) -> anyhow::Result<Operand> {
+        let source = self.noise(node_id, "source", point)?;
+        Ok(self.apply_unary(op, source))
+    }
+
+    fn apply_unary(&mut self, op: UnaryOp, source: Operand) -> Operand {
+        let source = match source {
+            Operand::Constant(value) => return Operand::Constant(op.apply(value)),
+            Operand::Register(source) => source,
+        };
+
+        let mut args = vec![source as u64];
+        match &op {
+            UnaryOp::Abs | UnaryOp::Negate => {}
+            UnaryOp::Exponent(a) | UnaryOp::Scale(a) => args.push(a.to_bits()),
+            UnaryOp::Clamp(a, b) | UnaryOp::ScaleBias(a, b) => {
+                args.extend([a.to_bits(), b.to_bits()])
+            }
+            UnaryOp::Curve(control_points) => args.extend(
+                control_points
+                    .iter()
+                    .flat_map(|(input, output)| [input.to_bits(), output.to_bits()]),
+            ),
+            UnaryOp::Terrace(control_points) => {
+                args.extend(control_points.iter().map(|x| x.to_bits()))
+            }
+        }
+        let name = match &op {
+            UnaryOp::Abs => "abs",
+            UnaryOp::Negate => "negate",
+            UnaryOp::Clamp(..) => "clamp",
+            UnaryOp::Exponent(_) => "exponent",
+            UnaryOp::ScaleBias(..) => "scale bias",
+            UnaryOp::Scale(_) => "scale",
+            UnaryOp::Curve(_) => "curve",
+            UnaryOp::Terrace(_) => "terrace",
+        };
+
+        self.emit(Key(name, args), |out| Instruction::Unary {
+            op,
+            source,
+            out,
+        })
+    }
+
+    fn binary(
+        &mut self,
+        node_id: NodeId,
+        point: PointRegister,
+        op: BinaryOp,
+    ) -> anyhow::Result<Operand> {
+        let source_1 = self.noise(node_id, "source 1", point)?;
+        let source_2 = self.noise(node_id, "source 2", point)?;
+        Ok(self.apply_binary(op, source_1, source_2))
+    }
+
+    fn apply_binary(&mut self, op: BinaryOp, source_1: Operand, source_2: Operand) -> Operand {
+        if let (Operand::Constant(a), Operand::Constant(b)) = (source_1, source_2) {
+            return Operand::Constant(op.apply(a, b));
+        }
+
+        let source_1 = self.register(source_1);
+        let source_2 = self.register(source_2);
+        let name = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Multiply => "multiply",
+            BinaryOp::Min => "min",
+            BinaryOp::Max => "max",
+            BinaryOp::Power => "power",
+        };
+
+        self.emit(Key(name, vec![source_1 as u64, source_2 as u64]), |out| {
+            Instruction::Binary {
+                op,
+                source_1,
+                source_2,
+                out,
+            }
+        })
+    }
+
+    fn apply_blend(&mut self, source_1: Operand, source_2: Operand, control: Operand) -> Operand {
+        if let (Operand::Constant(a), Operand::Constant(b), Operand::Constant(c)) =
+            (source_1, source_2, control)
+        {
+            return Operand::Constant(blend(a, b, c));
+        }
+
+        let source_1 = self.register(source_1);
+        let source_2 = self.register(source_2);
+        let control = self.register(control);
+        self.emit(
+            Key(
+                "blend",
+                vec![source_1 as u64, source_2 as u64, control as u64],
+            ),
+            |out| Instruction::Blend {
+                source_1,
+                source_2,
+                control,
+                out,
+            },
+        )
+    }
+
+    /// Applies a shaping function to the noise connected to the inputs, or
+    /// their constants.
+    fn shaping<const N: usize>(
+        &mut self,
+        node_id: NodeId,
+        shaping: Shaping,
+        inputs: [&str; N],
+        point: PointRegister,
+    ) -> anyhow::Result<Operand> {
+        let sources = inputs
+            .into_iter()
+            .map(|name| self.noise(node_id, name, point))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let constants: Option<Vec<f64>> = sources
+            .iter()
+            .map(|source| match source {
+                Operand::Constant(value) => Some(*value),
+                Operand::Register(_) => None,
+            })
+            .collect();
+        if let Some(constants) = constants {
+            return Ok(Operand::Constant(shaping.apply(&constants)));
+        }
+
+        let sources: Vec<Register> = sources
+            .into_iter()
+            .map(|source| self.register(source))
+            .collect();
+        let mut args = vec![shaping as u64];
+        args.extend(sources.iter().map(|source| *source as u64));
+
+        Ok(self.emit(Key("shaping", args), |out| Instruction::Shaping {
+            shaping,
+            sources,
+            out,
+        }))
+    }
+
+    /// Evaluates a formula, while compiling if it only depends on constants.
+    fn apply_expression(
+        &mut self,
+        formula: Formula,
+        inputs: Vec<Operand>,
+        point: PointRegister,
+    ) -> Operand {
+        let constants: Option<Vec<f64>> = inputs
+            .iter()
+            .map(|input| match input {
+                Operand::Constant(value) => Some(*value),
+                Operand::Register(_) => None,
+            })
+            .collect();
+        if let Some(constants) = constants.filter(|_| !formula.uses_point()) {
+            return Operand::Constant(formula.evaluate(&constants, [0.0; 2]));
+        }
+
+        let inputs = inputs
+            .into_iter()
+            .map(|input| self.register(input))
+            .collect();
+        Operand::Register(self.push(|out| Instruction::Expression {
+            formula: Box::new(formula),
+            inputs,
+            point,
+            out,
+        }))
+    }
+
+    /// Compiles a noise input into a separate list of instructions, which is
+    /// only run when needed. Nothing computed inside of it is reused outside.
+    fn branch(
+        &mut self,
+        node_id: NodeId,
+        name: &str,
+        point: PointRegister,
+    ) -> anyhow::Result<Branch> {
+        let outer = std::mem::take(&mut self.program.instructions);
+        self.scopes.push(Scope::default());
+
+        let result = self.noise(node_id, name, point);
+
+        let scope = self.scopes.pop().expect("Scope was pushed above");
+        for key in scope.values {
+            self.values.remove(&key);
+        }
+        for key in scope.points {
+            self.points.remove(&key);
+        }
+        for key in scope.nodes {
+            self.nodes.remove(&key);
+        }
+        let instructions = std::mem::replace(&mut self.program.instructions, outer);
+
+        Ok(Branch {
+            instructions,
+            result: self.register(result?),
+        })
+    }
+
+    /// One of the fbm functions `noise::Turbulence` distorts points with.
+    fn distortion(
+        &mut self,
+        noise_type: NoiseType,
+        seed: u32,
+        frequency: f64,
+        roughness: usize,
+        point: PointRegister,
+    ) -> anyhow::Result<Operand> {
+        type Defaults = Fbm<Perlin>;
+
+        let octaves = self.octaves(
+            OctaveSource::Generator(noise_type, seed),
+            clamp_octaves(roughness),
+            (frequency, Defaults::DEFAULT_LACUNARITY),
+            point,
+        )?;
+        Ok(self.apply_fractal(FractalKind::Fbm, Defaults::DEFAULT_PERSISTENCE, octaves))
+    }
+
+    /// Samples the octaves of a fractal node and combines them like
+    /// [`Fractal`](crate::noise_graph::node_template::Fractal) does.
+    fn fractal(
+        &mut self,
+        node_id: NodeId,
+        kind: FractalKind,
+        point: PointRegister,
+    ) -> anyhow::Result<Operand> {
+        let octaves = clamp_octaves(self.attribute(node_id, "octaves")?.try_to_usize()?);
+        let frequency = self.number(node_id, "frequency")?;
+        let lacunarity = self.number(node_id, "lacunarity")?;
+        let persistence = self.number(node_id, "persistence")?;
+
+        let input_id = self.graph[node_id].get_input("source")?;
+        let source = match self.graph.connection(input_id) {
+            Some(_) => OctaveSource::Node(node_id),
+            None => OctaveSource::Generator(self.noise_type(node_id)?, 0),
+        };
+
+        let octaves = self.octaves(source, octaves, (frequency, lacunarity), point)?;
+        Ok(self.apply_fractal(kind, persistence, octaves))
+    }
+
+    /// Samples every octave at `lacunarity` times the frequency of the
+    /// previous one, starting at `frequency`.
+    fn octaves(
+        &mut self,
+        source: OctaveSource,
+        octaves: usize,
+        (frequency, lacunarity): (f64, f64),
+        point: PointRegister,
+    ) -> anyhow::Result<Vec<Operand>> {
+        let mut point = self.transform(point, PointOp::Scale([frequency, frequency]));
+        let mut values = Vec::with_capacity(octaves);
+        for octave in 0..octaves {
+            if octave > 0 {
+                point = self.transform(point, PointOp::Scale([lacunarity, lacunarity]));
+            }
+            let value = match source {
+                OctaveSource::Generator(noise_type, seed) => {
+                    let seed = seed + octave as u32;
+                    self.sample(
+                        Key("octave", vec![noise_type as u64, seed as u64]),
+                        octave_generator(noise_type, seed),
+                        point,
+                    )
+                }
+                OctaveSource::Node(node_id) => self.noise(node_id, "source", point)?,
+            };
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    fn apply_fractal(
+        &mut self,
+        kind: FractalKind,
+        persistence: f64,
+        octaves: Vec<Operand>,
+    ) -> Operand {
+        let constants: Option<Vec<f64>> = octaves
+            .iter()
+            .map(|octave| match octave {
+                Operand::Constant(value) => Some(*value),
+                Operand::Register(_) => None,
+            })
+            .collect();
+        if let Some(constants) = constants {
+            return Operand::Constant(kind.combine(persistence, constants.into_iter()));
+        }
+
+        let octaves: Vec<Register> = octaves
+            .into_iter()
+            .map(|octave| self.register(octave))
+            .collect();
+        let mut args = match kind {
+            FractalKind::Fbm => vec![0],
+            FractalKind::Billow => vec![1],
+            FractalKind::BasicMulti => vec![2],
+            FractalKind::HybridMulti => vec![3],
+            FractalKind::RidgedMulti { attenuation } => vec![4, attenuation.to_bits()],
+        };
+        args.push(persistence.to_bits());
+        args.extend(octaves.iter().map(|octave| *octave as u64));
+
+        self.emit(Key("fractal", args), |out| Instruction::Fractal {
+            kind,
+            persistence,
+            octaves,
+            out,
+        })
+    }
+
+    /// Samples a generator at the frequency of its node and applies the
+    /// amplitude and offset, like `NodeEvaluator::output_generator`.
+    fn generator(
+        &mut self,
+        node_id: NodeId,
+        key: Key,
+        generator: Generator,
+        point: PointRegister,
+    ) -> anyhow::Result<Operand> {
+        let frequency = self.number(node_id, "frequency")?;
+        let amplitude = self.number(node_id, "amplitude")?;
+        let offset = self.number(node_id, "offset")?;
+
+        let scaled = self.transform(point, PointOp::Scale([frequency, frequency]));
+        let value = self.sample(key, generator, scaled);
+        Ok(self.apply_unary(UnaryOp::ScaleBias(amplitude, offset), value))
+    }
+
+    fn worley(
+        &mut self,
+        node_id: NodeId,
+        return_type: WorleyReturnType,
+        point: PointRegister,
+    ) -> anyhow::Result<Operand> {
+        let seed = self.seed(node_id)?;
+        let distance_function = self
+            .attribute(node_id, "distance function")?
+            .try_to_distance_function()?;
+        self.generator(
+            node_id,
+            Key(
+                "worley",
+                vec![seed as u64, return_type as u64, distance_function as u64],
+            ),
+            Generator::Worley(
+                SyncWorley::new(seed)
+                    .set_distance_function(distance_function.function())
+                    .set_return_type(return_type.into()),
+            ),
+            point,
+        )
+    }
+
+    fn shape(&mut self, shape: Shape, point: PointRegister) -> Operand {
+        let args = match shape {
+            Shape::X => vec![0],
+            Shape::Y => vec![1],
+            Shape::DistanceToPoint { point: [x, y] } => vec![2, x.to_bits(), y.to_bits()],
+            Shape::DistanceToLine { start, end } => {
+                vec![
+                    3,
+                    start[0].to_bits(),
+                    start[1].to_bits(),
+                    end[0].to_bits(),
+                    end[1].to_bits(),
+                ]
+            }
+            Shape::RadialGradient {
+                center,
+                radius,
+                falloff,
+            } => vec![
+                4,
+                center[0].to_bits(),
+                center[1].to_bits(),
+                radius.to_bits(),
+                falloff.to_bits(),
+            ],
+            Shape::LinearGradient {
+                start,
+                end,
+                falloff,
+            } => vec![
+                5,
+                start[0].to_bits(),
+                start[1].to_bits(),
+                end[0].to_bits(),
+                end[1].to_bits(),
+                falloff.to_bits(),
+            ],
+        };
+        self.sample(Key("shape", args), Generator::Shape(shape), point)
+    }
+
+    fn sample(&mut self, mut key: Key, generator: Generator, point: PointRegister) -> Operand {
+        key.1.push(point as u64);
+        self.emit(key, |out| Instruction::Sample {
+            generator: Box::new(generator),
+            point,
+            out,
+        })
+    }
+
+    fn transform(&mut self, point: PointRegister, op: PointOp) -> PointRegister {
+        let args = match op {
+            PointOp::Translate([x, y]) => vec![0, x.to_bits(), y.to_bits()],
+            PointOp::Scale([x, y]) => vec![1, x.to_bits(), y.to_bits()],
+            PointOp::Divide(divisor) => vec![2, divisor.to_bits()],
+            PointOp::Rotate { cos, sin } => vec![3, cos.to_bits(), sin.to_bits()],
+        };
+        let mut key = Key("transform", args);
+        key.1.push(point as u64);
+        self.emit_point(key, |out| Instruction::Transform { op, point, out })
+    }
+
+    fn displace(&mut self, point: PointRegister, x: Operand, y: Operand) -> PointRegister {
+        let x = self.register(x);
+        let y = self.register(y);
+        self.emit_point(
+            Key("displace", vec![point as u64, x as u64, y as u64]),
+            |out| Instruction::Displace { point, x, y, out },
+        )
+    }
+
+    /// Makes sure an operand lives in a register, loading constants into one.
+    fn register(&mut self, operand: Operand) -> Register {
+        match operand {
+            Operand::Register(register) => register,
+            Operand::Constant(value) => {
+                *self.constants.entry(value.to_bits()).or_insert_with(|| {
+                    let register = self.program.value_registers;
+                    self.program.value_registers += 1;
+                    self.program.constants.push((register, value));
+                    register
+                })
+            }
+        }
+    }
+
+    fn push(&mut self, instruction: impl FnOnce(Register) -> Instruction) -> Register {
+        let out = self.program.value_registers;
+        self.program.value_registers += 1;
+        self.program.instructions.push(instruction(out));
+        out
+    }
+
+    fn emit(&mut self, key: Key, instruction: impl FnOnce(Register) -> Instruction) -> Operand {
+        if let Some(register) = self.values.get(&key) {
+            return Operand::Register(*register);
+        }
+
+        let register = self.push(instruction);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.values.push(key.clone());
+        }
+        self.values.insert(key, register);
+        Operand::Register(register)
+    }
+
+    fn emit_point(
+        &mut self,
+        key: Key,
+        instruction: impl FnOnce(PointRegister) -> Instruction,
+    ) -> PointRegister {
+        if let Some(register) = self.points.get(&key) {
+            return *register;
+        }
+
+        let out = self.program.point_registers;
+        self.program.point_registers += 1;
+        self.program.instructions.push(instruction(out));
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.points.push(key.clone());
+        }
+        self.points.insert(key, out);
+        out
+    }
+}
+
+/// Where the octaves of a fractal come from.
+#[derive(Clone, Copy)]
+enum OctaveSource {
+    /// Generators of a noise type, seeded from the given seed upwards.
+    Generator(NoiseType, u32),
+    /// The noise connected to the source input of the node.
+    Node(NodeId),
+}
+
+fn octave_generator(noise_type: NoiseType, seed: u32) -> Generator {
+    match noise_type {
+        NoiseType::Perlin => Generator::Perlin(Perlin::new(seed)),
+        NoiseType::Simplex => Generator::Simplex(Simplex::new(seed)),
+        NoiseType::OpenSimplex => Generator::OpenSimplex(OpenSimplex::new(seed)),
+        NoiseType::SuperSimplex => Generator::SuperSimplex(SuperSimplex::new(seed)),
+        NoiseType::Value => Generator::Value(Value::new(seed)),
+        NoiseType::Worley => Generator::Worley(SyncWorley::new(seed)),
+        NoiseType::PerlinSurflet => Generator::PerlinSurflet(PerlinSurflet::new(seed)),
+    }
+}
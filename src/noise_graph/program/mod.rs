@@ -0,0 +1,527 @@
+use std::cell::RefCell;
+
+use noise::{
+    Checkerboard, Cylinders, NoiseFn, OpenSimplex, Perlin, PerlinSurflet, Simplex, SuperSimplex,
+    Value,
+};
+
+use super::{
+    dsl::{Formula, MAX_VARIABLES},
+    node_template::{ErodedFbm, FractalKind, Shape, Shaping, SyncWorley, MAX_SOURCES},
+};
+
+pub use self::compiler::{compile, compile_input};
+
+mod compiler;
+
+/// Index of a register holding a noise value.
+pub type Register = usize;
+
+/// Index of a register holding a sample point. Register `0` always holds the
+/// point the program is evaluated at.
+pub type PointRegister = usize;
+
+/// A noise graph compiled into a flat list of instructions.
+///
+/// Every instruction reads the registers written by earlier instructions, so
+/// evaluating the program is a single pass over the list without any pointer
+/// chasing or virtual calls, apart from select branches which are skipped when
+/// they aren't selected. Shared subgraphs are only evaluated once per
+/// sample and constant parts of the graph are folded while compiling.
+#[derive(Clone)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+    constants: Vec<(Register, f64)>,
+    value_registers: usize,
+    point_registers: usize,
+    output: Operand,
+}
+
+/// Either a value which is already known while compiling, or the register it
+/// will be computed into.
+#[derive(Clone, Copy, Debug)]
+pub enum Operand {
+    Constant(f64),
+    Register(Register),
+}
+
+#[derive(Clone)]
+pub enum Instruction {
+    Sample {
+        generator: Box<Generator>,
+        point: PointRegister,
+        out: Register,
+    },
+    Unary {
+        op: UnaryOp,
+        source: Register,
+        out: Register,
+    },
+    Binary {
+        op: BinaryOp,
+        source_1: Register,
+        source_2: Register,
+        out: Register,
+    },
+    Blend {
+        source_1: Register,
+        source_2: Register,
+        control: Register,
+        out: Register,
+    },
+    /// Only runs the branches the control value selects, just like
+    /// `noise::Select` only samples the sources it needs.
+    Select {
+        source_1: Branch,
+        source_2: Branch,
+        control: Register,
+        bounds: (f64, f64),
+        falloff: f64,
+        out: Register,
+    },
+    Transform {
+        op: PointOp,
+        point: PointRegister,
+        out: PointRegister,
+    },
+    Displace {
+        point: PointRegister,
+        x: Register,
+        y: Register,
+        out: PointRegister,
+    },
+    /// Combines the octaves of a fractal, which are sampled by the
+    /// instructions before it.
+    Fractal {
+        kind: FractalKind,
+        persistence: f64,
+        octaves: Vec<Register>,
+        out: Register,
+    },
+    Shaping {
+        shaping: Shaping,
+        sources: Vec<Register>,
+        out: Register,
+    },
+    Expression {
+        formula: Box<Formula>,
+        inputs: Vec<Register>,
+        point: PointRegister,
+        out: Register,
+    },
+}
+
+/// Instructions which are only run on demand. Their registers are never read
+/// from outside of the branch.
+#[derive(Clone)]
+pub struct Branch {
+    instructions: Vec<Instruction>,
+    result: Register,
+}
+
+/// Which sources of a select contribute to its output.
+#[derive(Clone, Copy)]
+enum Selection {
+    Source1,
+    Source2,
+    /// Interpolates from `from` to the other source.
+    Blend {
+        from: usize,
+        alpha: f64,
+    },
+}
+
+/// The noise functions which produce values from a sample point alone.
+#[derive(Clone)]
+pub enum Generator {
+    Checkerboard(Checkerboard),
+    Cylinders(Cylinders),
+    OpenSimplex(OpenSimplex),
+    Perlin(Perlin),
+    PerlinSurflet(PerlinSurflet),
+    Simplex(Simplex),
+    SuperSimplex(SuperSimplex),
+    Value(Value),
+    Worley(SyncWorley),
+    ErodedFbm(ErodedFbm),
+    Shape(Shape),
+}
+
+#[derive(Clone, Debug)]
+pub enum UnaryOp {
+    Abs,
+    Negate,
+    Clamp(f64, f64),
+    Exponent(f64),
+    ScaleBias(f64, f64),
+    /// The output correction of the `Scale` node.
+    Scale(f64),
+    /// Control points sorted by input, without duplicates.
+    Curve(Vec<(f64, f64)>),
+    /// Control points sorted and without duplicates.
+    Terrace(Vec<f64>),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum BinaryOp {
+    Add,
+    Multiply,
+    Min,
+    Max,
+    Power,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum PointOp {
+    Translate([f64; 2]),
+    Scale([f64; 2]),
+    /// The input correction of the `Scale` node.
+    Divide(f64),
+    Rotate {
+        cos: f64,
+        sin: f64,
+    },
+}
+
+#[derive(Default)]
+struct Registers {
+    values: Vec<f64>,
+    points: Vec<[f64; 2]>,
+}
+
+thread_local! {
+    static REGISTERS: RefCell<Registers> = RefCell::default();
+}
+
+impl Program {
+    fn execute(&self, values: &mut [f64], points: &mut [[f64; 2]], point: [f64; 2]) -> f64 {
+        for &(register, value) in &self.constants {
+            values[register] = value;
+        }
+        points[0] = point;
+
+        run(&self.instructions, values, points);
+
+        match self.output {
+            Operand::Constant(value) => value,
+            Operand::Register(register) => values[register],
+        }
+    }
+}
+
+impl Program {
+    /// Small programs keep their registers on the stack.
+    fn execute_on_stack<const N: usize>(&self, point: [f64; 2]) -> f64 {
+        let mut values = [0.0; N];
+        let mut points = [[0.0; 2]; N];
+        self.execute(&mut values, &mut points, point)
+    }
+}
+
+impl NoiseFn<f64, 2> for Program {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        if let Operand::Constant(value) = self.output {
+            return value;
+        }
+
+        let registers = self.value_registers.max(self.point_registers);
+        if registers <= 8 {
+            return self.execute_on_stack::<8>(point);
+        }
+        if registers <= 32 {
+            return self.execute_on_stack::<32>(point);
+        }
+
+        REGISTERS.with(|registers| {
+            let Registers { values, points } = &mut *registers.borrow_mut();
+            values.resize(self.value_registers, 0.0);
+            points.resize(self.point_registers, [0.0; 2]);
+            self.execute(values, points, point)
+        })
+    }
+}
+
+fn run(instructions: &[Instruction], values: &mut [f64], points: &mut [[f64; 2]]) {
+    for instruction in instructions {
+        match instruction {
+            Instruction::Sample {
+                generator,
+                point,
+                out,
+            } => values[*out] = generator.get(points[*point]),
+            Instruction::Unary { op, source, out } => values[*out] = op.apply(values[*source]),
+            Instruction::Binary {
+                op,
+                source_1,
+                source_2,
+                out,
+            } => values[*out] = op.apply(values[*source_1], values[*source_2]),
+            Instruction::Blend {
+                source_1,
+                source_2,
+                control,
+                out,
+            } => values[*out] = blend(values[*source_1], values[*source_2], values[*control]),
+            Instruction::Select {
+                source_1,
+                source_2,
+                control,
+                bounds,
+                falloff,
+                out,
+            } => {
+                let selected = selection(values[*control], *bounds, *falloff);
+                let sources = [source_1, source_2];
+                let mut sample = |source: usize| {
+                    let branch = sources[source];
+                    run(&branch.instructions, values, points);
+                    values[branch.result]
+                };
+                let value = match selected {
+                    Selection::Source1 => sample(0),
+                    Selection::Source2 => sample(1),
+                    Selection::Blend { from, alpha } => {
+                        let from_value = sample(from);
+                        let to_value = sample(1 - from);
+                        linear(from_value, to_value, alpha)
+                    }
+                };
+                values[*out] = value;
+            }
+            Instruction::Transform { op, point, out } => points[*out] = op.apply(points[*point]),
+            Instruction::Displace { point, x, y, out } => {
+                let [px, py] = points[*point];
+                points[*out] = [px + values[*x], py + values[*y]];
+            }
+            Instruction::Fractal {
+                kind,
+                persistence,
+                octaves,
+                out,
+            } => {
+                let value =
+                    kind.combine(*persistence, octaves.iter().map(|octave| values[*octave]));
+                values[*out] = value;
+            }
+            Instruction::Shaping {
+                shaping,
+                sources,
+                out,
+            } => {
+                let mut arguments = [0.0; MAX_SOURCES];
+                for (argument, source) in arguments.iter_mut().zip(sources) {
+                    *argument = values[*source];
+                }
+                values[*out] = shaping.apply(&arguments[..sources.len()]);
+            }
+            Instruction::Expression {
+                formula,
+                inputs,
+                point,
+                out,
+            } => {
+                let mut variables = [0.0; MAX_VARIABLES];
+                for (variable, input) in variables.iter_mut().zip(inputs) {
+                    *variable = values[*input];
+                }
+                values[*out] = formula.evaluate(&variables[..inputs.len()], points[*point]);
+            }
+        }
+    }
+}
+
+impl Generator {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        match self {
+            Generator::Checkerboard(noise) => noise.get(point),
+            Generator::Cylinders(noise) => noise.get(point),
+            Generator::OpenSimplex(noise) => noise.get(point),
+            Generator::Perlin(noise) => noise.get(point),
+            Generator::PerlinSurflet(noise) => noise.get(point),
+            Generator::Simplex(noise) => noise.get(point),
+            Generator::SuperSimplex(noise) => noise.get(point),
+            Generator::Value(noise) => noise.get(point),
+            Generator::Worley(noise) => noise.get(point),
+            Generator::ErodedFbm(noise) => noise.get(point),
+            Generator::Shape(noise) => noise.get(point),
+        }
+    }
+}
+
+// The operations below mirror the implementations in the `noise` crate
+// operation for operation, so compiled programs produce exactly the same
+// values as the nested noise functions.
+
+impl UnaryOp {
+    pub fn apply(&self, value: f64) -> f64 {
+        match self {
+            UnaryOp::Abs => value.abs(),
+            UnaryOp::Negate => -value,
+            UnaryOp::Clamp(lower, upper) => value.clamp(*lower, *upper),
+            UnaryOp::Exponent(exponent) => {
+                let value = ((value + 1.0) / 2.0).abs().powf(*exponent);
+                value.abs().mul_add(2.0, -1.0)
+            }
+            UnaryOp::ScaleBias(scale, bias) => value.mul_add(*scale, *bias),
+            UnaryOp::Scale(scale) => value * scale / 2.0,
+            UnaryOp::Curve(control_points) => curve(control_points, value),
+            UnaryOp::Terrace(control_points) => terrace(control_points, value),
+        }
+    }
+}
+
+impl BinaryOp {
+    pub fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            BinaryOp::Add => a + b,
+            BinaryOp::Multiply => a * b,
+            BinaryOp::Min => a.min(b),
+            BinaryOp::Max => a.max(b),
+            BinaryOp::Power => a.powf(b),
+        }
+    }
+}
+
+impl PointOp {
+    pub fn apply(self, [x, y]: [f64; 2]) -> [f64; 2] {
+        match self {
+            PointOp::Translate([tx, ty]) => [x + tx, y + ty],
+            PointOp::Scale([sx, sy]) => [x * sx, y * sy],
+            PointOp::Divide(divisor) => [x / divisor, y / divisor],
+            PointOp::Rotate { cos, sin } => [x * cos - y * sin, x * sin + y * cos],
+        }
+    }
+}
+
+fn linear(a: f64, b: f64, alpha: f64) -> f64 {
+    alpha.mul_add(b - a, a)
+}
+
+fn map_cubic(value: f64) -> f64 {
+    let x = value.clamp(0.0, 1.0);
+    x * x * (3.0 - (x * 2.0))
+}
+
+fn blend(source_1: f64, source_2: f64, control: f64) -> f64 {
+    linear(source_1, source_2, control)
+}
+
+/// Decides which sources a select samples for a control value.
+fn selection(control: f64, (lower, upper): (f64, f64), falloff: f64) -> Selection {
+    if falloff > 0.0 {
+        if control < lower - falloff {
+            Selection::Source1
+        } else if control < lower + falloff {
+            let lower_curve = lower - falloff;
+            let upper_curve = lower + falloff;
+            Selection::Blend {
+                from: 0,
+                alpha: map_cubic((control - lower_curve) / (upper_curve - lower_curve)),
+            }
+        } else if control < upper - falloff {
+            Selection::Source2
+        } else if control < upper + falloff {
+            let lower_curve = upper - falloff;
+            let upper_curve = upper + falloff;
+            Selection::Blend {
+                from: 1,
+                alpha: map_cubic((control - lower_curve) / (upper_curve - lower_curve)),
+            }
+        } else {
+            Selection::Source1
+        }
+    } else if control < lower || control > upper {
+        Selection::Source1
+    } else {
+        Selection::Source2
+    }
+}
+
+/// Sorts the control points of a curve by their input and drops points with
+/// the input of an earlier one, like `noise::Curve` does when adding them.
+pub fn sort_curve_points(control_points: impl IntoIterator<Item = (f64, f64)>) -> Vec<(f64, f64)> {
+    let mut sorted: Vec<(f64, f64)> = Vec::new();
+    for (input, output) in control_points {
+        if !sorted.iter().any(|(x, _)| (x - input).abs() < f64::EPSILON) {
+            let index = sorted
+                .iter()
+                .position(|(x, _)| *x >= input)
+                .unwrap_or(sorted.len());
+            sorted.insert(index, (input, output));
+        }
+    }
+    sorted
+}
+
+/// Sorts the control points of a terrace and drops duplicates, like
+/// `noise::Terrace` does when adding them.
+pub fn sort_terrace_points(control_points: impl IntoIterator<Item = f64>) -> Vec<f64> {
+    let mut sorted: Vec<f64> = Vec::new();
+    for control_point in control_points {
+        if !sorted
+            .iter()
+            .any(|x| (x - control_point).abs() < f64::EPSILON)
+        {
+            let index = sorted
+                .iter()
+                .position(|x| *x >= control_point)
+                .unwrap_or(sorted.len());
+            sorted.insert(index, control_point);
+        }
+    }
+    sorted
+}
+
+fn curve(control_points: &[(f64, f64)], value: f64) -> f64 {
+    let last = control_points.len() - 1;
+    let index_pos = control_points
+        .iter()
+        .position(|(input, _)| *input > value)
+        .unwrap_or(control_points.len())
+        .clamp(2, control_points.len());
+
+    let index0 = (index_pos - 2).clamp(0, last);
+    let index1 = (index_pos - 1).clamp(0, last);
+    let index2 = index_pos.clamp(0, last);
+    let index3 = (index_pos + 1).clamp(0, last);
+
+    if index1 == index2 {
+        return control_points[index1].1;
+    }
+
+    let input0 = control_points[index1].0;
+    let input1 = control_points[index2].0;
+    let alpha = (value - input0) / (input1 - input0);
+
+    let n0 = control_points[index0].1;
+    let n1 = control_points[index1].1;
+    let n2 = control_points[index2].1;
+    let n3 = control_points[index3].1;
+    let p = (n3 - n2) - (n0 - n1);
+    let q = (n0 - n1) - p;
+    let r = n2 - n0;
+    p * alpha * alpha * alpha + q * alpha * alpha + r * alpha + n1
+}
+
+fn terrace(control_points: &[f64], value: f64) -> f64 {
+    let last = control_points.len() as isize - 1;
+    let index_pos = control_points
+        .iter()
+        .position(|&point| point >= value)
+        .unwrap_or(control_points.len()) as isize;
+
+    let index0 = (index_pos - 1).clamp(0, last) as usize;
+    let index1 = index_pos.clamp(0, last) as usize;
+
+    if index0 == index1 {
+        return control_points[index1];
+    }
+
+    let input0 = control_points[index0];
+    let input1 = control_points[index1];
+    let alpha = (value - input0) / (input1 - input0);
+    linear(input0, input1, alpha * alpha)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,409 @@
+use egui_node_graph::{NodeId, NodeTemplateTrait};
+use noise::NoiseFn;
+
+use super::{compile, Instruction, Operand, Program};
+use crate::noise_graph::{
+    graph_ext::{EvaluationCache, GraphExt},
+    node_attribute::{NodeAttribute, NoiseType, Operator, WorleyReturnType},
+    node_template::NodeTemplate,
+    NoiseGraph, NoiseGraphState,
+};
+
+/// Builds graphs node by node, the way the node finder does.
+#[derive(Default)]
+struct TestGraph {
+    graph: NoiseGraph,
+    user_state: NoiseGraphState,
+}
+
+impl TestGraph {
+    /// Adds a node with the given constant input values.
+    fn node(&mut self, template: NodeTemplate, values: &[(&str, NodeAttribute)]) -> NodeId {
+        let user_state = &mut self.user_state;
+        let node_id = self.graph.add_node(
+            String::new(),
+            template.user_data(user_state),
+            |graph, node_id| template.build_node(graph, user_state, node_id),
+        );
+        for (name, value) in values {
+            let input_id = self.graph[node_id].get_input(name).unwrap();
+            self.graph[input_id].value = value.clone();
+        }
+        node_id
+    }
+
+    /// Adds a node and connects the given nodes to its inputs.
+    fn apply(
+        &mut self,
+        template: NodeTemplate,
+        sources: &[(&str, NodeId)],
+        values: &[(&str, NodeAttribute)],
+    ) -> NodeId {
+        let node_id = self.node(template, values);
+        for (name, source) in sources {
+            let output_id = self.graph[*source].get_output("out").unwrap();
+            let input_id = self.graph[node_id].get_input(name).unwrap();
+            self.graph.add_connection(output_id, input_id);
+        }
+        node_id
+    }
+
+    /// Sums up the nodes, which makes a single output of the nodes of a test.
+    fn sum(&mut self, nodes: &[NodeId]) -> NodeId {
+        nodes[1..].iter().fold(nodes[0], |sum, node_id| {
+            self.apply(
+                NodeTemplate::Add,
+                &[("source 1", sum), ("source 2", *node_id)],
+                &[],
+            )
+        })
+    }
+
+    /// Asserts that the compiled program of the node gives exactly the values
+    /// of the evaluated graph, and returns the program.
+    fn assert_same(&self, node_id: NodeId) -> Program {
+        let program = compile(&self.graph, node_id).unwrap();
+        let noise = self
+            .graph
+            .evaluate_cached(node_id, &mut EvaluationCache::default())
+            .and_then(|value| value.try_to_noise_function())
+            .unwrap();
+
+        for point in points() {
+            let expected = noise.get(point);
+            let actual = program.get(point);
+            assert!(
+                expected.to_bits() == actual.to_bits(),
+                "{actual} instead of {expected} at {point:?}"
+            );
+        }
+        program
+    }
+}
+
+/// Points spread over positive and negative coordinates, away from the
+/// lattice of the generators.
+fn points() -> impl Iterator<Item = [f64; 2]> {
+    (-16..16)
+        .flat_map(|x| (-16..16).map(move |y| [x as f64 * 13.37 + 0.21, y as f64 * 9.71 - 0.43]))
+}
+
+fn f64(value: f64) -> NodeAttribute {
+    NodeAttribute::F64(value)
+}
+
+/// Counts the instructions the predicate holds for, including the ones in
+/// select branches.
+fn count(instructions: &[Instruction], predicate: &impl Fn(&Instruction) -> bool) -> usize {
+    instructions
+        .iter()
+        .map(|instruction| {
+            let nested = match instruction {
+                Instruction::Select {
+                    source_1, source_2, ..
+                } => {
+                    count(&source_1.instructions, predicate)
+                        + count(&source_2.instructions, predicate)
+                }
+                _ => 0,
+            };
+            nested + usize::from(predicate(instruction))
+        })
+        .sum()
+}
+
+fn samples(program: &Program) -> usize {
+    count(&program.instructions, &|instruction| {
+        matches!(instruction, Instruction::Sample { .. })
+    })
+}
+
+#[test]
+fn generators() {
+    let mut test = TestGraph::default();
+    let nodes = [
+        test.node(NodeTemplate::Perlin, &[]),
+        test.node(NodeTemplate::Simplex, &[]),
+        test.node(NodeTemplate::OpenSimplex, &[]),
+        test.node(NodeTemplate::SuperSimplex, &[]),
+        test.node(NodeTemplate::Value, &[]),
+        test.node(NodeTemplate::PerlinSurflet, &[]),
+        test.node(
+            NodeTemplate::Checkerboard,
+            &[("size", NodeAttribute::Usize(2))],
+        ),
+        test.node(NodeTemplate::Cylinders, &[("frequency", f64(0.5))]),
+        test.node(NodeTemplate::Worley, &[("frequency", f64(0.05))]),
+        test.node(
+            NodeTemplate::Worley,
+            &[
+                ("frequency", f64(0.02)),
+                (
+                    "return type",
+                    NodeAttribute::ReturnType(WorleyReturnType::Value),
+                ),
+            ],
+        ),
+    ];
+    let out = test.sum(&nodes);
+    test.assert_same(out);
+}
+
+#[test]
+fn modifiers() {
+    let mut test = TestGraph::default();
+    let s = test.node(NodeTemplate::Fbm, &[("frequency", f64(0.02))]);
+    let points = [
+        (-1.0, -1.0),
+        (-0.5, 0.2),
+        (0.0, 0.1),
+        (0.6, 0.8),
+        (1.0, 1.0),
+    ];
+    let nodes = [
+        test.apply(NodeTemplate::Abs, &[("source", s)], &[]),
+        test.apply(NodeTemplate::Negate, &[("source", s)], &[]),
+        test.apply(
+            NodeTemplate::Clamp,
+            &[("source", s)],
+            &[("bounds lower", f64(-0.3)), ("bounds upper", f64(0.4))],
+        ),
+        test.apply(
+            NodeTemplate::Exponent,
+            &[("source", s)],
+            &[("exponent", f64(1.7))],
+        ),
+        test.apply(
+            NodeTemplate::ScaleBias,
+            &[("source", s)],
+            &[("scale", f64(0.7)), ("bias", f64(0.1))],
+        ),
+        test.apply(
+            NodeTemplate::Scale,
+            &[("source", s)],
+            &[("scale", f64(3.0))],
+        ),
+        test.apply(
+            NodeTemplate::Curve,
+            &[("source", s)],
+            &[(
+                "control points",
+                NodeAttribute::Vec {
+                    values: points
+                        .into_iter()
+                        .map(|(x, y)| NodeAttribute::F64Tuple(x, y))
+                        .collect(),
+                    template: Box::new(NodeAttribute::F64Tuple(0.0, 0.0)),
+                },
+            )],
+        ),
+        test.apply(
+            NodeTemplate::Terrace,
+            &[("source", s)],
+            &[(
+                "control points",
+                NodeAttribute::Vec {
+                    values: [-1.0, -0.3, 0.2, 1.0].into_iter().map(f64).collect(),
+                    template: Box::new(f64(0.0)),
+                },
+            )],
+        ),
+    ];
+    let out = test.sum(&nodes);
+    test.assert_same(out);
+}
+
+#[test]
+fn combinations() {
+    let mut test = TestGraph::default();
+    let a = test.node(NodeTemplate::Perlin, &[]);
+    let b = test.node(NodeTemplate::Billow, &[("frequency", f64(0.03))]);
+    let c = test.node(NodeTemplate::Simplex, &[]);
+    let sources = [("source 1", a), ("source 2", b)];
+    let nodes = [
+        test.apply(NodeTemplate::Multiply, &sources, &[]),
+        test.apply(NodeTemplate::Min, &sources, &[]),
+        test.apply(NodeTemplate::Max, &sources, &[]),
+        test.apply(NodeTemplate::Power, &sources, &[]),
+        test.apply(
+            NodeTemplate::Blend,
+            &[sources[0], sources[1], ("control", c)],
+            &[],
+        ),
+        test.apply(
+            NodeTemplate::Select,
+            &[sources[0], sources[1], ("control", c)],
+            &[
+                ("bounds lower", f64(-0.2)),
+                ("bounds upper", f64(0.4)),
+                ("falloff", f64(0.1)),
+            ],
+        ),
+    ];
+    let out = test.sum(&nodes);
+    test.assert_same(out);
+}
+
+#[test]
+fn transforms() {
+    let mut test = TestGraph::default();
+    let perlin = test.node(NodeTemplate::Perlin, &[]);
+    let translated = test.apply(
+        NodeTemplate::TranslatePoint,
+        &[("source", perlin)],
+        &[("x", f64(0.3)), ("y", f64(-1.2))],
+    );
+    let scaled = test.apply(
+        NodeTemplate::ScalePoint,
+        &[("source", translated)],
+        &[("x", f64(0.15)), ("y", f64(0.07))],
+    );
+    let rotated = test.apply(
+        NodeTemplate::RotatePoint,
+        &[("source", scaled)],
+        &[("z angle", f64(30.0))],
+    );
+    let cached = test.apply(NodeTemplate::Cache, &[("source", rotated)], &[]);
+
+    let displaced = test.node(NodeTemplate::Simplex, &[]);
+    let x = test.node(NodeTemplate::Value, &[]);
+    let y = test.node(NodeTemplate::PerlinSurflet, &[]);
+    let constant = test.node(NodeTemplate::Constant, &[]);
+    let displace = test.apply(
+        NodeTemplate::Displace,
+        &[
+            ("source", displaced),
+            ("x", x),
+            ("y", y),
+            ("z", constant),
+            ("u", constant),
+        ],
+        &[],
+    );
+    let turbulence = test.apply(
+        NodeTemplate::Turbulence,
+        &[("source", perlin)],
+        &[("frequency", f64(0.4)), ("power", f64(2.0))],
+    );
+
+    let out = test.sum(&[cached, displace, turbulence]);
+    test.assert_same(out);
+}
+
+#[test]
+fn fractals() {
+    let mut test = TestGraph::default();
+    let simplex = NodeAttribute::NoiseType(NoiseType::Simplex);
+    let nodes = [
+        test.node(
+            NodeTemplate::Fbm,
+            &[
+                ("octaves", NodeAttribute::Usize(1)),
+                ("frequency", f64(0.09)),
+            ],
+        ),
+        test.node(
+            NodeTemplate::Billow,
+            &[
+                ("noise type", simplex.clone()),
+                ("octaves", NodeAttribute::Usize(12)),
+                ("persistence", f64(0.7)),
+            ],
+        ),
+        test.node(NodeTemplate::BasicMulti, &[("lacunarity", f64(1.9))]),
+        test.node(
+            NodeTemplate::HybridMulti,
+            &[
+                ("noise type", simplex.clone()),
+                ("octaves", NodeAttribute::Usize(6)),
+            ],
+        ),
+        test.node(NodeTemplate::RidgedMulti, &[("noise type", simplex)]),
+    ];
+    let out = test.sum(&nodes);
+    test.assert_same(out);
+}
+
+#[test]
+fn shared_subgraphs_are_sampled_once() {
+    let mut test = TestGraph::default();
+    let s = test.node(NodeTemplate::Perlin, &[]);
+    let square = test.apply(
+        NodeTemplate::Multiply,
+        &[("source 1", s), ("source 2", s)],
+        &[],
+    );
+    let abs = test.apply(NodeTemplate::Abs, &[("source", s)], &[]);
+    let translated = [(); 2].map(|_| {
+        test.apply(
+            NodeTemplate::TranslatePoint,
+            &[("source", s)],
+            &[("x", f64(3.0))],
+        )
+    });
+    let out = test.sum(&[square, abs, translated[0], translated[1]]);
+    let program = test.assert_same(out);
+    // Once at the sample point and once at the translated one.
+    assert_eq!(samples(&program), 2);
+}
+
+#[test]
+fn numbers_are_folded() {
+    let mut test = TestGraph::default();
+    let a = test.node(NodeTemplate::Float, &[("value", f64(3.0))]);
+    let b = test.apply(
+        NodeTemplate::Arithmetic,
+        &[("a", a)],
+        &[
+            ("operator", NodeAttribute::Operator(Operator::Multiply)),
+            ("b", f64(0.5)),
+        ],
+    );
+    let constant = test.apply(NodeTemplate::Constant, &[("value", b)], &[]);
+    let perlin = test.node(NodeTemplate::Perlin, &[]);
+    let scaled = test.apply(
+        NodeTemplate::Scale,
+        &[("source", perlin)],
+        &[("scale", f64(2.0))],
+    );
+    let biased = test.apply(
+        NodeTemplate::ScaleBias,
+        &[("source", constant)],
+        &[("scale", f64(2.0)), ("bias", f64(0.5))],
+    );
+    let out = test.sum(&[scaled, biased]);
+    test.assert_same(out);
+
+    let program = test.assert_same(biased);
+    assert!(matches!(program.output, Operand::Constant(value) if value == 3.5));
+    assert!(program.instructions.is_empty());
+}
+
+#[test]
+fn constant_selects_only_keep_the_selected_branch() {
+    let mut test = TestGraph::default();
+    let a = test.node(NodeTemplate::Perlin, &[]);
+    let b = test.node(NodeTemplate::Simplex, &[]);
+    let inside = test.node(NodeTemplate::Constant, &[("value", f64(0.9))]);
+    let select = test.apply(
+        NodeTemplate::Select,
+        &[("source 1", a), ("source 2", b), ("control", inside)],
+        &[],
+    );
+    let program = test.assert_same(select);
+    let selects = count(&program.instructions, &|instruction| {
+        matches!(instruction, Instruction::Select { .. })
+    });
+    assert_eq!(selects, 0);
+    assert_eq!(samples(&program), 1);
+
+    // Within the falloff both sources are blended.
+    let near = test.node(NodeTemplate::Constant, &[("value", f64(0.05))]);
+    let select = test.apply(
+        NodeTemplate::Select,
+        &[("source 1", a), ("source 2", b), ("control", near)],
+        &[("falloff", f64(0.1))],
+    );
+    let program = test.assert_same(select);
+    assert_eq!(samples(&program), 2);
+}
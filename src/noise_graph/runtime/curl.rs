@@ -0,0 +1,26 @@
+use noise::NoiseFn;
+
+use super::{dyn_fn::VectorFn, gradient::Gradient};
+
+/// The curl of a noise function, its gradient turned a quarter to the left.
+/// It flows along the contour lines without ever converging, which makes for
+/// swirling warps.
+#[derive(Clone)]
+pub struct Curl<T> {
+    gradient: Gradient<T>,
+}
+
+impl<T> Curl<T> {
+    pub fn new(source: T, step: f64) -> Self {
+        Self {
+            gradient: Gradient::new(source, step),
+        }
+    }
+}
+
+impl<T: NoiseFn<f64, 2>> VectorFn for Curl<T> {
+    fn get(&self, point: [f64; 2]) -> [f64; 2] {
+        let [x, y] = self.gradient.get(point);
+        [-y, x]
+    }
+}
@@ -0,0 +1,46 @@
+use noise::NoiseFn;
+
+use super::dyn_fn::VectorFn;
+
+/// The most iterations a domain warp does, each one samples the vector field
+/// once more.
+pub const MAX_WARP_ITERATIONS: usize = 8;
+
+/// Samples a noise function at points offset by a vector field times the
+/// strength. Every further iteration samples the field at the previously
+/// warped point instead, so the offsets get warped as well.
+#[derive(Clone)]
+pub struct DomainWarp<S, W> {
+    source: S,
+    warp: W,
+    strength: f64,
+    iterations: usize,
+}
+
+impl<S, W> DomainWarp<S, W> {
+    /// At most [`MAX_WARP_ITERATIONS`] are done, no iterations sample the
+    /// source at the point itself.
+    pub fn new(source: S, warp: W, strength: f64, iterations: usize) -> Self {
+        Self {
+            source,
+            warp,
+            strength,
+            iterations: iterations.min(MAX_WARP_ITERATIONS),
+        }
+    }
+}
+
+impl<S, W> NoiseFn<f64, 2> for DomainWarp<S, W>
+where
+    S: NoiseFn<f64, 2>,
+    W: VectorFn,
+{
+    fn get(&self, point: [f64; 2]) -> f64 {
+        let mut warped = point;
+        for _ in 0..self.iterations {
+            let [x, y] = self.warp.get(warped);
+            warped = [point[0] + x * self.strength, point[1] + y * self.strength];
+        }
+        self.source.get(warped)
+    }
+}
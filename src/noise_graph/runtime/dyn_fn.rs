@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use noise::NoiseFn;
+
+#[derive(Clone)]
+pub struct DynNoiseFn(Arc<dyn NoiseFn<f64, 2> + Send + Sync>);
+
+/// A field of 2D vectors, which gives a vector for every point like a noise
+/// function gives a value.
+pub trait VectorFn {
+    fn get(&self, point: [f64; 2]) -> [f64; 2];
+}
+
+#[derive(Clone)]
+pub struct DynVectorFn(Arc<dyn VectorFn + Send + Sync>);
+
+impl DynNoiseFn {
+    pub fn new<T: NoiseFn<f64, 2> + Send + Sync + 'static>(noise: T) -> Self {
+        Self(Arc::new(noise))
+    }
+}
+
+impl NoiseFn<f64, 2> for DynNoiseFn {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        self.0.get(point)
+    }
+}
+
+impl DynVectorFn {
+    pub fn new<T: VectorFn + Send + Sync + 'static>(vector: T) -> Self {
+        Self(Arc::new(vector))
+    }
+}
+
+impl VectorFn for DynVectorFn {
+    fn get(&self, point: [f64; 2]) -> [f64; 2] {
+        self.0.get(point)
+    }
+}
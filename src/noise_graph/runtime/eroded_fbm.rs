@@ -0,0 +1,170 @@
+use std::f64::consts::SQRT_2;
+
+use noise::{
+    permutationtable::{NoiseHasher, PermutationTable},
+    NoiseFn, Seedable,
+};
+
+use super::fractal::clamp_octaves;
+
+/// Fractal gradient noise where the slope accumulated over the previous
+/// octaves damps the octaves on top of it. Steep flanks stay smooth while
+/// detail gathers on ridges and in valleys, which looks eroded without
+/// simulating any erosion.
+///
+/// See <https://iquilezles.org/articles/morenoise/>.
+#[derive(Clone)]
+pub struct ErodedFbm {
+    octaves: usize,
+    gain: f64,
+    lacunarity: f64,
+    gradient_strength: f64,
+    seed: u32,
+    perm_table: PermutationTable,
+}
+
+impl ErodedFbm {
+    pub const DEFAULT_OCTAVES: usize = 8;
+    pub const DEFAULT_GAIN: f64 = 0.5;
+    pub const DEFAULT_LACUNARITY: f64 = 2.0;
+    pub const DEFAULT_GRADIENT_STRENGTH: f64 = 1.0;
+
+    pub fn new(seed: u32) -> Self {
+        Self {
+            octaves: Self::DEFAULT_OCTAVES,
+            gain: Self::DEFAULT_GAIN,
+            lacunarity: Self::DEFAULT_LACUNARITY,
+            gradient_strength: Self::DEFAULT_GRADIENT_STRENGTH,
+            seed,
+            perm_table: PermutationTable::new(seed),
+        }
+    }
+
+    /// Sets the number of octaves, clamped like the octaves of the other
+    /// fractals.
+    pub fn set_octaves(self, octaves: usize) -> Self {
+        Self {
+            octaves: clamp_octaves(octaves),
+            ..self
+        }
+    }
+
+    /// Sets the factor the amplitude shrinks by from one octave to the next.
+    pub fn set_gain(self, gain: f64) -> Self {
+        Self { gain, ..self }
+    }
+
+    pub fn set_lacunarity(self, lacunarity: f64) -> Self {
+        Self { lacunarity, ..self }
+    }
+
+    /// Sets how strongly the slope damps higher octaves. Zero gives plain fbm.
+    pub fn set_gradient_strength(self, gradient_strength: f64) -> Self {
+        Self {
+            gradient_strength,
+            ..self
+        }
+    }
+}
+
+impl Default for ErodedFbm {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Seedable for ErodedFbm {
+    fn set_seed(self, seed: u32) -> Self {
+        if self.seed == seed {
+            return self;
+        }
+
+        Self {
+            perm_table: PermutationTable::new(seed),
+            seed,
+            ..self
+        }
+    }
+
+    fn seed(&self) -> u32 {
+        self.seed
+    }
+}
+
+impl NoiseFn<f64, 2> for ErodedFbm {
+    fn get(&self, mut point: [f64; 2]) -> f64 {
+        let mut result = 0.0;
+        let mut amplitude = 1.0;
+        let mut amplitudes = 0.0;
+        let mut slope = [0.0, 0.0];
+
+        for _ in 0..self.octaves {
+            let (value, [dx, dy]) = gradient_noise(&self.perm_table, point);
+            slope = [slope[0] + dx, slope[1] + dy];
+            let damping =
+                1.0 + self.gradient_strength * (slope[0] * slope[0] + slope[1] * slope[1]);
+            result += amplitude * value / damping;
+            amplitudes += amplitude;
+            amplitude *= self.gain;
+
+            // Rotating every octave keeps the grids of the octaves from
+            // lining up.
+            let [x, y] = point;
+            point = [
+                (0.8 * x - 0.6 * y) * self.lacunarity,
+                (0.6 * x + 0.8 * y) * self.lacunarity,
+            ];
+        }
+
+        result / amplitudes
+    }
+}
+
+/// Perlin style gradient noise along with its partial derivatives.
+fn gradient_noise(hasher: &impl NoiseHasher, [x, y]: [f64; 2]) -> (f64, [f64; 2]) {
+    let (floor_x, floor_y) = (x.floor(), y.floor());
+    let (fx, fy) = (x - floor_x, y - floor_y);
+    let corner = [floor_x as isize, floor_y as isize];
+    let gradient = |offset_x: isize, offset_y: isize| match hasher
+        .hash(&[corner[0] + offset_x, corner[1] + offset_y])
+        & 0b11
+    {
+        0 => [1.0, 1.0],
+        1 => [-1.0, 1.0],
+        2 => [1.0, -1.0],
+        _ => [-1.0, -1.0],
+    };
+
+    let ga = gradient(0, 0);
+    let gb = gradient(1, 0);
+    let gc = gradient(0, 1);
+    let gd = gradient(1, 1);
+    let va = ga[0] * fx + ga[1] * fy;
+    let vb = gb[0] * (fx - 1.0) + gb[1] * fy;
+    let vc = gc[0] * fx + gc[1] * (fy - 1.0);
+    let vd = gd[0] * (fx - 1.0) + gd[1] * (fy - 1.0);
+
+    let (ux, uy) = (quintic(fx), quintic(fy));
+    let k = va - vb - vc + vd;
+    let value = va + ux * (vb - va) + uy * (vc - va) + ux * uy * k;
+    let derivative = |axis: usize, du: f64, u_other: f64, v_next: f64| {
+        ga[axis]
+            + ux * (gb[axis] - ga[axis])
+            + uy * (gc[axis] - ga[axis])
+            + ux * uy * (ga[axis] - gb[axis] - gc[axis] + gd[axis])
+            + du * (u_other * k + v_next - va)
+    };
+    let dx = derivative(0, quintic_derivative(fx), uy, vb);
+    let dy = derivative(1, quintic_derivative(fy), ux, vc);
+
+    // Scales the values to about [-1, 1], like `noise::Perlin` does.
+    (value * SQRT_2, [dx * SQRT_2, dy * SQRT_2])
+}
+
+fn quintic(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn quintic_derivative(t: f64) -> f64 {
+    30.0 * t * t * (t * (t - 2.0) + 1.0)
+}
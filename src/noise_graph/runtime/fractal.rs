@@ -0,0 +1,148 @@
+use noise::NoiseFn;
+
+/// Sums octaves of arbitrary noise functions, each sampled at a higher
+/// frequency than the previous one.
+///
+/// The fractals of the `noise` crate only work with their own generators as
+/// octaves. This does the same math operation for operation, so a fractal over
+/// `Perlin` octaves seeded `0, 1, 2, ...` gives exactly the same values as the
+/// corresponding `noise` fractal.
+#[derive(Clone)]
+pub struct Fractal<Source> {
+    kind: FractalKind,
+    sources: Vec<Source>,
+    frequency: f64,
+    lacunarity: f64,
+    persistence: f64,
+}
+
+/// How the octaves of a [`Fractal`] are combined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FractalKind {
+    Fbm,
+    Billow,
+    BasicMulti,
+    HybridMulti,
+    RidgedMulti { attenuation: f64 },
+}
+
+/// The most octaves the fractals of the `noise` crate support.
+pub const MAX_OCTAVES: usize = 32;
+
+impl<Source> Fractal<Source> {
+    /// Creates a fractal with one octave per source.
+    pub fn new(kind: FractalKind, sources: Vec<Source>) -> Self {
+        Self {
+            kind,
+            sources,
+            frequency: 1.0,
+            lacunarity: std::f64::consts::PI * 2.0 / 3.0,
+            persistence: 0.5,
+        }
+    }
+
+    pub fn set_frequency(self, frequency: f64) -> Self {
+        Self { frequency, ..self }
+    }
+
+    pub fn set_lacunarity(self, lacunarity: f64) -> Self {
+        Self { lacunarity, ..self }
+    }
+
+    pub fn set_persistence(self, persistence: f64) -> Self {
+        Self {
+            persistence,
+            ..self
+        }
+    }
+}
+
+impl<Source> NoiseFn<f64, 2> for Fractal<Source>
+where
+    Source: NoiseFn<f64, 2>,
+{
+    fn get(&self, point: [f64; 2]) -> f64 {
+        let mut point = point.map(|x| x * self.frequency);
+        let signals = self.sources.iter().map(|source| {
+            let signal = source.get(point);
+            point = point.map(|x| x * self.lacunarity);
+            signal
+        });
+
+        self.kind.combine(self.persistence, signals)
+    }
+}
+
+/// The number of octaves the fractals of the `noise` crate end up with when
+/// asked for `octaves`.
+pub fn clamp_octaves(octaves: usize) -> usize {
+    octaves.clamp(1, MAX_OCTAVES)
+}
+
+impl FractalKind {
+    /// Combines the values of the octaves into the value of the fractal.
+    pub fn combine(self, persistence: f64, signals: impl ExactSizeIterator<Item = f64>) -> f64 {
+        let octaves = signals.len();
+
+        match self {
+            FractalKind::Fbm | FractalKind::Billow => {
+                let mut result = 0.0;
+                for (x, mut signal) in signals.enumerate() {
+                    if self == FractalKind::Billow {
+                        signal = scale_shift(signal, 2.0);
+                    }
+                    signal *= persistence.powi(x as i32);
+                    result += signal;
+                }
+                result / (1.0 - persistence.powi(octaves as i32))
+            }
+            FractalKind::BasicMulti => {
+                let mut result = 0.0;
+                for (x, mut signal) in signals.enumerate() {
+                    if x == 0 {
+                        result = signal;
+                        continue;
+                    }
+                    signal *= persistence.powi(x as i32);
+                    signal *= result;
+                    result += signal;
+                }
+                result * 0.5
+            }
+            FractalKind::HybridMulti => {
+                let mut result = 0.0;
+                let mut weight = 0.0;
+                for (x, mut signal) in signals.enumerate() {
+                    if x == 0 {
+                        result = signal * persistence;
+                        weight = result;
+                        continue;
+                    }
+                    weight = f64::max(weight, 1.0);
+                    signal *= persistence.powi(x as i32);
+                    result += weight * signal;
+                    weight *= signal;
+                }
+                result * 3.0
+            }
+            FractalKind::RidgedMulti { attenuation } => {
+                let mut result = 0.0;
+                let mut weight = 1.0;
+                for (x, mut signal) in signals.enumerate() {
+                    signal = 1.0 - signal.abs();
+                    signal *= signal;
+                    signal *= weight;
+                    weight = (signal / attenuation).clamp(0.0, 1.0);
+                    signal *= persistence.powi(x as i32);
+                    result += signal;
+                }
+                let scale = 2.0 - 0.5_f64.powi(octaves as i32 - 1);
+                scale_shift(result, 2.0 / scale)
+            }
+        }
+    }
+}
+
+fn scale_shift(value: f64, n: f64) -> f64 {
+    value.abs().mul_add(n, -1.0)
+}
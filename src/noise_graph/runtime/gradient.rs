@@ -0,0 +1,29 @@
+use noise::NoiseFn;
+
+use super::dyn_fn::VectorFn;
+
+/// The gradient of a noise function, which points uphill and grows with the
+/// slope. It's estimated from the values `step` away on either side of the
+/// point.
+#[derive(Clone)]
+pub struct Gradient<T> {
+    source: T,
+    step: f64,
+}
+
+impl<T> Gradient<T> {
+    pub fn new(source: T, step: f64) -> Self {
+        Self { source, step }
+    }
+}
+
+impl<T: NoiseFn<f64, 2>> VectorFn for Gradient<T> {
+    fn get(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let step = self.step;
+        let scale = 0.5 / step;
+        [
+            (self.source.get([x + step, y]) - self.source.get([x - step, y])) * scale,
+            (self.source.get([x, y + step]) - self.source.get([x, y - step])) * scale,
+        ]
+    }
+}
@@ -0,0 +1,31 @@
+//! The noise functions the nodes evaluate to, besides the ones of the noise
+//! crate. They only depend on std, the noise crate and each other, so that
+//! exported Rust code can include them as they are.
+
+pub mod cache;
+pub mod curl;
+pub mod domain_warp;
+pub mod dyn_fn;
+pub mod eroded_fbm;
+pub mod fractal;
+pub mod gradient;
+pub mod scale;
+pub mod shape;
+pub mod shaping;
+pub mod vector;
+pub mod worley;
+
+pub use self::{
+    cache::SyncCache,
+    curl::Curl,
+    domain_warp::{DomainWarp, MAX_WARP_ITERATIONS},
+    dyn_fn::{DynNoiseFn, DynVectorFn, VectorFn},
+    eroded_fbm::ErodedFbm,
+    fractal::{clamp_octaves, Fractal, FractalKind, MAX_OCTAVES},
+    gradient::Gradient,
+    scale::Scale,
+    shape::Shape,
+    shaping::{Shaped, Shaping, MAX_SOURCES},
+    vector::Vector,
+    worley::{DistanceFunction, SyncWorley},
+};
@@ -0,0 +1,18 @@
+use noise::NoiseFn;
+
+pub struct Scale<Source> {
+    source: Source,
+    scale: f64,
+}
+
+impl<Source> Scale<Source> {
+    pub fn new(source: Source, scale: f64) -> Self {
+        Self { source, scale }
+    }
+}
+
+impl<Source: NoiseFn<f64, 2>> NoiseFn<f64, 2> for Scale<Source> {
+    fn get(&self, [x, y]: [f64; 2]) -> f64 {
+        self.source.get([x / self.scale, y / self.scale]) * self.scale / 2.0
+    }
+}
@@ -0,0 +1,83 @@
+use noise::NoiseFn;
+
+/// Noise functions of the sample point alone, which lay out the terrain
+/// instead of adding detail to it, e.g. as the mask of a continent. Distances
+/// are measured in the units of the sample point and gradients range from 0 to
+/// 1, raised to the power of their falloff.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    X,
+    Y,
+    DistanceToPoint {
+        point: [f64; 2],
+    },
+    /// The distance to the closest point of the segment from `start` to `end`.
+    DistanceToLine {
+        start: [f64; 2],
+        end: [f64; 2],
+    },
+    /// 1 at the center, falling to 0 at the radius and beyond.
+    RadialGradient {
+        center: [f64; 2],
+        radius: f64,
+        falloff: f64,
+    },
+    /// 0 at `start`, rising to 1 at `end` along the line through both and
+    /// constant beyond them.
+    LinearGradient {
+        start: [f64; 2],
+        end: [f64; 2],
+        falloff: f64,
+    },
+}
+
+impl NoiseFn<f64, 2> for Shape {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        match *self {
+            Shape::X => point[0],
+            Shape::Y => point[1],
+            Shape::DistanceToPoint { point: to } => distance(point, to),
+            Shape::DistanceToLine { start, end } => {
+                let along = projection(point, start, end).clamp(0.0, 1.0);
+                let closest = [
+                    along.mul_add(end[0] - start[0], start[0]),
+                    along.mul_add(end[1] - start[1], start[1]),
+                ];
+                distance(point, closest)
+            }
+            Shape::RadialGradient {
+                center,
+                radius,
+                falloff,
+            } => {
+                // A gradient without a radius has no inside.
+                if radius <= 0.0 {
+                    return 0.0;
+                }
+                let gradient = (1.0 - distance(point, center) / radius).max(0.0);
+                gradient.powf(falloff)
+            }
+            Shape::LinearGradient {
+                start,
+                end,
+                falloff,
+            } => projection(point, start, end).clamp(0.0, 1.0).powf(falloff),
+        }
+    }
+}
+
+fn distance([x1, y1]: [f64; 2], [x2, y2]: [f64; 2]) -> f64 {
+    (x2 - x1).hypot(y2 - y1)
+}
+
+/// How far the point is along the line from `start` to `end`, where `start` is
+/// at 0 and `end` at 1. Points are at `start` if both ends are the same.
+fn projection([x, y]: [f64; 2], start: [f64; 2], end: [f64; 2]) -> f64 {
+    let direction = [end[0] - start[0], end[1] - start[1]];
+    let length_squared = direction[0].mul_add(direction[0], direction[1] * direction[1]);
+    if length_squared == 0.0 {
+        return 0.0;
+    }
+
+    (x - start[0]).mul_add(direction[0], (y - start[1]) * direction[1]) / length_squared
+}
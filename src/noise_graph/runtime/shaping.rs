@@ -0,0 +1,133 @@
+use noise::NoiseFn;
+
+use super::dyn_fn::DynNoiseFn;
+
+/// The most sources a [`Shaping`] function takes.
+pub const MAX_SOURCES: usize = 5;
+
+/// Shapes the values of noise with one of the [`Shaping`] functions. Their
+/// parameters are sources too, so they can vary across the terrain, constant
+/// parameters are `Constant` sources.
+#[derive(Clone)]
+pub struct Shaped {
+    shaping: Shaping,
+    sources: Vec<DynNoiseFn>,
+}
+
+/// The functions of the shaping nodes, which take their sources in the order
+/// of the node's inputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shaping {
+    /// Maps `[from min, from max]` linearly to `[to min, to max]`.
+    Remap,
+    /// Hermite interpolation from 0 at the lower edge to 1 at the upper edge.
+    Smoothstep,
+    /// Interpolates linearly from the first to the second source.
+    Lerp,
+    /// 0 below the threshold and 1 from the threshold on.
+    Step,
+    /// Rounds down to a multiple of the step size, a step size of 0 keeps the
+    /// value as it is.
+    Quantize,
+    Sin,
+    Cos,
+    /// The remainder of dividing by the divisor, which always has the sign of
+    /// the divisor. A divisor of 0 keeps the value as it is.
+    Modulo,
+    // The blend modes of image editors, with noise values mapped from
+    // [-1, 1] to the [0, 1] range of colors and back.
+    Overlay,
+    Screen,
+    SoftLight,
+}
+
+impl Shaped {
+    pub fn new(shaping: Shaping, sources: Vec<DynNoiseFn>) -> Self {
+        assert_eq!(
+            shaping.sources(),
+            sources.len(),
+            "Expected {} sources for {shaping:?}",
+            shaping.sources()
+        );
+        Self { shaping, sources }
+    }
+}
+
+impl NoiseFn<f64, 2> for Shaped {
+    fn get(&self, point: [f64; 2]) -> f64 {
+        let mut values = [0.0; MAX_SOURCES];
+        for (value, source) in values.iter_mut().zip(&self.sources) {
+            *value = source.get(point);
+        }
+
+        self.shaping.apply(&values[..self.sources.len()])
+    }
+}
+
+impl Shaping {
+    /// The number of sources, including the parameters.
+    pub fn sources(self) -> usize {
+        match self {
+            Shaping::Step | Shaping::Quantize | Shaping::Modulo => 2,
+            Shaping::Overlay | Shaping::Screen | Shaping::SoftLight => 2,
+            Shaping::Smoothstep | Shaping::Lerp | Shaping::Sin | Shaping::Cos => 3,
+            Shaping::Remap => 5,
+        }
+    }
+
+    pub fn apply(self, values: &[f64]) -> f64 {
+        let value = values[0];
+
+        match self {
+            Shaping::Remap => {
+                let [from_min, from_max, to_min, to_max] =
+                    [values[1], values[2], values[3], values[4]];
+                let alpha = (value - from_min) / (from_max - from_min);
+                alpha.mul_add(to_max - to_min, to_min)
+            }
+            Shaping::Smoothstep => {
+                let (lower, upper) = (values[1], values[2]);
+                let t = ((value - lower) / (upper - lower)).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            }
+            Shaping::Lerp => values[2].mul_add(values[1] - value, value),
+            Shaping::Step => {
+                if value < values[1] {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Shaping::Quantize => {
+                let step = values[1];
+                if step == 0.0 {
+                    value
+                } else {
+                    (value / step).floor() * step
+                }
+            }
+            Shaping::Sin => value.mul_add(values[1], values[2]).sin(),
+            Shaping::Cos => value.mul_add(values[1], values[2]).cos(),
+            Shaping::Modulo => {
+                let divisor = values[1];
+                if divisor == 0.0 {
+                    value
+                } else {
+                    value - divisor * (value / divisor).floor()
+                }
+            }
+            Shaping::Overlay | Shaping::Screen | Shaping::SoftLight => {
+                let base = (value + 1.0) / 2.0;
+                let blend = (values[1] + 1.0) / 2.0;
+                let color = match self {
+                    Shaping::Overlay if base < 0.5 => 2.0 * base * blend,
+                    Shaping::Overlay => 1.0 - 2.0 * (1.0 - base) * (1.0 - blend),
+                    Shaping::Screen => 1.0 - (1.0 - base) * (1.0 - blend),
+                    // The formula of Pegtop, which has no discontinuity.
+                    _ => (1.0 - 2.0 * blend) * base * base + 2.0 * blend * base,
+                };
+                color.mul_add(2.0, -1.0)
+            }
+        }
+    }
+}
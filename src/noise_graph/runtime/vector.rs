@@ -0,0 +1,26 @@
+use noise::NoiseFn;
+
+use super::dyn_fn::VectorFn;
+
+/// A vector field with the values of two noise functions as its components.
+#[derive(Clone)]
+pub struct Vector<X, Y> {
+    x: X,
+    y: Y,
+}
+
+impl<X, Y> Vector<X, Y> {
+    pub fn new(x: X, y: Y) -> Self {
+        Self { x, y }
+    }
+}
+
+impl<X, Y> VectorFn for Vector<X, Y>
+where
+    X: NoiseFn<f64, 2>,
+    Y: NoiseFn<f64, 2>,
+{
+    fn get(&self, point: [f64; 2]) -> [f64; 2] {
+        [self.x.get(point), self.y.get(point)]
+    }
+}
@@ -0,0 +1,83 @@
+use noise::{
+    core::worley::{distance_functions, worley_2d, ReturnType},
+    permutationtable::PermutationTable,
+    NoiseFn, Seedable,
+};
+
+#[derive(Clone)]
+pub struct SyncWorley {
+    distance_function: DistanceFunction,
+    return_type: ReturnType,
+    seed: u32,
+    perm_table: PermutationTable,
+}
+
+pub type DistanceFunction = fn(&[f64], &[f64]) -> f64;
+
+impl SyncWorley {
+    pub const _DEFAULT_SEED: u32 = 0;
+
+    pub fn new(seed: u32) -> Self {
+        Self {
+            perm_table: PermutationTable::new(seed),
+            seed,
+            distance_function: distance_functions::euclidean,
+            return_type: ReturnType::Value,
+        }
+    }
+
+    /// Enables or disables applying the distance from the nearest seed point
+    /// to the output value.
+    pub fn set_return_type(self, return_type: ReturnType) -> Self {
+        Self {
+            return_type,
+            ..self
+        }
+    }
+
+    /// Sets the function used to measure the distance to the seed points.
+    pub fn set_distance_function(self, distance_function: DistanceFunction) -> Self {
+        Self {
+            distance_function,
+            ..self
+        }
+    }
+}
+
+impl Default for SyncWorley {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Seedable for SyncWorley {
+    /// Sets the seed value used by the Worley cells.
+    fn set_seed(self, seed: u32) -> Self {
+        // If the new seed is the same as the current seed, just return self.
+        if self.seed == seed {
+            return self;
+        }
+
+        // Otherwise, regenerate the permutation table based on the new seed.
+        Self {
+            perm_table: PermutationTable::new(seed),
+            seed,
+            ..self
+        }
+    }
+
+    fn seed(&self) -> u32 {
+        self.seed
+    }
+}
+
+impl NoiseFn<f64, 2> for SyncWorley {
+    fn get(&self, [x, y]: [f64; 2]) -> f64 {
+        worley_2d(
+            &self.perm_table,
+            self.distance_function,
+            self.return_type,
+            [x, y],
+        )
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy_egui::egui::{self, Button, Color32, DragValue, Grid, Label, Sense, Stroke};
+use egui_node_graph::{InputId, NodeId};
+use futures_lite::future;
+use noise::NoiseFn;
+
+use super::{
+    curve_editor::Histogram,
+    graph_ext::{EvaluationCache, GraphExt},
+    node_attribute::NodeAttribute,
+    DynNoiseFn, MyResponse, NoiseGraph,
+};
+
+/// The part of the world the output of a node is sampled over when it is
+/// analysed, and how many samples are taken along each axis.
+#[derive(Clone, Copy, PartialEq)]
+struct AnalysisRegion {
+    center: [f64; 2],
+    half_extent: f64,
+    resolution: usize,
+}
+
+impl Default for AnalysisRegion {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0],
+            half_extent: 2048.0,
+            resolution: 256,
+        }
+    }
+}
+
+/// How the values of a node's output are distributed over the region.
+struct Statistics {
+    min: f64,
+    max: f64,
+    mean: f64,
+    /// The values below which the given fraction of the samples lie.
+    percentiles: Vec<(f64, f64)>,
+    histogram: Histogram,
+    /// Samples which were infinite or NaN, they are left out of everything
+    /// else.
+    non_finite: usize,
+}
+
+/// The analyses of the nodes they were asked for, which show the range of a
+/// node's output in order to tune the parameters depending on it.
+#[derive(Default)]
+pub struct NodeStatistics {
+    region: AnalysisRegion,
+    entries: HashMap<NodeId, Analysis>,
+}
+
+struct Analysis {
+    statistics: Option<Statistics>,
+    task: Option<Task<Option<Statistics>>>,
+    error: Option<String>,
+    stale: bool,
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Self {
+            statistics: None,
+            task: None,
+            error: None,
+            stale: true,
+        }
+    }
+}
+
+impl NodeStatistics {
+    const PERCENTILES: [f64; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+    const BINS: usize = 48;
+    const PLOT_SIZE: egui::Vec2 = egui::vec2(220.0, 80.0);
+
+    pub fn is_open(&self, node_id: NodeId) -> bool {
+        self.entries.contains_key(&node_id)
+    }
+
+    /// Opens the analysis of a node, or closes it if it's open.
+    pub fn toggle(&mut self, node_id: NodeId) {
+        if self.entries.remove(&node_id).is_none() {
+            self.entries.insert(node_id, Analysis::default());
+        }
+    }
+
+    /// Shows the analysis of a node if it's open, sampling the output first
+    /// if the analysis is missing or outdated. Sampling happens on the async
+    /// compute pool, so the results show up a few frames later. Every value
+    /// can be copied into the number inputs of the nodes the output is
+    /// connected to.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        graph: &NoiseGraph,
+        evaluation_cache: &mut EvaluationCache,
+    ) -> Vec<MyResponse> {
+        if !self.is_open(node_id) {
+            return Vec::new();
+        }
+
+        let previous = self.region;
+        let region = &mut self.region;
+        Grid::new(("NodeStatistics.region", node_id)).show(ui, |ui| {
+            ui.label("center");
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut region.center[0]));
+                ui.add(DragValue::new(&mut region.center[1]));
+            });
+            ui.end_row();
+
+            ui.label("half extent");
+            ui.add(DragValue::new(&mut region.half_extent).clamp_range(1.0..=f64::MAX));
+            ui.end_row();
+
+            ui.label("samples")
+                .on_hover_text("The number of samples along each side of the region");
+            ui.add(DragValue::new(&mut region.resolution).clamp_range(2..=1024));
+            ui.end_row();
+        });
+        if self.region != previous {
+            self.invalidate_all();
+        }
+
+        let region = self.region;
+        let analysis = self.entries.entry(node_id).or_default();
+
+        if let Some(task) = &mut analysis.task {
+            if let Some(statistics) = future::block_on(future::poll_once(task)) {
+                analysis.statistics = statistics;
+                analysis.task = None;
+            }
+        }
+
+        if analysis.stale && analysis.task.is_none() {
+            analysis.stale = false;
+
+            match graph
+                .evaluate_cached(node_id, evaluation_cache)
+                .and_then(NodeAttribute::try_to_noise_function)
+            {
+                Ok(noise) => {
+                    analysis.error = None;
+                    analysis.task = Some(
+                        AsyncComputeTaskPool::get().spawn(async move { statistics(noise, region) }),
+                    );
+                }
+                Err(e) => {
+                    analysis.error = Some(e.to_string());
+                    analysis.statistics = None;
+                }
+            }
+        }
+
+        if let Some(error) = &analysis.error {
+            ui.colored_label(Color32::LIGHT_RED, error);
+            return Vec::new();
+        }
+        match &analysis.statistics {
+            Some(statistics) => show_statistics(ui, node_id, graph, statistics),
+            None if analysis.task.is_some() => {
+                ui.spinner();
+                Vec::new()
+            }
+            None => {
+                ui.colored_label(Color32::LIGHT_RED, "The output has no finite values");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Marks every analysis as outdated, so it is sampled again the next
+    /// time it is drawn.
+    pub fn invalidate_all(&mut self) {
+        self.entries
+            .values_mut()
+            .for_each(|analysis| analysis.stale = true);
+    }
+
+    /// Marks the analyses of the given nodes as outdated.
+    pub fn invalidate(&mut self, nodes: &HashSet<NodeId>) {
+        for node_id in nodes {
+            if let Some(analysis) = self.entries.get_mut(node_id) {
+                analysis.stale = true;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, node_id: NodeId) {
+        self.entries.remove(&node_id);
+    }
+}
+
+/// Shows the values with a plot of the histogram below them. Clicking a
+/// value copies it to the clipboard, its arrow menu sets it as the value of a
+/// downstream input.
+fn show_statistics(
+    ui: &mut egui::Ui,
+    node_id: NodeId,
+    graph: &NoiseGraph,
+    statistics: &Statistics,
+) -> Vec<MyResponse> {
+    let mut responses = Vec::new();
+    let targets = targets(graph, node_id);
+
+    let mut rows = vec![
+        ("min".to_string(), statistics.min),
+        ("max".to_string(), statistics.max),
+        ("mean".to_string(), statistics.mean),
+    ];
+    rows.extend(statistics.percentiles.iter().map(|(fraction, value)| {
+        (
+            format!("{}th percentile", (fraction * 100.0).round()),
+            *value,
+        )
+    }));
+
+    Grid::new(("NodeStatistics.values", node_id)).show(ui, |ui| {
+        for (label, value) in rows {
+            ui.label(label);
+            let text = format!("{value:.4}");
+            if ui
+                .add(Label::new(text).sense(Sense::click()))
+                .on_hover_text("Click to copy")
+                .clicked()
+            {
+                ui.output().copied_text = value.to_string();
+            }
+            if !targets.is_empty() {
+                ui.menu_button("➡", |ui| {
+                    for (input_id, name) in &targets {
+                        let check = graph.check_input(*input_id, &NodeAttribute::F64(value));
+                        let button = ui.add_enabled(check.is_ok(), Button::new(name));
+                        let button = match check {
+                            Ok(_) => button,
+                            Err(e) => button.on_disabled_hover_text(e.to_string()),
+                        };
+                        if button.clicked() {
+                            responses.push(MyResponse::SetNumber(*input_id, value));
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
+            ui.end_row();
+        }
+    });
+
+    if statistics.non_finite > 0 {
+        ui.colored_label(
+            Color32::LIGHT_RED,
+            format!("{} samples are not finite", statistics.non_finite),
+        );
+    }
+
+    let (rect, _) = ui.allocate_exact_size(NodeStatistics::PLOT_SIZE, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let range = (statistics.max - statistics.min).max(f64::EPSILON);
+    let screen_x = |x: f64| rect.left() + rect.width() * ((x - statistics.min) / range) as f32;
+    statistics
+        .histogram
+        .paint(&painter, rect, screen_x, Color32::from_rgb(100, 180, 255));
+    let weak = Stroke::new(1.0, ui.visuals().weak_text_color());
+    for (_, value) in &statistics.percentiles {
+        painter.vline(screen_x(*value), rect.y_range(), weak);
+    }
+    painter.vline(
+        screen_x(statistics.mean),
+        rect.y_range(),
+        Stroke::new(1.0, Color32::GOLD),
+    );
+
+    responses
+}
+
+/// The unconnected number inputs of the nodes the output of a node is
+/// connected to, with labels naming the node and the input.
+fn targets(graph: &NoiseGraph, node_id: NodeId) -> Vec<(InputId, String)> {
+    let mut downstream: Vec<NodeId> = graph
+        .iter_connections()
+        .filter(|(_, output_id)| graph[*output_id].node == node_id)
+        .map(|(input_id, _)| graph[input_id].node)
+        .collect();
+    downstream.sort();
+    downstream.dedup();
+
+    downstream
+        .into_iter()
+        .flat_map(|target| {
+            graph[target]
+                .inputs
+                .iter()
+                .filter(|(_, input_id)| {
+                    graph.connection(*input_id).is_none()
+                        && matches!(graph[*input_id].value(), NodeAttribute::F64(_))
+                })
+                .map(move |(name, input_id)| {
+                    (*input_id, format!("{}: {name}", graph.node_name(target)))
+                })
+        })
+        .collect()
+}
+
+/// Samples the noise over the region. There are no statistics if the noise
+/// has no finite values.
+fn statistics(noise: DynNoiseFn, region: AnalysisRegion) -> Option<Statistics> {
+    let resolution = region.resolution.max(2);
+    let [center_x, center_y] = region.center;
+    let step = 2.0 * region.half_extent / (resolution - 1) as f64;
+
+    let mut values = Vec::with_capacity(resolution * resolution);
+    let mut non_finite = 0;
+    for y in 0..resolution {
+        for x in 0..resolution {
+            let value = noise.get([
+                center_x - region.half_extent + x as f64 * step,
+                center_y - region.half_extent + y as f64 * step,
+            ]);
+            if value.is_finite() {
+                values.push(value);
+            } else {
+                non_finite += 1;
+            }
+        }
+    }
+
+    let histogram = Histogram::new(&values, NodeStatistics::BINS)?;
+    values.sort_by(f64::total_cmp);
+    let percentiles = NodeStatistics::PERCENTILES
+        .iter()
+        .map(|fraction| {
+            let index = ((values.len() - 1) as f64 * fraction).round() as usize;
+            (*fraction, values[index])
+        })
+        .collect();
+
+    Some(Statistics {
+        min: histogram.min,
+        max: histogram.max,
+        mean: values.iter().sum::<f64>() / values.len() as f64,
+        percentiles,
+        histogram,
+        non_finite,
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,74 @@
+use super::{statistics, targets, AnalysisRegion};
+use crate::noise_graph::{
+    dsl,
+    graph_ext::{EvaluationCache, GraphExt},
+    DynNoiseFn, NoiseGraphResource,
+};
+
+/// A region of 3 by 3 samples at -10, 0 and 10 along each axis.
+const REGION: AnalysisRegion = AnalysisRegion {
+    center: [0.0, 0.0],
+    half_extent: 10.0,
+    resolution: 3,
+};
+
+fn noise(resource: &NoiseGraphResource, node_name: &str) -> DynNoiseFn {
+    let graph = &resource.state.graph;
+    let node_id = graph
+        .iter_nodes()
+        .find(|node_id| graph.node_name(*node_id) == node_name)
+        .unwrap();
+    graph
+        .evaluate_cached(node_id, &mut EvaluationCache::default())
+        .and_then(|value| value.try_to_noise_function())
+        .unwrap()
+}
+
+#[test]
+fn statistics_describe_the_values_over_the_region() {
+    let resource = dsl::parse("out = x();").unwrap();
+    let statistics = statistics(noise(&resource, "out"), REGION).unwrap();
+
+    assert_eq!((statistics.min, statistics.max), (-10.0, 10.0));
+    assert_eq!(statistics.mean, 0.0);
+    assert_eq!(
+        statistics.percentiles,
+        [
+            (0.05, -10.0),
+            (0.25, -10.0),
+            (0.5, 0.0),
+            (0.75, 10.0),
+            (0.95, 10.0)
+        ]
+    );
+    assert_eq!(statistics.histogram.bins.iter().sum::<usize>(), 9);
+    assert_eq!(statistics.non_finite, 0);
+}
+
+#[test]
+fn non_finite_values_are_counted_apart() {
+    let resource = dsl::parse("out = expression(\"1 / x\");").unwrap();
+    let reciprocal = statistics(noise(&resource, "out"), REGION).unwrap();
+
+    assert_eq!(reciprocal.non_finite, 3);
+    assert_eq!((reciprocal.min, reciprocal.max), (-0.1, 0.1));
+
+    let resource = dsl::parse("out = expression(\"0 / 0\");").unwrap();
+    assert!(statistics(noise(&resource, "out"), REGION).is_none());
+}
+
+#[test]
+fn targets_are_the_unconnected_numbers_of_the_nodes_downstream() {
+    let resource = dsl::parse("hills = perlin();\nout = scale(hills, scale: 2);").unwrap();
+    let graph = &resource.state.graph;
+    let hills = graph
+        .iter_nodes()
+        .find(|node_id| graph.node_name(*node_id) == "hills")
+        .unwrap();
+
+    let labels: Vec<String> = targets(graph, hills)
+        .into_iter()
+        .map(|(_, label)| label)
+        .collect();
+    assert_eq!(labels, ["out: scale"]);
+}
@@ -0,0 +1,183 @@
+use bevy::tasks::{AsyncComputeTaskPool, TaskPool};
+use bevy_egui::egui;
+use egui_node_graph::{AnyParameterId, NodeId, NodeResponse};
+use noise::{NoiseFn, Perlin};
+
+use super::{
+    changes_graph, channel_outputs, dsl, graph_ext::GraphExt, node_attribute::NodeAttribute,
+    MyResponse, NoiseGraph, NoiseGraphResource,
+};
+
+const POINTS: [[f64; 2]; 4] = [[0.0, 0.0], [0.4, -0.7], [37.7, 29.2], [-186.1, 90.3]];
+
+const CHANNELS: &str = "
+    mountains = perlin(seed: 1);
+    moisture = output(perlin(seed: 2));
+    height = output(mountains);
+    alpha = output(perlin(seed: 3));
+    out = billow();";
+
+#[test]
+fn output_nodes_define_the_terrain_channels() {
+    let mut resource = dsl::parse(CHANNELS).unwrap();
+    resource.update_current_noise();
+
+    let height = resource.terrain_height();
+    let channels = resource.terrain_channels();
+    assert_eq!(channels.len(), 2);
+    for point in POINTS {
+        assert_eq!(height.get(point), Perlin::new(1).get(point));
+        // Sorted by name, "alpha" comes first.
+        assert_eq!(channels[0].get(point), Perlin::new(3).get(point));
+        assert_eq!(channels[1].get(point), Perlin::new(2).get(point));
+    }
+}
+
+#[test]
+fn the_first_output_with_a_name_defines_the_channel() {
+    let mut resource = dsl::parse(CHANNELS).unwrap();
+    let graph = &mut resource.state.graph;
+    let alpha = graph
+        .iter_nodes()
+        .find(|node_id| graph.node_name(*node_id) == "alpha")
+        .unwrap();
+    let name = graph[alpha].get_input("name").unwrap();
+    graph[name].value = NodeAttribute::Name("moisture".into());
+
+    let outputs = channel_outputs(graph);
+    let names: Vec<&str> = outputs.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["height", "moisture"]);
+    assert_ne!(outputs[1].1, alpha);
+}
+
+#[test]
+fn the_active_node_shapes_the_terrain_without_a_height_channel() {
+    let mut resource =
+        dsl::parse("moisture = output(perlin(seed: 2)); out = perlin(seed: 4);").unwrap();
+    resource.update_current_noise();
+
+    for point in POINTS {
+        assert_eq!(
+            resource.terrain_height().get(point),
+            Perlin::new(4).get(point)
+        );
+    }
+}
+
+fn find_node(graph: &NoiseGraph, name: &str) -> NodeId {
+    graph
+        .iter_nodes()
+        .find(|node_id| graph.node_name(*node_id) == name)
+        .unwrap_or_else(|| panic!("No node '{name}'"))
+}
+
+const CONNECTED: &str = "a = fbm(source: perlin());\nb = scale(a, scale: 2);\nc = perlin();";
+
+#[test]
+fn connections_creating_a_cycle_are_taken_back() {
+    let mut resource = dsl::parse(CONNECTED).unwrap();
+    let graph = &mut resource.state.graph;
+    let (a, b) = (find_node(graph, "a"), find_node(graph, "b"));
+    let source = graph[a].get_input("source").unwrap();
+    let previous = graph.connection(source).unwrap();
+    let b_out = graph[b].get_output("out").unwrap();
+
+    // The editor replaces the connection of the input before responding.
+    resource.user_state.connections_before_drag = graph.iter_connections().collect();
+    graph.add_connection(b_out, source);
+    resource.end_connection(b_out, source);
+
+    assert!(resource.state.graph.connection(source) == Some(previous));
+    assert_eq!(
+        resource.user_state.editor_error.as_deref(),
+        Some("Can't connect 'b' to 'a', it would create a cycle")
+    );
+    assert!(!resource.file.unsaved_changes);
+}
+
+#[test]
+fn accepted_connections_are_unsaved_changes() {
+    let mut resource = dsl::parse(CONNECTED).unwrap();
+    let graph = &mut resource.state.graph;
+    let (a, c) = (find_node(graph, "a"), find_node(graph, "c"));
+    let source = graph[a].get_input("source").unwrap();
+    let c_out = graph[c].get_output("out").unwrap();
+
+    resource.user_state.connections_before_drag = graph.iter_connections().collect();
+    graph.add_connection(c_out, source);
+    resource.end_connection(c_out, source);
+
+    assert!(resource.state.graph.connection(source) == Some(c_out));
+    assert!(resource.user_state.editor_error.is_none());
+    assert!(resource.file.unsaved_changes);
+}
+
+#[test]
+fn only_responses_changing_the_saved_graph_are_unsaved_changes() {
+    let resource = dsl::parse(CONNECTED).unwrap();
+    let graph = &resource.state.graph;
+    let (a, c) = (find_node(graph, "a"), find_node(graph, "c"));
+    let input = graph[a].get_input("source").unwrap();
+    let output = graph[c].get_output("out").unwrap();
+
+    let changing = [
+        NodeResponse::DisconnectEvent { input, output },
+        NodeResponse::CreatedNode(a),
+        NodeResponse::MoveNode {
+            node: a,
+            drag_delta: Default::default(),
+        },
+        NodeResponse::User(MyResponse::SetActiveNode(a)),
+        NodeResponse::User(MyResponse::ClearActiveNode),
+        NodeResponse::User(MyResponse::InputChanged(a)),
+        NodeResponse::User(MyResponse::ParametersChanged),
+        NodeResponse::User(MyResponse::SetNumber(input, 1.0)),
+    ];
+    let unchanging = [
+        // Connections count once they are accepted.
+        NodeResponse::ConnectEventEnded { input, output },
+        NodeResponse::SelectNode(a),
+        NodeResponse::RaiseNode(a),
+        NodeResponse::DeleteNodeUi(a),
+        NodeResponse::User(MyResponse::SaveImage),
+        NodeResponse::User(MyResponse::ExportRust),
+    ];
+
+    for (index, response) in changing.iter().enumerate() {
+        assert!(changes_graph(response), "changing response {index}");
+    }
+    for (index, response) in unchanging.iter().enumerate() {
+        assert!(!changes_graph(response), "unchanging response {index}");
+    }
+}
+
+/// Draws the editor for one frame.
+fn draw(resource: &mut NoiseGraphResource) {
+    AsyncComputeTaskPool::init(TaskPool::new);
+    let _ = egui::Context::default().run(egui::RawInput::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| ui.add(&mut *resource));
+    });
+}
+
+#[test]
+fn the_graph_is_copied_once_per_drag() {
+    let mut resource = dsl::parse(CONNECTED).unwrap();
+    let graph = &resource.state.graph;
+    let c = find_node(graph, "c");
+    let c_out = graph[c].get_output("out").unwrap();
+    let seed = graph[c].get_input("seed").unwrap();
+
+    resource.state.connection_in_progress = Some((c, AnyParameterId::Output(c_out)));
+    draw(&mut resource);
+    assert!(resource.user_state.graph_before_drag.is_some());
+
+    // A copy taken again would see the new value.
+    resource.state.graph[seed].value = NodeAttribute::Usize(7);
+    draw(&mut resource);
+    let copy = resource.user_state.graph_before_drag.as_ref().unwrap();
+    assert!(matches!(copy[seed].value, NodeAttribute::Usize(0)));
+
+    resource.state.connection_in_progress = None;
+    draw(&mut resource);
+    assert!(resource.user_state.graph_before_drag.is_none());
+}
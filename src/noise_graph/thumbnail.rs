@@ -0,0 +1,232 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy_egui::egui::{
+    self, Checkbox, Color32, ColorImage, DragValue, Grid, TextureFilter, TextureHandle, Widget,
+};
+use egui_node_graph::NodeId;
+use futures_lite::future;
+use noise::NoiseFn;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    graph_ext::{EvaluationCache, GraphExt},
+    DynNoiseFn, NoiseGraph, NoiseGraphResource,
+};
+
+/// Which part of the world is rendered into the node thumbnails, and at which
+/// resolution.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThumbnailConfig {
+    pub enabled: bool,
+    pub resolution: usize,
+    pub center: [f64; 2],
+    pub half_extent: f64,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: 64,
+            center: [0.0, 0.0],
+            half_extent: 2048.0,
+        }
+    }
+}
+
+/// The rendered thumbnails of all nodes, keyed by the node they belong to.
+#[derive(Default)]
+pub struct Thumbnails {
+    entries: HashMap<NodeId, Thumbnail>,
+}
+
+struct Thumbnail {
+    texture: Option<TextureHandle>,
+    task: Option<Task<ColorImage>>,
+    error: Option<String>,
+    stale: bool,
+}
+
+impl Default for Thumbnail {
+    fn default() -> Self {
+        Self {
+            texture: None,
+            task: None,
+            error: None,
+            stale: true,
+        }
+    }
+}
+
+impl Thumbnails {
+    const DISPLAY_SIZE: f32 = 128.0;
+
+    /// Draws the thumbnail of a node, rendering it first if it is missing or
+    /// outdated. Rendering happens on the async compute pool, so the image
+    /// shows up a few frames later.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        graph: &NoiseGraph,
+        evaluation_cache: &mut EvaluationCache,
+        config: ThumbnailConfig,
+    ) {
+        let thumbnail = self.entries.entry(node_id).or_default();
+
+        if let Some(task) = &mut thumbnail.task {
+            if let Some(image) = future::block_on(future::poll_once(task)) {
+                match &mut thumbnail.texture {
+                    Some(texture) => texture.set(image, TextureFilter::Linear),
+                    None => {
+                        thumbnail.texture = Some(ui.ctx().load_texture(
+                            format!("thumbnail {node_id:?}"),
+                            image,
+                            TextureFilter::Linear,
+                        ))
+                    }
+                }
+                thumbnail.task = None;
+            }
+        }
+
+        if thumbnail.stale && thumbnail.task.is_none() {
+            thumbnail.stale = false;
+
+            match graph
+                .evaluate_cached(node_id, evaluation_cache)
+                .and_then(|value| value.try_to_noise_function())
+            {
+                Ok(noise) => {
+                    thumbnail.error = None;
+                    thumbnail.task = Some(
+                        AsyncComputeTaskPool::get().spawn(async move { render(noise, config) }),
+                    );
+                }
+                Err(e) => {
+                    thumbnail.error = Some(e.to_string());
+                    thumbnail.texture = None;
+                }
+            }
+        }
+
+        if let Some(error) = &thumbnail.error {
+            ui.colored_label(Color32::LIGHT_RED, error);
+        } else if let Some(texture) = &thumbnail.texture {
+            ui.image(texture, egui::vec2(Self::DISPLAY_SIZE, Self::DISPLAY_SIZE));
+        } else {
+            ui.spinner();
+        }
+    }
+
+    /// Marks every thumbnail as outdated, so it gets rendered again the next
+    /// time it is drawn.
+    pub fn invalidate_all(&mut self) {
+        self.entries
+            .values_mut()
+            .for_each(|thumbnail| thumbnail.stale = true);
+    }
+
+    /// Marks the thumbnails of the given nodes as outdated.
+    pub fn invalidate(&mut self, nodes: &HashSet<NodeId>) {
+        for node_id in nodes {
+            if let Some(thumbnail) = self.entries.get_mut(node_id) {
+                thumbnail.stale = true;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, node_id: NodeId) {
+        self.entries.remove(&node_id);
+    }
+}
+
+/// Samples the noise over the configured window and maps its output range to
+/// grayscale, so nodes with very different output ranges stay readable.
+fn render(noise: DynNoiseFn, config: ThumbnailConfig) -> ColorImage {
+    let resolution = config.resolution.max(2);
+    let [center_x, center_y] = config.center;
+    let step = 2.0 * config.half_extent / (resolution - 1) as f64;
+
+    let mut values = Vec::with_capacity(resolution * resolution);
+    for y in 0..resolution {
+        for x in 0..resolution {
+            values.push(noise.get([
+                center_x - config.half_extent + x as f64 * step,
+                center_y - config.half_extent + y as f64 * step,
+            ]));
+        }
+    }
+
+    let (min, max) = values
+        .iter()
+        .filter(|value| value.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+    let range = max - min;
+
+    let pixels = values
+        .into_iter()
+        .map(|value| {
+            if !value.is_finite() {
+                Color32::RED
+            } else if range > f64::EPSILON {
+                Color32::from_gray(((value - min) / range * u8::MAX as f64) as u8)
+            } else {
+                Color32::GRAY
+            }
+        })
+        .collect();
+
+    ColorImage {
+        size: [resolution, resolution],
+        pixels,
+    }
+}
+
+/// Side panel settings for the node thumbnails.
+pub struct ThumbnailSettings<'a>(pub &'a mut NoiseGraphResource);
+
+impl<'a> Widget for ThumbnailSettings<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let graph = self.0;
+        let user_state = &mut graph.user_state;
+        let config = &mut user_state.thumbnail_config;
+        let previous = *config;
+
+        ui.heading("Thumbnails");
+        let response = Grid::new("ThumbnailSettings.grid")
+            .show(ui, |ui| {
+                ui.label("enabled");
+                ui.add(Checkbox::new(&mut config.enabled, ""));
+                ui.end_row();
+
+                ui.label("resolution");
+                ui.add(DragValue::new(&mut config.resolution).clamp_range(2..=256));
+                ui.end_row();
+
+                ui.label("center");
+                ui.horizontal(|ui| {
+                    ui.add(DragValue::new(&mut config.center[0]));
+                    ui.add(DragValue::new(&mut config.center[1]));
+                });
+                ui.end_row();
+
+                ui.label("half extent");
+                ui.add(DragValue::new(&mut config.half_extent).clamp_range(1.0..=f64::MAX));
+                ui.end_row();
+            })
+            .response;
+
+        if *config != previous {
+            user_state.thumbnails.invalidate_all();
+            user_state.source_histograms.invalidate_all();
+            graph.file.mark_changed();
+        }
+
+        response
+    }
+}
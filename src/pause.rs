@@ -2,7 +2,15 @@ use bevy::{prelude::*, window::CursorGrabMode};
 use bevy_egui::EguiContext;
 use bevy_inspector_egui::egui::{SidePanel, Window};
 
-use crate::{chunk::ChunksConfig, learn_shaders::ColorGradient, noise_graph::NoiseGraphResource};
+use crate::{
+    camera_controller::CameraController,
+    chunk::ChunksConfig,
+    learn_shaders::ColorGradient,
+    noise_graph::{
+        GraphFileMenu, GraphParameters, MapPreviewPanel, NoiseGraphResource, ProbePanel,
+        ThumbnailSettings,
+    },
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GameState {
@@ -27,15 +35,29 @@ fn draw_pause_menu(
     mut graph: ResMut<NoiseGraphResource>,
     mut color_gradient: ResMut<ColorGradient>,
     mut chunks_config: ResMut<ChunksConfig>,
+    camera: Query<&Transform, With<CameraController>>,
 ) {
+    let camera = camera.get_single().copied().unwrap_or_default();
+
     let ctx = context.ctx_mut();
 
     SidePanel::left("Side Panel").show(ctx, |ui| {
+        ui.add(GraphFileMenu(&mut graph));
+        ui.separator();
         ui.add(&mut *color_gradient);
         ui.separator();
         ui.add(&mut *chunks_config);
         ui.separator();
+        ui.add(GraphParameters(&mut graph));
+        ui.separator();
+        ui.add(ThumbnailSettings(&mut graph));
+        ui.separator();
+        ui.add(ProbePanel(&mut graph, camera.translation));
+        ui.separator();
     });
+    SidePanel::right("Map Preview")
+        .resizable(true)
+        .show(ctx, |ui| ui.add(MapPreviewPanel(&mut graph, camera)));
     Window::new("noise graph")
         .title_bar(false)
         .fixed_rect(ctx.available_rect())
@@ -22,11 +22,11 @@ pub struct Velocity(f32);
 #[derive(Component)]
 struct PreviousTranslation(Vec3);
 
+/// The player before it got its velocity.
+type NewPlayer = (Added<CameraController>, Without<Velocity>);
+
 impl Velocity {
-    fn add_to_player(
-        mut commands: Commands,
-        query: Query<(Entity, &Transform), (Added<CameraController>, Without<Velocity>)>,
-    ) {
+    fn add_to_player(mut commands: Commands, query: Query<(Entity, &Transform), NewPlayer>) {
         for (entity, transform) in &query {
             commands
                 .entity(entity)
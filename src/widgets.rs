@@ -26,9 +26,9 @@ where
             let list = self.0;
             let mut indices_to_remove = Vec::with_capacity(list.len());
 
-            for i in 0..list.len() {
+            for (i, item) in list.iter_mut().enumerate() {
                 ui.horizontal(|ui| {
-                    ui.add(&mut list[i]);
+                    ui.add(item);
                     if ui.button("x").clicked() {
                         indices_to_remove.push(i);
                     }